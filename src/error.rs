@@ -0,0 +1,62 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    InvalidMagic { expected: String, found: String },
+    UnsupportedVersion { major: u32, minor: u32 },
+    InvalidValueType(u8),
+}
+
+impl Error {
+    /// Maps the error onto the closest `io::ErrorKind` so callers can keep matching on kinds
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::Io(error) => error.kind(),
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::InvalidMagic { expected, found } => {
+                write!(f, "Invalid magic: expected {}, found {}", expected, found)
+            }
+            Error::UnsupportedVersion { major, minor } => {
+                write!(f, "Unsupported version: {}.{}", major, minor)
+            }
+            Error::InvalidValueType(value_type) => write!(f, "Invalid value type: {}", value_type),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            error => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
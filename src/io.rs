@@ -6,3 +6,7 @@ pub mod simple_skin;
 pub mod static_object;
 pub mod world_geometry;
 pub mod simple_environment;
+pub mod detect;
+pub mod serialize;
+pub mod ply;
+pub mod obj;
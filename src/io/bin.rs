@@ -1,24 +1,34 @@
 use crate::{
+    error::{Error, Result},
     io::{binary_reader::BinaryReader, binary_writer::BinaryWriter},
     structures::{color::LinSrgbaExt, vector2::Vector2, vector3::Vector3, vector4::Vector4},
-    utilities::hashing::StringHasher,
+    utilities::hash_table::HashTable,
+    utilities::hashing::{fnv1a_lc, StringHasher},
+    utilities::version::Version,
 };
 use num_traits::{FromPrimitive, ToPrimitive};
 use palette::LinSrgba;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
     fs::File,
     hash::{Hash, Hasher},
     io,
-    io::{Cursor, Read, Seek, Write},
+    io::{Cursor, Read, Seek, SeekFrom, Write},
     mem,
     path::Path,
 };
 
 #[derive(Debug)]
 pub struct BinTree {
+    version: Version,
+    // The 8 bytes after the PTCH magic, `None` for trees without it
+    patch_header: Option<u64>,
     dependencies: Vec<String>,
     entries: Vec<BinEntry>,
+
+    // Built when the tree is read, maps entry paths to their index. The last entry wins for duplicate paths
+    path_index: HashMap<u32, usize>,
 }
 
 #[derive(Debug)]
@@ -46,7 +56,7 @@ pub enum BinValue {
     Vector3      { name: u32, value: Vector3, },
     Vector4      { name: u32, value: Vector4, },
     Matrix44     { name: u32, value: [[f32; 4]; 4], },
-    Color        { name: u32, value: LinSrgba, },
+    Color        { name: u32, value: LinSrgba, format: ColorFormat, },
     String       { name: u32, value: String, },
     Hash         { name: u32, value: u32, },
     Container    { name: u32, value: BinContainer, },
@@ -57,6 +67,8 @@ pub enum BinValue {
     Optional     { name: u32, value_type: BinValueType, value: Option<Box<BinValue>>, },
     Map          { name: u32, value: BinMap, },
     FlagsBoolean { name: u32, value: bool, },
+    /// A value type this crate doesn't know, only read by a lenient reader as the last field of an entry or structure
+    Unknown      { name: u32, type_id: u8, raw: Vec<u8>, },
 }
 
 #[derive(FromPrimitive, ToPrimitive, PartialEq, Copy, Clone, Debug)]
@@ -89,6 +101,14 @@ pub enum BinValueType {
     FlagsBoolean = 25,
 }
 
+/// How a color is encoded, files read from disk always use `U8`.
+/// `F32` can't be written, writing it fails with `InvalidInput` until a type id for the 16-byte form is known
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ColorFormat {
+    U8,
+    F32,
+}
+
 #[derive(PartialEq, Debug)]
 pub struct BinStructure {
     name: u32,
@@ -108,73 +128,208 @@ pub struct BinMap {
     map: HashMap<BinValue, BinValue>,
 }
 
+/// One step of a path into nested values, see `BinEntry::navigate`
+#[derive(PartialEq, Debug)]
+pub enum BinPathSegment {
+    /// Name hash of a field in an entry or structure
+    Field(u32),
+    /// Index into a container
+    Index(usize),
+    /// Key of a map entry, keys are stored with a name of 0
+    Key(BinValue),
+}
+
 pub struct BinReader;
+
+struct BinHeader {
+    version: Version,
+    patch_header: Option<u64>,
+    dependencies: Vec<String>,
+    entry_classes: Vec<u32>,
+}
 pub struct BinWriter;
 
+pub struct BinEntries<'a, R: Read + Seek> {
+    reader: &'a mut BinaryReader<R>,
+    entry_classes: Vec<u32>,
+    current: usize,
+}
+
 impl BinReader {
-    pub fn read_tree_file(path: &Path) -> io::Result<BinTree> {
+    pub fn read_tree_file(path: &Path) -> Result<BinTree> {
         BinReader::read_tree(&mut BinaryReader::from_file(File::open(path)?))
     }
-    pub fn read_tree_buffer(buffer: Cursor<Vec<u8>>) -> io::Result<BinTree> {
+    pub fn read_tree_buffer(buffer: Cursor<Vec<u8>>) -> Result<BinTree> {
         BinReader::read_tree(&mut BinaryReader::from_buffer(buffer))
     }
-    pub fn read_tree<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<BinTree> {
-        let magic = reader.read_string(4)?;
+    pub fn read_tree<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<BinTree> {
+        BinReader::read_tree_with_progress(reader, &mut |_, _| {})
+    }
+    /// `progress` is called with (done, total) after every entry
+    pub fn read_tree_with_progress<R: Read + Seek>(
+        reader: &mut BinaryReader<R>,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<BinTree> {
+        let BinHeader { version, patch_header, dependencies, entry_classes } = BinReader::read_header(reader)?;
+
+        let total = entry_classes.len() as u64;
+        let mut entries: Vec<BinEntry> = Vec::with_capacity(entry_classes.len());
+        for entry_class in &entry_classes {
+            entries.push(BinEntry::read(*entry_class, reader)?);
+            progress(entries.len() as u64, total);
+        }
+
+        Ok(BinTree {
+            version,
+            patch_header,
+            dependencies,
+            path_index: BinTree::build_path_index(&entries),
+            entries,
+        })
+    }
+    pub fn entries_iter<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<BinEntries<'_, R>> {
+        let entry_classes = BinReader::read_header(reader)?.entry_classes;
+
+        Ok(BinEntries {
+            reader,
+            entry_classes,
+            current: 0,
+        })
+    }
+    /// Skips over entries without parsing them until one with a matching path is found
+    pub fn find_entry<R: Read + Seek>(reader: &mut BinaryReader<R>, path: u32) -> Result<Option<BinEntry>> {
+        let entry_classes = BinReader::read_header(reader)?.entry_classes;
+
+        for entry_class in entry_classes {
+            let entry_offset = reader.position()?;
+            let size = reader.read_u32()? as u64;
+            let entry_path = reader.read_u32()?;
+
+            reader.seek(SeekFrom::Start(entry_offset))?;
+            if entry_path == path {
+                return Ok(Some(BinEntry::read(entry_class, reader)?));
+            }
+
+            reader.seek(SeekFrom::Start(entry_offset + 4 + size))?;
+        }
+
+        Ok(None)
+    }
+    fn read_header<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<BinHeader> {
+        let mut magic = reader.read_string(4)?;
+        let mut patch_header = None;
+        if magic.as_str() == "PTCH" {
+            patch_header = Some(reader.read_u64()?); // Unknown
+
+            magic = reader.read_string(4)?;
+        }
+
         if magic.as_str() != "PROP" {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid magic"));
+            return Err(Error::InvalidMagic {
+                expected: String::from("PROP"),
+                found: magic,
+            });
         }
 
         let version = reader.read_u32()?;
         if version != 1 && version != 2 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Unsupported version",
-            ));
+            return Err(Error::UnsupportedVersion { major: version, minor: 0 });
         }
 
         let mut dependencies: Vec<String> = Vec::default();
         if version >= 2 {
             let dependency_count = reader.read_u32()?;
             for _ in 0..dependency_count {
-                let length = reader.read_u16()? as usize;
-                dependencies.push(reader.read_string(length)?);
+                dependencies.push(reader.read_sized_string_u16()?);
             }
         }
 
         let entry_count = reader.read_u32()? as usize;
-        let mut entries: Vec<BinEntry> = Vec::with_capacity(entry_count);
         let mut entry_classes: Vec<u32> = Vec::with_capacity(entry_count);
-
         for _ in 0..entry_count {
             entry_classes.push(reader.read_u32()?);
         }
 
-        for entry_class in &entry_classes {
-            entries.push(BinEntry::read(*entry_class, reader)?);
-        }
+        let major = u8::try_from(version).map_err(|_| Error::UnsupportedVersion { major: version, minor: 0 })?;
 
-        Ok(BinTree {
+        Ok(BinHeader {
+            version: Version::new(major, 0),
+            patch_header,
             dependencies,
-            entries,
+            entry_classes,
         })
     }
 }
 
+impl<'a, R: Read + Seek> BinEntries<'a, R> {
+    fn read_next(&mut self, entry_class: u32) -> Result<BinEntry> {
+        let entry_offset = self.reader.position()?;
+        let size = self.reader.read_u32()? as u64;
+
+        self.reader.seek(SeekFrom::Start(entry_offset))?;
+        let entry = BinEntry::read(entry_class, self.reader)?;
+
+        // Entries store their size so we always land on the next one
+        self.reader.seek(SeekFrom::Start(entry_offset + 4 + size))?;
+
+        Ok(entry)
+    }
+}
+
+impl<'a, R: Read + Seek> Iterator for BinEntries<'a, R> {
+    type Item = Result<BinEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry_class = *self.entry_classes.get(self.current)?;
+
+        let entry = self.read_next(entry_class);
+        self.current = if entry.is_ok() {
+            self.current + 1
+        } else {
+            self.entry_classes.len()
+        };
+
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.entry_classes.len() - self.current;
+
+        (0, Some(remaining))
+    }
+}
+
 impl BinWriter {
-    pub fn write_tree_file(tree: &BinTree, path: &Path) -> io::Result<()> {
-        BinWriter::write_tree(tree, &mut BinaryWriter::from_location(path))
+    pub fn write_tree_file(tree: &BinTree, path: &Path) -> Result<()> {
+        let mut writer = BinaryWriter::from_location(path)?;
+        BinWriter::write_tree(tree, &mut writer)?;
+
+        Ok(writer.flush()?)
     }
-    pub fn write_tree_buffer(tree: &BinTree, buffer: Cursor<Vec<u8>>) -> io::Result<()> {
-        BinWriter::write_tree(tree, &mut BinaryWriter::from_buffer(buffer))
+    pub fn write_tree_buffer(tree: &BinTree, buffer: Cursor<Vec<u8>>) -> Result<()> {
+        let mut writer = BinaryWriter::from_buffer(buffer);
+        BinWriter::write_tree(tree, &mut writer)?;
+
+        Ok(writer.flush()?)
+    }
+    pub fn write_tree_to_vec(tree: &BinTree) -> Result<Vec<u8>> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        BinWriter::write_tree(tree, &mut writer)?;
+
+        Ok(writer.into_inner()?.into_inner())
     }
-    pub fn write_tree<W: Write + Seek>(tree: &BinTree, writer: &mut BinaryWriter<W>, ) -> io::Result<()> {
+    pub fn write_tree<W: Write + Seek>(tree: &BinTree, writer: &mut BinaryWriter<W>, ) -> Result<()> {
+        if let Some(patch_header) = tree.patch_header() {
+            writer.write_string("PTCH")?; // Patch Magic
+            writer.write_u64(patch_header)?; // Unknown
+        }
+
         writer.write_string("PROP")?; // Magic
         writer.write_u32(2)?; // Version
 
         writer.write_u32(tree.dependencies().len() as u32)?;
         for dependency in tree.dependencies() {
-            writer.write_u16(dependency.len() as u16)?;
-            writer.write_string(dependency)?;
+            writer.write_sized_string_u16(dependency)?;
         }
 
         writer.write_u32(tree.entries().len() as u32)?;
@@ -190,24 +345,353 @@ impl BinWriter {
 }
 
 impl BinTree {
+    /// The version the tree was read as, trees are always written as version 2
+    pub fn version(&self) -> Version {
+        self.version
+    }
+    pub fn is_patch(&self) -> bool {
+        self.patch_header.is_some()
+    }
+    /// The unknown 8 bytes following the PTCH magic, written back as they were read
+    pub fn patch_header(&self) -> Option<u64> {
+        self.patch_header
+    }
     pub fn dependencies(&self) -> &Vec<String> {
         &self.dependencies
     }
+    pub fn dependencies_mut(&mut self) -> &mut Vec<String> {
+        &mut self.dependencies
+    }
+    /// Returns the index of the dependency, adding it only if it isn't registered yet
+    pub fn add_dependency(&mut self, path: String) -> usize {
+        match self.dependencies.iter().position(|dependency| dependency.eq_ignore_ascii_case(&path)) {
+            Some(index) => index,
+            None => {
+                self.dependencies.push(path);
+                self.dependencies.len() - 1
+            }
+        }
+    }
+    /// Removes dependencies which aren't referenced by any `String` value or by a `Link` to their path hash
+    pub fn prune_unused_dependencies(&mut self) {
+        let mut strings: HashSet<String> = HashSet::new();
+        let mut links: HashSet<u32> = HashSet::new();
+        let mut hashes: HashSet<u32> = HashSet::new();
+        for entry in &self.entries {
+            for value in &entry.values {
+                BinTree::collect_references(value, &mut strings, &mut links, &mut hashes);
+            }
+        }
+
+        self.dependencies.retain(|dependency| {
+            strings.contains(&dependency.to_ascii_lowercase()) || links.contains(&fnv1a_lc(dependency))
+        });
+    }
+    /// Maps the path of every entry to the entry paths it references, sorted and without duplicates.
+    /// `Link` values count whether or not their target is in this tree, `Hash` values only if they match an entry path
+    pub fn reference_graph(&self) -> HashMap<u32, Vec<u32>> {
+        let mut graph: HashMap<u32, Vec<u32>> = HashMap::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            let mut strings: HashSet<String> = HashSet::new();
+            let mut links: HashSet<u32> = HashSet::new();
+            let mut hashes: HashSet<u32> = HashSet::new();
+            for value in &entry.values {
+                BinTree::collect_references(value, &mut strings, &mut links, &mut hashes);
+            }
+
+            let mut references: Vec<u32> = links
+                .into_iter()
+                .chain(hashes.into_iter().filter(|hash| self.entry_index(*hash).is_some()))
+                .collect();
+            references.sort_unstable();
+            references.dedup();
+
+            graph.entry(entry.path).or_default().extend(references);
+        }
+
+        graph
+    }
+    /// Entries which no other entry references, see `reference_graph`
+    pub fn unreferenced_entries(&self) -> Vec<&BinEntry> {
+        let mut referenced: HashSet<u32> = HashSet::new();
+        for (path, references) in self.reference_graph() {
+            referenced.extend(references.into_iter().filter(|reference| *reference != path));
+        }
+
+        self.entries.iter().filter(|entry| !referenced.contains(&entry.path)).collect()
+    }
+    fn collect_references(
+        value: &BinValue,
+        strings: &mut HashSet<String>,
+        links: &mut HashSet<u32>,
+        hashes: &mut HashSet<u32>,
+    ) {
+        match value {
+            BinValue::String { value, .. } => {
+                strings.insert(value.to_ascii_lowercase());
+            }
+            BinValue::Link { value, .. } => {
+                links.insert(*value);
+            }
+            BinValue::Hash { value, .. } => {
+                hashes.insert(*value);
+            }
+            BinValue::Container { value, .. } | BinValue::Container2 { value, .. } => {
+                for element in &value.values {
+                    BinTree::collect_references(element, strings, links, hashes);
+                }
+            }
+            BinValue::Structure { value, .. } | BinValue::Embedded { value, .. } => {
+                for field in &value.fields {
+                    BinTree::collect_references(field, strings, links, hashes);
+                }
+            }
+            BinValue::Optional { value: Some(value), .. } => {
+                BinTree::collect_references(value, strings, links, hashes);
+            }
+            BinValue::Map { value, .. } => {
+                for (key, map_value) in &value.map {
+                    BinTree::collect_references(key, strings, links, hashes);
+                    BinTree::collect_references(map_value, strings, links, hashes);
+                }
+            }
+            _ => {}
+        }
+    }
     pub fn entries(&self) -> &Vec<BinEntry> {
         &self.entries
     }
+
+    /// If several entries share a path the last one is returned
+    pub fn entry(&self, path: u32) -> Option<&BinEntry> {
+        let index = self.entry_index(path)?;
+
+        self.entries.get(index)
+    }
+    /// If several entries share a path the last one is returned
+    pub fn entry_mut(&mut self, path: u32) -> Option<&mut BinEntry> {
+        let index = self.entry_index(path)?;
+
+        self.entries.get_mut(index)
+    }
+    pub fn entries_of_class(&self, class: u32) -> impl Iterator<Item = &BinEntry> {
+        self.entries.iter().filter(move |entry| entry.class == class)
+    }
+
+    /// Checks that container, map and optional values match their declared types,
+    /// returns every violation along with the entry path and name hash
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors: Vec<String> = Vec::new();
+        for entry in &self.entries {
+            for value in &entry.values {
+                BinTree::validate_value(value, entry.path, value.name(), &mut errors);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    fn validate_value(value: &BinValue, path: u32, name: u32, errors: &mut Vec<String>) {
+        // Values nested in containers and maps are unnamed so we report the closest named parent
+        let name = if value.name() != 0 { value.name() } else { name };
+        let mut check_type = |declared: BinValueType, value: &BinValue| {
+            if value.value_type() != declared {
+                errors.push(format!(
+                    "Entry {:#010x}, {:#010x}: expected {:?}, found {:?}",
+                    path,
+                    name,
+                    declared,
+                    value.value_type()
+                ));
+            }
+        };
+
+        match value {
+            BinValue::Container { value, .. } | BinValue::Container2 { value, .. } => {
+                for element in &value.values {
+                    check_type(value.value_type, element);
+                }
+                for element in &value.values {
+                    BinTree::validate_value(element, path, name, errors);
+                }
+            }
+            BinValue::Structure { value, .. } | BinValue::Embedded { value, .. } => {
+                for field in &value.fields {
+                    BinTree::validate_value(field, path, name, errors);
+                }
+            }
+            BinValue::Optional { value_type, value: Some(value), .. } => {
+                check_type(*value_type, value);
+                BinTree::validate_value(value, path, name, errors);
+            }
+            BinValue::Map { value, .. } => {
+                for (key, map_value) in &value.map {
+                    check_type(value.key_type, key);
+                    check_type(value.value_type, map_value);
+                }
+                for (key, map_value) in &value.map {
+                    BinTree::validate_value(key, path, name, errors);
+                    BinTree::validate_value(map_value, path, name, errors);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders the tree for human reading, hashes missing from `names` are printed as hex
+    pub fn pretty_print(&self, names: &HashTable) -> String {
+        let mut output = String::new();
+
+        if !self.dependencies.is_empty() {
+            output.push_str("dependencies [\n");
+            for dependency in &self.dependencies {
+                output.push_str(&format!("    {:?}\n", dependency));
+            }
+            output.push_str("]\n");
+        }
+
+        for entry in &self.entries {
+            output.push_str(&format!(
+                "{}: {} {{\n",
+                BinTree::resolve_name(names, entry.path),
+                BinTree::resolve_name(names, entry.class)
+            ));
+            for value in &entry.values {
+                BinTree::pretty_print_field(value, names, 1, &mut output);
+            }
+            output.push_str("}\n");
+        }
+
+        output
+    }
+    fn pretty_print_field(value: &BinValue, names: &HashTable, depth: usize, output: &mut String) {
+        output.push_str(&"    ".repeat(depth));
+        output.push_str(&format!(
+            "{}: {} = ",
+            BinTree::resolve_name(names, value.name()),
+            BinTree::pretty_print_type(value)
+        ));
+        BinTree::pretty_print_value(value, names, depth, output);
+        output.push('\n');
+    }
+    fn pretty_print_type(value: &BinValue) -> String {
+        match value {
+            BinValue::Container { value, .. } => format!("Container<{:?}>", value.value_type),
+            BinValue::Container2 { value, .. } => format!("Container2<{:?}>", value.value_type),
+            BinValue::Optional { value_type, .. } => format!("Optional<{:?}>", value_type),
+            BinValue::Map { value, .. } => format!("Map<{:?}, {:?}>", value.key_type, value.value_type),
+            BinValue::Unknown { type_id, .. } => format!("Unknown<{}>", type_id),
+            _ => format!("{:?}", value.value_type()),
+        }
+    }
+    #[rustfmt::skip]
+    fn pretty_print_value(value: &BinValue, names: &HashTable, depth: usize, output: &mut String) {
+        let indent = "    ".repeat(depth);
+
+        match value {
+            BinValue::None         { .. } => output.push_str("null"),
+            BinValue::Boolean      { value, .. } => output.push_str(&value.to_string()),
+            BinValue::SByte        { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Byte         { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Int16        { value, .. } => output.push_str(&value.to_string()),
+            BinValue::UInt16       { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Int32        { value, .. } => output.push_str(&value.to_string()),
+            BinValue::UInt32       { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Int64        { value, .. } => output.push_str(&value.to_string()),
+            BinValue::UInt64       { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Float        { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Vector2      { value, .. } => output.push_str(&format!("({}, {})", value.x, value.y)),
+            BinValue::Vector3      { value, .. } => output.push_str(&format!("({}, {}, {})", value.x, value.y, value.z)),
+            BinValue::Vector4      { value, .. } => output.push_str(&format!("({}, {}, {}, {})", value.x, value.y, value.z, value.w)),
+            BinValue::Matrix44     { value, .. } => {
+                let rows: Vec<String> = value.iter()
+                    .map(|row| format!("({}, {}, {}, {})", row[0], row[1], row[2], row[3]))
+                    .collect();
+
+                output.push_str(&format!("[{}]", rows.join(", ")));
+            }
+            BinValue::Color        { value, .. } => output.push_str(&format!(
+                "rgba({}, {}, {}, {})",
+                (value.color.red * 255.0).round(),
+                (value.color.green * 255.0).round(),
+                (value.color.blue * 255.0).round(),
+                (value.alpha * 255.0).round()
+            )),
+            BinValue::String       { value, .. } => output.push_str(&format!("{:?}", value)),
+            BinValue::Hash         { value, .. } => output.push_str(&BinTree::resolve_name(names, *value)),
+            BinValue::Link         { value, .. } => output.push_str(&BinTree::resolve_name(names, *value)),
+            BinValue::Container    { value, .. } | BinValue::Container2 { value, .. } => {
+                output.push_str("[\n");
+                for item in &value.values {
+                    output.push_str(&"    ".repeat(depth + 1));
+                    BinTree::pretty_print_value(item, names, depth + 1, output);
+                    output.push('\n');
+                }
+                output.push_str(&indent);
+                output.push(']');
+            }
+            BinValue::Structure    { value, .. } | BinValue::Embedded { value, .. } => {
+                if value.name == 0 {
+                    output.push_str("null");
+                } else {
+                    output.push_str(&format!("{} {{\n", BinTree::resolve_name(names, value.name)));
+                    for field in &value.fields {
+                        BinTree::pretty_print_field(field, names, depth + 1, output);
+                    }
+                    output.push_str(&indent);
+                    output.push('}');
+                }
+            }
+            BinValue::Optional     { value, .. } => match value {
+                Some(value) => BinTree::pretty_print_value(value, names, depth, output),
+                None => output.push_str("null"),
+            },
+            BinValue::Map          { value, .. } => {
+                output.push_str("{\n");
+                for (key, value) in &value.map {
+                    output.push_str(&"    ".repeat(depth + 1));
+                    BinTree::pretty_print_value(key, names, depth + 1, output);
+                    output.push_str(" = ");
+                    BinTree::pretty_print_value(value, names, depth + 1, output);
+                    output.push('\n');
+                }
+                output.push_str(&indent);
+                output.push('}');
+            }
+            BinValue::FlagsBoolean { value, .. } => output.push_str(&value.to_string()),
+            BinValue::Unknown      { raw, .. } => {
+                let bytes: Vec<String> = raw.iter().map(|byte| format!("{:02x}", byte)).collect();
+                output.push_str(&format!("[{}]", bytes.join(" ")));
+            }
+        }
+    }
+    /// Looks up the name of a hash, falling back to its hex representation
+    pub fn resolve_name(names: &HashTable, hash: u32) -> String {
+        match names.get(hash as u64) {
+            Some(name) => name.to_string(),
+            None => format!("{:#010x}", hash),
+        }
+    }
+
+    fn build_path_index(entries: &[BinEntry]) -> HashMap<u32, usize> {
+        entries.iter().enumerate().map(|(index, entry)| (entry.path, index)).collect()
+    }
+    fn entry_index(&self, path: u32) -> Option<usize> {
+        self.path_index.get(&path).copied()
+    }
 }
 
 impl BinEntry {
-    pub fn read<R: Read + Seek>(class: u32, reader: &mut BinaryReader<R>) -> io::Result<Self> {
+    pub fn read<R: Read + Seek>(class: u32, reader: &mut BinaryReader<R>) -> Result<Self> {
         let size = reader.read_u32()?;
+        let end = reader.position()? + size as u64;
         let path = reader.read_u32()?;
 
         let value_count = reader.read_u16()? as usize;
-        let mut values: Vec<BinValue> = Vec::with_capacity(value_count);
-        for i in 0..value_count {
-            values.push(BinValue::read(reader)?);
-        }
+        let values = BinValue::read_fields(reader, value_count, end)?;
 
         Ok(BinEntry {
             class,
@@ -237,6 +721,98 @@ impl BinEntry {
     pub fn values(&self) -> &Vec<BinValue> {
         &self.values
     }
+    pub fn values_mut(&mut self) -> &mut Vec<BinValue> {
+        &mut self.values
+    }
+
+    pub fn field(&self, name: u32) -> Option<&BinValue> {
+        self.values.iter().find(|value| value.name() == name)
+    }
+    /// Replaces the field with the same name or appends it if the entry doesn't have it
+    pub fn set_field(&mut self, name: u32, value: BinValue) {
+        assert_eq!(name, value.name(), "Field name doesn't match the name of the value");
+
+        match self.values.iter_mut().find(|field| field.name() == name) {
+            Some(field) => *field = value,
+            None => self.values.push(value),
+        }
+    }
+    pub fn remove_field(&mut self, name: u32) -> Option<BinValue> {
+        let index = self.values.iter().position(|value| value.name() == name)?;
+
+        Some(self.values.remove(index))
+    }
+
+    /// Descends through structure fields, container indices and map keys, optionals are looked through.
+    /// Returns `None` as soon as a segment doesn't match the value it's applied to
+    pub fn navigate(&self, path: &[BinPathSegment]) -> Option<&BinValue> {
+        let (first, rest) = path.split_first()?;
+        let mut current = match first {
+            BinPathSegment::Field(name) => self.field(*name)?,
+            _ => return None,
+        };
+
+        for segment in rest {
+            while let BinValue::Optional { value, .. } = current {
+                current = value.as_deref()?;
+            }
+
+            current = match (current, segment) {
+                (BinValue::Structure { value, .. }, BinPathSegment::Field(name))
+                | (BinValue::Embedded { value, .. }, BinPathSegment::Field(name)) => value.field(*name)?,
+                (BinValue::Container { value, .. }, BinPathSegment::Index(index))
+                | (BinValue::Container2 { value, .. }, BinPathSegment::Index(index)) => value.values.get(*index)?,
+                (BinValue::Map { value, .. }, BinPathSegment::Key(key)) => value.map.get(key)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    /// Looks the field up and extracts its value, `None` if it's missing or of a different type
+    pub fn get_bool(&self, name: u32) -> Option<bool> {
+        self.field(name)?.as_bool()
+    }
+    pub fn get_u32(&self, name: u32) -> Option<u32> {
+        self.field(name)?.as_u32()
+    }
+    pub fn get_i32(&self, name: u32) -> Option<i32> {
+        self.field(name)?.as_i32()
+    }
+    pub fn get_f32(&self, name: u32) -> Option<f32> {
+        self.field(name)?.as_f32()
+    }
+    pub fn get_vector2(&self, name: u32) -> Option<Vector2> {
+        self.field(name)?.as_vector2()
+    }
+    pub fn get_vector3(&self, name: u32) -> Option<Vector3> {
+        self.field(name)?.as_vector3()
+    }
+    pub fn get_vector4(&self, name: u32) -> Option<Vector4> {
+        self.field(name)?.as_vector4()
+    }
+    pub fn get_color(&self, name: u32) -> Option<LinSrgba> {
+        self.field(name)?.as_color()
+    }
+    pub fn get_hash(&self, name: u32) -> Option<u32> {
+        self.field(name)?.as_hash()
+    }
+    pub fn get_link(&self, name: u32) -> Option<u32> {
+        self.field(name)?.as_link()
+    }
+    pub fn get_string(&self, name: u32) -> Option<&str> {
+        self.field(name)?.as_string()
+    }
+    pub fn get_container(&self, name: u32) -> Option<&BinContainer> {
+        self.field(name)?.as_container()
+    }
+    pub fn get_structure(&self, name: u32) -> Option<&BinStructure> {
+        self.field(name)?.as_structure()
+    }
+    pub fn get_map(&self, name: u32) -> Option<&BinMap> {
+        self.field(name)?.as_map()
+    }
 
     pub(crate) fn size(&self) -> usize {
         let mut size = 6usize;
@@ -249,14 +825,39 @@ impl BinEntry {
 }
 
 impl BinValue {
-    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<Self> {
         let name = reader.read_u32()?;
-        let value_type = BinValue::unpack_value_type(reader.read_u8()?);
+        let value_type = BinValue::unpack_value_type(reader.read_u8()?)?;
 
         BinValue::read_value(name, value_type, reader)
     }
+    /// Reads the fields of an entry or structure ending at `end`. A lenient reader keeps a field of an unknown type
+    /// as raw bytes if it's the last one, anywhere else its size can't be known
+    fn read_fields<R: Read + Seek>(reader: &mut BinaryReader<R>, count: usize, end: u64) -> Result<Vec<Self>> {
+        let mut fields: Vec<BinValue> = Vec::with_capacity(count);
+        for i in 0..count {
+            let name = reader.read_u32()?;
+            let type_id = reader.read_u8()?;
+
+            let field = match BinValue::unpack_value_type(type_id) {
+                Ok(value_type) => BinValue::read_value(name, value_type, reader)?,
+                Err(error) => {
+                    let position = reader.position()?;
+                    if !reader.lenient() || i + 1 != count || end < position {
+                        return Err(error);
+                    }
+
+                    let size = reader.check_count((end - position) as usize, 1)?;
+                    BinValue::Unknown { name, type_id, raw: reader.read_bytes(size)? }
+                }
+            };
+            fields.push(field);
+        }
+
+        Ok(fields)
+    }
     #[rustfmt::skip]
-    fn read_value<R: Read + Seek>(name: u32, value_type: BinValueType, reader: &mut BinaryReader<R>, ) -> io::Result<Self> {
+    fn read_value<R: Read + Seek>(name: u32, value_type: BinValueType, reader: &mut BinaryReader<R>, ) -> Result<Self> {
         Ok(match value_type {
             BinValueType::None => BinValue::None { name },
             BinValueType::Boolean => BinValue::Boolean { name, value: reader.read_u8()? != 0, },
@@ -273,21 +874,17 @@ impl BinValue {
             BinValueType::Vector3 => BinValue::Vector3 { name, value: Vector3::read(reader)?, },
             BinValueType::Vector4 => BinValue::Vector4 { name, value: Vector4::read(reader)?, },
             BinValueType::Matrix44 => BinValue::Matrix44 { name, value: {
+                    let values = reader.read_f32_array::<16>()?;
                     let mut matrix = [[0.0, 0.0, 0.0, 0.0]; 4];
 
-                    for row in matrix.iter_mut() {
-                        for entry in row.iter_mut() {
-                            *entry = reader.read_f32()?;
-                        }
+                    for (row, values) in matrix.iter_mut().zip(values.chunks_exact(4)) {
+                        row.copy_from_slice(values);
                     }
 
                     matrix
             }},
-            BinValueType::Color => BinValue::Color { name, value: LinSrgba::read_rgba_u8(reader)?, },
-            BinValueType::String => BinValue::String { name, value: {
-                    let length = reader.read_u16()? as usize;
-                    reader.read_string(length)?
-                } },
+            BinValueType::Color => BinValue::Color { name, value: LinSrgba::read_rgba_u8(reader)?, format: ColorFormat::U8, },
+            BinValueType::String => BinValue::String { name, value: reader.read_sized_string_u16()?, },
             BinValueType::Hash => BinValue::Hash { name, value: reader.read_u32()?, },
             BinValueType::Container => BinValue::Container { name, value: BinContainer::read(reader)?, },
             BinValueType::Container2 => BinValue::Container2 { name, value: BinContainer::read(reader)?, },
@@ -303,8 +900,8 @@ impl BinValue {
             BinValueType::FlagsBoolean => BinValue::FlagsBoolean { name, value: reader.read_u8()? != 0, },
         })
     }
-    fn read_optional<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<(BinValueType, Option<Box<BinValue>>)> {
-        let value_type = BinValue::unpack_value_type(reader.read_u8()?);
+    fn read_optional<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<(BinValueType, Option<Box<BinValue>>)> {
+        let value_type = BinValue::unpack_value_type(reader.read_u8()?)?;
         let is_some = reader.read_u8()? != 0;
 
         if is_some {
@@ -346,9 +943,10 @@ impl BinValue {
             BinValue::Optional     { name, .. } => { writer.write_u32(*name)?; }
             BinValue::Map          { name, .. } => { writer.write_u32(*name)?; }
             BinValue::FlagsBoolean { name, .. } => { writer.write_u32(*name)?; }
+            BinValue::Unknown      { name, .. } => { writer.write_u32(*name)?; }
         };
 
-        writer.write_u8(BinValue::pack_value_type(self.value_type()))?;
+        writer.write_u8(self.type_id())?;
         self.write_value(writer)?;
 
         Ok(())
@@ -372,17 +970,20 @@ impl BinValue {
             BinValue::Vector3      { name, value } => { value.write(writer)?; }
             BinValue::Vector4      { name, value } => { value.write(writer)?; }
             BinValue::Matrix44     { name, value } => {
-                for row in value.iter() {
-                    for entry in row.iter() {
-                        writer.write_f32(*entry)?;
-                    }
+                let mut values = [0.0; 16];
+                for (values, row) in values.chunks_exact_mut(4).zip(value.iter()) {
+                    values.copy_from_slice(row);
                 }
+
+                writer.write_f32_slice(&values)?;
             }
-            BinValue::Color        { name, value } => { value.write_rgba_u8(writer)?; }
-            BinValue::String       { name, value } => {
-                writer.write_u16(value.len() as u16)?;
-                writer.write_string(value)?;
-            }
+            BinValue::Color        { name, value, format } => match format {
+                ColorFormat::U8 => { value.write_rgba_u8(writer)?; }
+                ColorFormat::F32 => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "f32 colors have no known BIN type id"));
+                }
+            },
+            BinValue::String       { name, value } => { writer.write_sized_string_u16(value)?; }
             BinValue::Hash         { name, value } => { writer.write_u32(*value)?; }
             BinValue::Container    { name, value } => { value.write(writer)?; }
             BinValue::Container2   { name, value } => { value.write(writer)?; }
@@ -399,18 +1000,20 @@ impl BinValue {
             }
             BinValue::Map          { name, value } => { value.write(writer)?; }
             BinValue::FlagsBoolean { name, value } => { writer.write_u8(*value as u8)?; }
+            BinValue::Unknown      { raw, .. } => { writer.write(raw.as_slice())?; }
         };
 
         Ok(())
     }
 
-    fn unpack_value_type(mut value_type: u8) -> BinValueType {
+    fn unpack_value_type(packed_value_type: u8) -> Result<BinValueType> {
+        let mut value_type = packed_value_type;
         if value_type & 128 == 128 {
             value_type -= 128;
             value_type += 18;
         }
 
-        return BinValueType::from_u8(value_type).expect("Invalid Value type");
+        BinValueType::from_u8(value_type).ok_or(Error::InvalidValueType(packed_value_type))
     }
     fn pack_value_type(value_type: BinValueType) -> u8 {
         let mut value_type = value_type.to_u8().expect("Invalid Value Type");
@@ -422,6 +1025,40 @@ impl BinValue {
         value_type
     }
 
+    #[rustfmt::skip]
+    pub fn name(&self) -> u32 {
+        match self {
+            BinValue::None         { name } => *name,
+            BinValue::Boolean      { name, .. } => *name,
+            BinValue::SByte        { name, .. } => *name,
+            BinValue::Byte         { name, .. } => *name,
+            BinValue::Int16        { name, .. } => *name,
+            BinValue::UInt16       { name, .. } => *name,
+            BinValue::Int32        { name, .. } => *name,
+            BinValue::UInt32       { name, .. } => *name,
+            BinValue::Int64        { name, .. } => *name,
+            BinValue::UInt64       { name, .. } => *name,
+            BinValue::Float        { name, .. } => *name,
+            BinValue::Vector2      { name, .. } => *name,
+            BinValue::Vector3      { name, .. } => *name,
+            BinValue::Vector4      { name, .. } => *name,
+            BinValue::Matrix44     { name, .. } => *name,
+            BinValue::Color        { name, .. } => *name,
+            BinValue::String       { name, .. } => *name,
+            BinValue::Hash         { name, .. } => *name,
+            BinValue::Container    { name, .. } => *name,
+            BinValue::Container2   { name, .. } => *name,
+            BinValue::Structure    { name, .. } => *name,
+            BinValue::Embedded     { name, .. } => *name,
+            BinValue::Link         { name, .. } => *name,
+            BinValue::Optional     { name, .. } => *name,
+            BinValue::Map          { name, .. } => *name,
+            BinValue::FlagsBoolean { name, .. } => *name,
+            BinValue::Unknown      { name, .. } => *name,
+        }
+    }
+
+    /// `Unknown` values report `BinValueType::None` here, use `known_value_type` to tell them apart
     #[rustfmt::skip]
     pub fn value_type(&self) -> BinValueType {
         match self {
@@ -451,6 +1088,156 @@ impl BinValue {
             BinValue::Optional { .. } => BinValueType::Optional,
             BinValue::Map { .. } => BinValueType::Map,
             BinValue::FlagsBoolean { .. } => BinValueType::FlagsBoolean,
+            BinValue::Unknown { .. } => BinValueType::None,
+        }
+    }
+    /// The value type, or `None` for `Unknown` values whose id is only available through `type_id`
+    pub fn known_value_type(&self) -> Option<BinValueType> {
+        match self {
+            BinValue::Unknown { .. } => None,
+            _ => Some(self.value_type()),
+        }
+    }
+    /// The type id as it's stored in the file
+    pub fn type_id(&self) -> u8 {
+        match self {
+            BinValue::Unknown { type_id, .. } => *type_id,
+            _ => BinValue::pack_value_type(self.value_type()),
+        }
+    }
+
+    /// Typed accessors, these return `None` if the value is of a different type
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            BinValue::Boolean { value, .. } | BinValue::FlagsBoolean { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_i8(&self) -> Option<i8> {
+        match self {
+            BinValue::SByte { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_u8(&self) -> Option<u8> {
+        match self {
+            BinValue::Byte { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_i16(&self) -> Option<i16> {
+        match self {
+            BinValue::Int16 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_u16(&self) -> Option<u16> {
+        match self {
+            BinValue::UInt16 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            BinValue::Int32 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            BinValue::UInt32 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            BinValue::Int64 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            BinValue::UInt64 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            BinValue::Float { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_vector2(&self) -> Option<Vector2> {
+        match self {
+            BinValue::Vector2 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_vector3(&self) -> Option<Vector3> {
+        match self {
+            BinValue::Vector3 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_vector4(&self) -> Option<Vector4> {
+        match self {
+            BinValue::Vector4 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_matrix44(&self) -> Option<[[f32; 4]; 4]> {
+        match self {
+            BinValue::Matrix44 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_color(&self) -> Option<LinSrgba> {
+        match self {
+            BinValue::Color { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_hash(&self) -> Option<u32> {
+        match self {
+            BinValue::Hash { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_link(&self) -> Option<u32> {
+        match self {
+            BinValue::Link { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_string(&self) -> Option<&str> {
+        match self {
+            BinValue::String { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    pub fn as_container(&self) -> Option<&BinContainer> {
+        match self {
+            BinValue::Container { value, .. } | BinValue::Container2 { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    pub fn as_structure(&self) -> Option<&BinStructure> {
+        match self {
+            BinValue::Structure { value, .. } | BinValue::Embedded { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    pub fn as_map(&self) -> Option<&BinMap> {
+        match self {
+            BinValue::Map { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    /// Returns `None` both for a type mismatch and for an empty optional
+    pub fn as_optional(&self) -> Option<&BinValue> {
+        match self {
+            BinValue::Optional { value, .. } => value.as_deref(),
+            _ => None,
         }
     }
 
@@ -473,7 +1260,8 @@ impl BinValue {
             BinValue::Vector3 { name, value } => mem::size_of::<Vector3>(),
             BinValue::Vector4 { name, value } => mem::size_of::<Vector4>(),
             BinValue::Matrix44 { name, value } => mem::size_of::<[[f32; 4]; 4]>(),
-            BinValue::Color { name, value } => 4,
+            BinValue::Color { format: ColorFormat::U8, .. } => 4,
+            BinValue::Color { format: ColorFormat::F32, .. } => 16,
             BinValue::String { name, value } => value.len() + 2,
             BinValue::Hash { name, value } => mem::size_of::<u32>(),
             BinValue::Container { name, value } => value.size(),
@@ -490,6 +1278,7 @@ impl BinValue {
             }
             BinValue::Map { name, value } => value.size(),
             BinValue::FlagsBoolean { name, value } => mem::size_of::<u8>(),
+            BinValue::Unknown { raw, .. } => raw.len(),
         };
 
         type_size + value_size
@@ -497,7 +1286,10 @@ impl BinValue {
 }
 
 impl BinStructure {
-    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+    pub fn new(name: u32, fields: Vec<BinValue>) -> Self {
+        BinStructure { name, fields }
+    }
+    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<Self> {
         let name = reader.read_u32()?;
         if name == 0 {
             Ok(BinStructure {
@@ -506,12 +1298,12 @@ impl BinStructure {
             })
         } else {
             let size = reader.read_u32()?;
+            let start = reader.position()?;
 
             let field_count = reader.read_u16()? as usize;
-            let mut fields: Vec<BinValue> = Vec::with_capacity(field_count);
-            for _ in 0..field_count {
-                fields.push(BinValue::read(reader)?);
-            }
+            let fields = BinValue::read_fields(reader, field_count, start + size as u64)?;
+
+            verify_content_size("Structure", size, reader.position()? - start)?;
 
             Ok(BinStructure { name, fields })
         }
@@ -537,6 +1329,9 @@ impl BinStructure {
     pub fn fields(&self) -> &Vec<BinValue> {
         &self.fields
     }
+    pub fn field(&self, name: u32) -> Option<&BinValue> {
+        self.fields.iter().find(|field| field.name() == name)
+    }
 
     pub(crate) fn size(&self) -> usize {
         if self.name == 0 {
@@ -556,16 +1351,22 @@ impl BinStructure {
 }
 
 impl BinContainer {
-    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
-        let value_type = BinValue::unpack_value_type(reader.read_u8()?);
+    pub fn new(value_type: BinValueType, values: Vec<BinValue>) -> Self {
+        BinContainer { value_type, values }
+    }
+    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<Self> {
+        let value_type = BinValue::unpack_value_type(reader.read_u8()?)?;
         let size = reader.read_u32()?;
+        let start = reader.position()?;
 
         let value_count = reader.read_u32()? as usize;
-        let mut values: Vec<BinValue> = Vec::with_capacity(value_count);
+        let mut values: Vec<BinValue> = Vec::with_capacity(value_count.min(size as usize));
         for _ in 0..value_count {
             values.push(BinValue::read_value(0, value_type, reader)?);
         }
 
+        verify_content_size("Container", size, reader.position()? - start)?;
+
         Ok(BinContainer { value_type, values })
     }
 
@@ -602,13 +1403,21 @@ impl BinContainer {
 }
 
 impl BinMap {
-    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
-        let key_type = BinValue::unpack_value_type(reader.read_u8()?);
-        let value_type = BinValue::unpack_value_type(reader.read_u8()?);
+    pub fn new(key_type: BinValueType, value_type: BinValueType, map: HashMap<BinValue, BinValue>) -> Self {
+        BinMap {
+            key_type,
+            value_type,
+            map,
+        }
+    }
+    pub fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<Self> {
+        let key_type = BinValue::unpack_value_type(reader.read_u8()?)?;
+        let value_type = BinValue::unpack_value_type(reader.read_u8()?)?;
         let size = reader.read_u32()?;
+        let start = reader.position()?;
 
         let entry_count = reader.read_u32()? as usize;
-        let mut map: HashMap<BinValue, BinValue> = HashMap::with_capacity(entry_count);
+        let mut map: HashMap<BinValue, BinValue> = HashMap::with_capacity(entry_count.min(size as usize));
         for _ in 0..entry_count {
             map.insert(
                 BinValue::read_value(0, key_type, reader)?,
@@ -616,6 +1425,8 @@ impl BinMap {
             );
         }
 
+        verify_content_size("Map", size, reader.position()? - start)?;
+
         Ok(BinMap {
             key_type,
             value_type,
@@ -684,3 +1495,15 @@ impl Hash for BinValue {
 }
 
 impl Eq for BinValue {}
+
+fn verify_content_size(kind: &str, declared: u32, consumed: u64) -> Result<()> {
+    if consumed != declared as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} declares {} bytes of content but {} were read", kind, declared, consumed),
+        )
+        .into());
+    }
+
+    Ok(())
+}
@@ -1,37 +1,74 @@
+use crate::io::serialize::BinDeserialize;
 use std::fs::File;
 use std::io::Read;
 use std::io::SeekFrom;
-use std::io::{BufReader, Cursor, ErrorKind, Seek};
+use std::io::{BufRead, BufReader, Cursor, ErrorKind, Seek};
 use std::path::Path;
 use std::{io, vec};
 
 pub struct BinaryReader<T: Read = File> {
     reader: BufReader<T>,
+    // Reused by the string readers so only the final String gets allocated
+    buffer: Vec<u8>,
+    strict_utf8: bool,
+    lenient: bool,
 }
 
 impl BinaryReader<Cursor<Vec<u8>>> {
     pub fn from_buffer(buffer: Cursor<Vec<u8>>) -> Self {
-        BinaryReader {
-            reader: BufReader::new(buffer),
-        }
+        BinaryReader::new(buffer)
     }
 }
 impl BinaryReader<File> {
-    pub fn from_location(file_location: &Path) -> Self {
-        let file = File::open(file_location).unwrap();
+    pub fn from_location(file_location: &Path) -> io::Result<Self> {
+        let file = File::open(file_location)?;
 
-        BinaryReader {
-            reader: BufReader::new(file),
-        }
+        Ok(BinaryReader::new(file))
     }
     pub fn from_file(file: File) -> Self {
-        BinaryReader {
-            reader: BufReader::new(file),
-        }
+        BinaryReader::new(file)
+    }
+}
+
+#[cfg(feature = "memmap")]
+impl BinaryReader<Cursor<memmap::Mmap>> {
+    pub fn from_mmap(file_location: &Path) -> io::Result<Self> {
+        let file = File::open(file_location)?;
+
+        // The mapping is only sound as long as nobody truncates the file while we read it
+        let mmap = unsafe { memmap::Mmap::map(&file)? };
+
+        Ok(BinaryReader::new(Cursor::new(mmap)))
     }
 }
 
 impl<T: Read + Seek> BinaryReader<T> {
+    pub fn new(inner: T) -> Self {
+        BinaryReader {
+            reader: BufReader::new(inner),
+            buffer: Vec::new(),
+            strict_utf8: true,
+            lenient: false,
+        }
+    }
+
+    /// Whether padded strings fail on invalid UTF-8, on by default.
+    /// Old texture paths can contain Windows-1252 bytes which only decode with this turned off
+    pub fn strict_utf8(&self) -> bool {
+        self.strict_utf8
+    }
+    pub fn set_strict_utf8(&mut self, strict_utf8: bool) {
+        self.strict_utf8 = strict_utf8;
+    }
+    /// Whether data of an unknown kind is kept as raw bytes instead of failing, off by default.
+    /// Only done where the size of that data is known, see `BinValue::Unknown`
+    pub fn lenient(&self) -> bool {
+        self.lenient
+    }
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
     pub fn read_char(&mut self) -> io::Result<char> {
         match self.read_u8() {
             Ok(x) => Ok(x as char),
@@ -66,6 +103,20 @@ impl<T: Read + Seek> BinaryReader<T> {
 
         Ok(u16::from_le_bytes(buffer))
     }
+    pub fn read_u24(&mut self) -> io::Result<u32> {
+        let mut buffer = [0; 3];
+
+        self.reader.read_exact(&mut buffer)?;
+
+        Ok(u32::from_le_bytes([buffer[0], buffer[1], buffer[2], 0]))
+    }
+    pub fn read_u24_be(&mut self) -> io::Result<u32> {
+        let mut buffer = [0; 3];
+
+        self.reader.read_exact(&mut buffer)?;
+
+        Ok(u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]))
+    }
     pub fn read_i32(&mut self) -> io::Result<i32> {
         let mut buffer = [0; 4];
 
@@ -94,6 +145,13 @@ impl<T: Read + Seek> BinaryReader<T> {
 
         Ok(u64::from_le_bytes(buffer))
     }
+    pub fn read_u128(&mut self) -> io::Result<u128> {
+        let mut buffer = [0; 16];
+
+        self.reader.read_exact(&mut buffer)?;
+
+        Ok(u128::from_le_bytes(buffer))
+    }
     pub fn read_f32(&mut self) -> io::Result<f32> {
         let mut buffer = [0; 4];
 
@@ -109,6 +167,80 @@ impl<T: Read + Seek> BinaryReader<T> {
         Ok(f64::from_le_bytes(buffer))
     }
 
+    pub fn read_u8_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buffer = [0; N];
+
+        self.reader.read_exact(&mut buffer)?;
+
+        Ok(buffer)
+    }
+    /// Reads up to 16 floats with a single `read_exact`, larger arrays are read in chunks of 16
+    pub fn read_f32_array<const N: usize>(&mut self) -> io::Result<[f32; N]> {
+        let mut array = [0.0; N];
+        let mut buffer = [0; 64];
+
+        for chunk in array.chunks_mut(16) {
+            let bytes = &mut buffer[..chunk.len() * 4];
+            self.reader.read_exact(bytes)?;
+
+            for (value, bytes) in chunk.iter_mut().zip(bytes.chunks_exact(4)) {
+                *value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+        }
+
+        Ok(array)
+    }
+
+    /// Reads a u32 count and makes sure the stream has enough bytes left for `count` elements
+    pub fn read_count(&mut self, element_size: usize) -> io::Result<usize> {
+        let count = self.read_u32()? as usize;
+
+        self.check_count(count, element_size)
+    }
+    pub fn check_count(&mut self, count: usize, element_size: usize) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+
+        match count.checked_mul(element_size) {
+            Some(size) if size as u64 <= remaining => Ok(count),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Count {} exceeds the remaining stream size of {} bytes", count, remaining),
+            )),
+        }
+    }
+    pub fn remaining(&mut self) -> io::Result<u64> {
+        let position = self.reader.stream_position()?;
+        let length = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(position))?;
+
+        Ok(length.saturating_sub(position))
+    }
+
+    pub fn read_vec<V>(
+        &mut self,
+        count: usize,
+        mut read_element: impl FnMut(&mut Self) -> io::Result<V>,
+    ) -> io::Result<Vec<V>> {
+        let mut vector: Vec<V> = Vec::with_capacity(count);
+        for _ in 0..count {
+            vector.push(read_element(self)?);
+        }
+
+        Ok(vector)
+    }
+    /// Reads `count` structures with a fixed layout, see `BinDeserialize`
+    pub fn read_vec_of<V: BinDeserialize>(&mut self, count: usize) -> io::Result<Vec<V>> {
+        self.read_vec(count, V::read)
+    }
+    pub fn read_length_prefixed_vec<V>(
+        &mut self,
+        read_element: impl FnMut(&mut Self) -> io::Result<V>,
+    ) -> io::Result<Vec<V>> {
+        let count = self.read_u32()? as usize;
+
+        self.read_vec(count, read_element)
+    }
+
     pub fn read_bytes(&mut self, size: usize) -> io::Result<Vec<u8>> {
         let mut buffer = vec![0; size];
 
@@ -116,43 +248,116 @@ impl<T: Read + Seek> BinaryReader<T> {
 
         Ok(buffer)
     }
+    /// Like `read_bytes` but reuses the allocation of `buffer`, which is overwritten
+    pub fn read_bytes_into(&mut self, buffer: &mut Vec<u8>, size: usize) -> io::Result<()> {
+        buffer.clear();
+        buffer.resize(size, 0);
+
+        self.reader.read_exact(buffer)
+    }
+    /// Reads everything from the current position until the end of the stream
+    pub fn read_all(&mut self) -> io::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+
+        self.reader.read_to_end(&mut buffer)?;
+
+        Ok(buffer)
+    }
     pub fn read_string(&mut self, length: usize) -> io::Result<String> {
-        let buffer = self.read_bytes(length)?;
+        self.read_into_buffer(length)?;
 
-        match String::from_utf8(buffer) {
-            Ok(x) => Ok(x),
-            Err(error) => Err(io::Error::new(ErrorKind::InvalidData, error.to_string())),
-        }
+        BinaryReader::<T>::decode_string(&self.buffer)
     }
     pub fn read_sized_string(&mut self) -> io::Result<String> {
-        let length = self.read_u32()? as usize;
+        let length = self.read_count(1)?;
+
+        self.read_string(length)
+    }
+    pub fn read_sized_string_u16(&mut self) -> io::Result<String> {
+        let length = self.read_u16()? as usize;
 
         self.read_string(length)
     }
+    /// Replaces invalid UTF-8 sequences with `U+FFFD` instead of failing
+    pub fn read_string_lossy(&mut self, length: usize) -> io::Result<String> {
+        self.read_into_buffer(length)?;
+
+        Ok(String::from_utf8_lossy(&self.buffer).into_owned())
+    }
+    /// Everything after the first null byte is ignored, the whole string is used if there is none.
+    /// Decoded lossily if `strict_utf8` is turned off
     pub fn read_padded_string(&mut self, length: usize) -> io::Result<String> {
-        let string = self.read_string(length)?;
-        Ok(string[0..string.find('\0').unwrap()].to_string())
+        self.read_into_buffer(length)?;
+
+        let end = self.buffer.iter().position(|c| *c == 0).unwrap_or(length);
+        self.decode_buffer(end)
     }
+    /// Decoded lossily if `strict_utf8` is turned off
     pub fn read_null_terminated_string(&mut self) -> io::Result<String> {
-        let mut string = String::new();
-
-        let mut c: char;
-        loop {
-            c = self.read_char()?;
+        self.buffer.clear();
+        self.reader.read_until(0, &mut self.buffer)?;
 
-            match c {
-                '\0' => break,
-                _ => string.push(c),
-            }
+        match self.buffer.pop() {
+            Some(0) => self.decode_buffer(self.buffer.len()),
+            _ => Err(io::Error::new(ErrorKind::UnexpectedEof, "Missing null terminator")),
         }
+    }
 
-        Ok(string)
+    fn read_into_buffer(&mut self, length: usize) -> io::Result<()> {
+        self.buffer.clear();
+        self.buffer.resize(length, 0);
+
+        self.reader.read_exact(&mut self.buffer)
+    }
+    // Decodes the first `end` bytes of the scratch buffer according to `strict_utf8`
+    fn decode_buffer(&self, end: usize) -> io::Result<String> {
+        if self.strict_utf8 {
+            BinaryReader::<T>::decode_string(&self.buffer[..end])
+        } else {
+            Ok(String::from_utf8_lossy(&self.buffer[..end]).into_owned())
+        }
+    }
+    fn decode_string(bytes: &[u8]) -> io::Result<String> {
+        match std::str::from_utf8(bytes) {
+            Ok(x) => Ok(x.to_owned()),
+            Err(error) => Err(io::Error::new(ErrorKind::InvalidData, error.to_string())),
+        }
     }
 
     pub fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
         self.reader.seek(position)
     }
-    pub fn position(&mut self) -> u64 {
-        return self.reader.seek(SeekFrom::Current(0)).unwrap();
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.reader.stream_position()
+    }
+
+    /// Formats up to `length` bytes from the current position as an offset, hex and ASCII dump, 16 bytes a line.
+    /// Stops early at the end of the stream, the position is left where it was
+    pub fn hexdump(&mut self, length: usize) -> io::Result<String> {
+        let start = self.position()?;
+        let mut bytes = Vec::with_capacity(length.min(self.remaining()? as usize));
+        let read = (&mut self.reader).take(length as u64).read_to_end(&mut bytes);
+        self.reader.seek(SeekFrom::Start(start))?;
+        read?;
+
+        let mut dump = String::new();
+        for (i, line) in bytes.chunks(16).enumerate() {
+            let hex: Vec<String> = line.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = line
+                .iter()
+                .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+                .collect();
+
+            let (left, right) = hex.split_at(hex.len().min(8));
+            dump.push_str(&format!(
+                "{:08x}  {:<23}  {:<23}  |{}|\n",
+                start + i as u64 * 16,
+                left.join(" "),
+                right.join(" "),
+                ascii
+            ));
+        }
+
+        Ok(dump)
     }
 }
@@ -1,5 +1,7 @@
+use crate::io::serialize::BinSerialize;
 use std::fs::{File, OpenOptions};
 use std::io;
+use std::io::ErrorKind;
 use std::io::SeekFrom;
 use std::io::Write;
 use std::io::{BufWriter, Cursor, Seek};
@@ -10,15 +12,16 @@ pub struct BinaryWriter<T: Write = File> {
 }
 
 impl BinaryWriter<File> {
-    pub fn from_location(file_location: &Path) -> Self {
+    pub fn from_location(file_location: &Path) -> io::Result<Self> {
         let file = OpenOptions::new()
             .write(true)
             .create(true)
-            .open(file_location);
+            .truncate(true)
+            .open(file_location)?;
 
-        BinaryWriter {
-            writer: BufWriter::new(file.unwrap()),
-        }
+        Ok(BinaryWriter {
+            writer: BufWriter::new(file),
+        })
     }
 
     pub fn from_file(file: File) -> Self {
@@ -56,6 +59,13 @@ impl<T: Write + Seek> BinaryWriter<T> {
     pub fn write_u16(&mut self, to_write: u16) -> io::Result<usize> {
         self.writer.write(&to_write.to_le_bytes())
     }
+    /// Only the lower 24 bits are written
+    pub fn write_u24(&mut self, to_write: u32) -> io::Result<usize> {
+        self.writer.write(&to_write.to_le_bytes()[..3])
+    }
+    pub fn write_u24_be(&mut self, to_write: u32) -> io::Result<usize> {
+        self.writer.write(&to_write.to_be_bytes()[1..])
+    }
     pub fn write_i32(&mut self, to_write: i32) -> io::Result<usize> {
         self.writer.write(&to_write.to_le_bytes())
     }
@@ -75,9 +85,31 @@ impl<T: Write + Seek> BinaryWriter<T> {
         self.writer.write(&to_write.to_le_bytes())
     }
 
+    /// Converts the floats on the stack so they go out in a single write per 16 floats
+    pub fn write_f32_slice(&mut self, to_write: &[f32]) -> io::Result<usize> {
+        let mut buffer = [0u8; 64];
+
+        for chunk in to_write.chunks(16) {
+            for (bytes, value) in buffer.chunks_exact_mut(4).zip(chunk) {
+                bytes.copy_from_slice(&value.to_le_bytes());
+            }
+
+            self.writer.write_all(&buffer[..chunk.len() * 4])?;
+        }
+
+        Ok(to_write.len() * 4)
+    }
     pub fn write_bytes(&mut self, to_write: Vec<u8>) -> io::Result<usize> {
         self.writer.write(to_write.as_slice())
     }
+    /// Writes structures with a fixed layout back to back, see `BinSerialize`
+    pub fn write_slice_of<V: BinSerialize>(&mut self, to_write: &[V]) -> io::Result<()> {
+        for value in to_write {
+            value.write(self)?;
+        }
+
+        Ok(())
+    }
     pub fn write_string(&mut self, to_write: &str) -> io::Result<usize> {
         self.writer.write(to_write.as_bytes())
     }
@@ -95,6 +127,18 @@ impl<T: Write + Seek> BinaryWriter<T> {
         self.write_u32(to_write.len() as u32)?;
         self.write_string(to_write)
     }
+    /// Fails with `InvalidInput` if the string is longer than `u16::MAX` bytes
+    pub fn write_sized_string_u16(&mut self, to_write: &str) -> io::Result<usize> {
+        if to_write.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("String of {} bytes doesn't fit a u16 length", to_write.len()),
+            ));
+        }
+
+        self.write_u16(to_write.len() as u16)?;
+        self.write_string(to_write)
+    }
     pub fn write_null_terminated_string(&mut self, to_write: &str) -> io::Result<usize> {
         self.write_string(to_write)?;
         self.write_u8(0)
@@ -103,8 +147,17 @@ impl<T: Write + Seek> BinaryWriter<T> {
     pub fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
         self.writer.seek(position)
     }
-    pub fn position(&mut self) -> u64 {
-        return self.writer.seek(SeekFrom::Current(0)).unwrap();
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.writer.stream_position()
+    }
+
+    /// Dropping the writer flushes too, but swallows any error
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    /// Flushes the buffered bytes and returns the underlying writer
+    pub fn into_inner(self) -> io::Result<T> {
+        self.writer.into_inner().map_err(io::Error::from)
     }
 }
 
@@ -0,0 +1,188 @@
+use crate::error::Result;
+use crate::io::bin::{BinReader, BinTree, BinWriter};
+use crate::io::release_manifest::ReleaseManifest;
+use crate::io::simple_environment::SimpleEnvironment;
+use crate::io::simple_skin::SimpleSkin;
+use crate::io::static_object::StaticObject;
+use crate::io::world_geometry::WorldGeometry;
+use std::fs::File;
+use std::io;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FileKind {
+    SimpleSkin,
+    StaticObject,
+    WorldGeometry,
+    SimpleEnvironment,
+    Bin,
+    ReleaseManifest,
+    Skeleton,
+    Animation,
+    Wad,
+}
+
+/// A parsed file of any kind that can be read, there's no WAD reader yet so WAD files are only detected
+pub enum AnyAsset {
+    SimpleSkin(SimpleSkin),
+    StaticObject(StaticObject),
+    WorldGeometry(WorldGeometry),
+    SimpleEnvironment(SimpleEnvironment),
+    Bin(BinTree),
+    ReleaseManifest(ReleaseManifest),
+}
+
+impl FileKind {
+    /// Classifies a file by the magic in its first 8 bytes
+    pub fn detect(bytes: &[u8]) -> Option<FileKind> {
+        let starts_with = |magic: &[u8]| bytes.starts_with(magic);
+
+        if starts_with(b"r3d2Mesh") {
+            Some(FileKind::StaticObject)
+        } else if starts_with(b"r3d2sklt") {
+            Some(FileKind::Skeleton)
+        } else if starts_with(b"r3d2anmd") || starts_with(b"r3d2canm") {
+            Some(FileKind::Animation)
+        } else if starts_with(&0x00112233u32.to_le_bytes()) {
+            Some(FileKind::SimpleSkin)
+        } else if starts_with(b"WGEO") {
+            Some(FileKind::WorldGeometry)
+        } else if starts_with(b"NVR\0") {
+            Some(FileKind::SimpleEnvironment)
+        } else if starts_with(b"RMAN") {
+            Some(FileKind::ReleaseManifest)
+        } else if starts_with(b"PROP") || starts_with(b"PTCH") {
+            Some(FileKind::Bin)
+        } else if starts_with(b"RW") {
+            Some(FileKind::Wad)
+        } else if bytes.len() >= 8 && bytes[4..8] == 0x22FD4FC3u32.to_le_bytes() {
+            Some(FileKind::Skeleton)
+        } else {
+            None
+        }
+    }
+
+    pub fn detect_file(path: &Path) -> io::Result<Option<FileKind>> {
+        let mut magic: Vec<u8> = Vec::with_capacity(8);
+        File::open(path)?.take(8).read_to_end(&mut magic)?;
+
+        Ok(FileKind::detect(&magic))
+    }
+
+    /// Detects the kind of the file and reads it with the matching parser
+    pub fn read_from_file(path: &Path) -> Result<AnyAsset> {
+        AnyAsset::read_from_file(path)
+    }
+}
+
+impl AnyAsset {
+    /// Detects the kind of the file and reads it with the matching parser
+    pub fn read_from_file(path: &Path) -> Result<AnyAsset> {
+        let kind = FileKind::detect_file(path)?.ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidData, format!("Unrecognized file: {}", path.display()))
+        })?;
+
+        Ok(match kind {
+            FileKind::SimpleSkin => AnyAsset::SimpleSkin(SimpleSkin::read_from_file(path)?),
+            FileKind::StaticObject => AnyAsset::StaticObject(StaticObject::read_scb_from_file(path)?),
+            FileKind::WorldGeometry => AnyAsset::WorldGeometry(WorldGeometry::read_from_file(path)?),
+            FileKind::SimpleEnvironment => AnyAsset::SimpleEnvironment(SimpleEnvironment::read_file(path)?),
+            FileKind::Bin => AnyAsset::Bin(BinReader::read_tree_file(path)?),
+            FileKind::ReleaseManifest => AnyAsset::ReleaseManifest(ReleaseManifest::read_from_file(path)?),
+            FileKind::Skeleton | FileKind::Animation | FileKind::Wad => {
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    format!("{:?} files are detected but can't be read yet", kind),
+                )
+                .into());
+            }
+        })
+    }
+    /// Writes the asset in the format it was read from, environments and manifests have no writer yet
+    pub fn write_to_file(&mut self, path: &Path) -> Result<()> {
+        match self {
+            AnyAsset::SimpleSkin(simple_skin) => simple_skin.write_to_file(path),
+            AnyAsset::StaticObject(static_object) => static_object.write_scb_to_file(path),
+            AnyAsset::WorldGeometry(world_geometry) => world_geometry.write_to_file(path),
+            AnyAsset::Bin(tree) => BinWriter::write_tree_file(tree, path),
+            AnyAsset::SimpleEnvironment(_) | AnyAsset::ReleaseManifest(_) => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                format!("{:?} files can't be written yet", self.kind()),
+            )
+            .into()),
+        }
+    }
+
+    pub fn kind(&self) -> FileKind {
+        match self {
+            AnyAsset::SimpleSkin(_) => FileKind::SimpleSkin,
+            AnyAsset::StaticObject(_) => FileKind::StaticObject,
+            AnyAsset::WorldGeometry(_) => FileKind::WorldGeometry,
+            AnyAsset::SimpleEnvironment(_) => FileKind::SimpleEnvironment,
+            AnyAsset::Bin(_) => FileKind::Bin,
+            AnyAsset::ReleaseManifest(_) => FileKind::ReleaseManifest,
+        }
+    }
+
+    pub fn as_skin(&self) -> Option<&SimpleSkin> {
+        match self {
+            AnyAsset::SimpleSkin(simple_skin) => Some(simple_skin),
+            _ => None,
+        }
+    }
+    pub fn as_skin_mut(&mut self) -> Option<&mut SimpleSkin> {
+        match self {
+            AnyAsset::SimpleSkin(simple_skin) => Some(simple_skin),
+            _ => None,
+        }
+    }
+    pub fn as_static_object(&self) -> Option<&StaticObject> {
+        match self {
+            AnyAsset::StaticObject(static_object) => Some(static_object),
+            _ => None,
+        }
+    }
+    pub fn as_static_object_mut(&mut self) -> Option<&mut StaticObject> {
+        match self {
+            AnyAsset::StaticObject(static_object) => Some(static_object),
+            _ => None,
+        }
+    }
+    pub fn as_world_geometry(&self) -> Option<&WorldGeometry> {
+        match self {
+            AnyAsset::WorldGeometry(world_geometry) => Some(world_geometry),
+            _ => None,
+        }
+    }
+    pub fn as_world_geometry_mut(&mut self) -> Option<&mut WorldGeometry> {
+        match self {
+            AnyAsset::WorldGeometry(world_geometry) => Some(world_geometry),
+            _ => None,
+        }
+    }
+    pub fn as_simple_environment(&self) -> Option<&SimpleEnvironment> {
+        match self {
+            AnyAsset::SimpleEnvironment(simple_environment) => Some(simple_environment),
+            _ => None,
+        }
+    }
+    pub fn as_bin(&self) -> Option<&BinTree> {
+        match self {
+            AnyAsset::Bin(tree) => Some(tree),
+            _ => None,
+        }
+    }
+    pub fn as_bin_mut(&mut self) -> Option<&mut BinTree> {
+        match self {
+            AnyAsset::Bin(tree) => Some(tree),
+            _ => None,
+        }
+    }
+    pub fn as_manifest(&self) -> Option<&ReleaseManifest> {
+        match self {
+            AnyAsset::ReleaseManifest(manifest) => Some(manifest),
+            _ => None,
+        }
+    }
+}
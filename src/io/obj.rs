@@ -0,0 +1,159 @@
+use crate::structures::vector2::Vector2;
+use crate::structures::vector3::Vector3;
+use std::io;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::Path;
+
+/// A Wavefront OBJ with its faces grouped by `usemtl`, or by `g` for groups without a material
+pub(crate) struct ObjFile {
+    pub positions: Vec<Vector3>,
+    pub uvs: Vec<Vector2>,
+    pub normals: Vec<Vector3>,
+    pub groups: Vec<ObjGroup>,
+}
+
+pub(crate) struct ObjGroup {
+    pub name: String,
+    /// Faces with more than 3 corners are already triangulated as a fan
+    pub triangles: Vec<[ObjCorner; 3]>,
+}
+
+/// Zero based indices into the position, UV and normal lists
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct ObjCorner {
+    pub position: usize,
+    pub uv: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+impl ObjFile {
+    pub(crate) fn read_from_file(file_location: &Path) -> io::Result<Self> {
+        ObjFile::read(BufReader::new(std::fs::File::open(file_location)?))
+    }
+
+    pub(crate) fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut obj = ObjFile {
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            groups: Vec::new(),
+        };
+        let mut group_name = String::from("default");
+        let mut material: Option<String> = None;
+        let mut current_group: Option<usize> = None;
+
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = line_index + 1;
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => &line[..],
+            };
+
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            match keyword {
+                "v" => {
+                    let values = parse_floats(tokens, 3, line_number)?;
+                    obj.positions.push(Vector3::new(values[0], values[1], values[2]));
+                }
+                "vt" => {
+                    let values = parse_floats(tokens, 2, line_number)?;
+                    obj.uvs.push(Vector2::new(values[0], values[1]));
+                }
+                "vn" => {
+                    let values = parse_floats(tokens, 3, line_number)?;
+                    obj.normals.push(Vector3::new(values[0], values[1], values[2]));
+                }
+                "g" => {
+                    group_name = tokens.collect::<Vec<&str>>().join(" ");
+                    if group_name.is_empty() {
+                        group_name = String::from("default");
+                    }
+                    current_group = None;
+                }
+                "usemtl" => {
+                    material = Some(tokens.collect::<Vec<&str>>().join(" "));
+                    current_group = None;
+                }
+                "f" => {
+                    let corners = tokens
+                        .map(|token| obj.parse_corner(token, line_number))
+                        .collect::<io::Result<Vec<ObjCorner>>>()?;
+                    if corners.len() < 3 {
+                        return Err(invalid_line(line_number, "a face needs at least 3 vertices"));
+                    }
+
+                    let group = match current_group {
+                        Some(group) => group,
+                        None => {
+                            let name = material.as_ref().unwrap_or(&group_name);
+                            let group = match obj.groups.iter().position(|group| &group.name == name) {
+                                Some(group) => group,
+                                None => {
+                                    obj.groups.push(ObjGroup { name: name.clone(), triangles: Vec::new() });
+                                    obj.groups.len() - 1
+                                }
+                            };
+                            current_group = Some(group);
+                            group
+                        }
+                    };
+                    for i in 1..corners.len() - 1 {
+                        obj.groups[group].triangles.push([corners[0], corners[i], corners[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(obj)
+    }
+
+    fn parse_corner(&self, token: &str, line_number: usize) -> io::Result<ObjCorner> {
+        let mut parts = token.split('/');
+        let position = parse_index(parts.next(), self.positions.len(), line_number)?;
+        let uv = parse_index(parts.next(), self.uvs.len(), line_number)?;
+        let normal = parse_index(parts.next(), self.normals.len(), line_number)?;
+
+        match position {
+            Some(position) => Ok(ObjCorner { position, uv, normal }),
+            None => Err(invalid_line(line_number, "a face vertex needs a position")),
+        }
+    }
+}
+
+// OBJ indices start at 1, negative ones are relative to the end of the list so far
+fn parse_index(part: Option<&str>, count: usize, line_number: usize) -> io::Result<Option<usize>> {
+    let part = match part {
+        Some(part) if !part.is_empty() => part,
+        _ => return Ok(None),
+    };
+
+    let index: i64 = part.parse().map_err(|_| invalid_line(line_number, "invalid index"))?;
+    let index = if index < 0 { count as i64 + index } else { index - 1 };
+    if index < 0 || index >= count as i64 {
+        return Err(invalid_line(line_number, "index out of range"));
+    }
+
+    Ok(Some(index as usize))
+}
+
+fn parse_floats<'a, I: Iterator<Item = &'a str>>(tokens: I, count: usize, line_number: usize) -> io::Result<Vec<f32>> {
+    let values = tokens
+        .take(count)
+        .map(|token| token.parse::<f32>().map_err(|_| invalid_line(line_number, "invalid number")))
+        .collect::<io::Result<Vec<f32>>>()?;
+    if values.len() < count {
+        return Err(invalid_line(line_number, "not enough values"));
+    }
+
+    Ok(values)
+}
+
+fn invalid_line(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("OBJ line {}: {}", line_number, message))
+}
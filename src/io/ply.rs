@@ -0,0 +1,191 @@
+use crate::structures::color::to_u8;
+use palette::LinSrgba;
+use std::io;
+use std::io::Write;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Type of a property as it's declared in the header
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum PlyType {
+    Float,
+    UChar,
+}
+
+/// A property value of a vertex row
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum PlyValue {
+    Float(f32),
+    UChar(u8),
+}
+
+impl PlyType {
+    fn name(self) -> &'static str {
+        match self {
+            PlyType::Float => "float",
+            PlyType::UChar => "uchar",
+        }
+    }
+}
+
+pub(crate) const COLOR_PROPERTIES: [(&str, PlyType); 4] = [
+    ("red", PlyType::UChar),
+    ("green", PlyType::UChar),
+    ("blue", PlyType::UChar),
+    ("alpha", PlyType::UChar),
+];
+
+/// Vertex colors are stored as they are, vertices without one are written as white
+pub(crate) fn color_values(color: Option<LinSrgba>) -> [PlyValue; 4] {
+    let color = color.unwrap_or_else(|| LinSrgba::new(1.0, 1.0, 1.0, 1.0));
+
+    [
+        PlyValue::UChar(to_u8(color.color.red)),
+        PlyValue::UChar(to_u8(color.color.green)),
+        PlyValue::UChar(to_u8(color.color.blue)),
+        PlyValue::UChar(to_u8(color.alpha)),
+    ]
+}
+
+/// PLY has no groups, so the faces of each submesh are listed in a comment
+pub(crate) fn material_comment(name: &str, first_face: usize, face_count: usize) -> String {
+    format!("material {} faces {} {}", name, first_face, face_count)
+}
+
+/// A submesh or model, its indices point into its own vertices
+pub(crate) struct PlyPart<'a, V, I> {
+    /// Parts with a name get a `material` comment
+    pub(crate) name: Option<&'a str>,
+    pub(crate) vertices: &'a [V],
+    pub(crate) indices: &'a [I],
+}
+
+/// Writes `parts` as a single mesh, the vertices in order and the faces rebased onto them.
+/// `vertex_values` pushes the values of one vertex, matching `vertex_properties`
+pub(crate) fn write_parts<W: Write, V, I: Copy + Into<u32>>(
+    out: &mut W,
+    format: PlyFormat,
+    comments: &[String],
+    parts: &[PlyPart<V, I>],
+    vertex_properties: &[(&str, PlyType)],
+    mut vertex_values: impl FnMut(&V, &mut Vec<PlyValue>),
+) -> io::Result<()> {
+    let mut comments = comments.to_vec();
+    let mut face_count = 0;
+    for part in parts {
+        if let Some(name) = part.name {
+            comments.push(material_comment(name, face_count, part.indices.len() / 3));
+        }
+        face_count += part.indices.len() / 3;
+    }
+    let vertex_count = parts.iter().map(|part| part.vertices.len()).sum();
+
+    let mut ply = PlyWriter::new(
+        out,
+        format,
+        &comments,
+        vertex_count,
+        vertex_properties,
+        face_count,
+    )?;
+    let mut values = Vec::with_capacity(vertex_properties.len());
+    for vertex in parts.iter().flat_map(|part| part.vertices.iter()) {
+        values.clear();
+        vertex_values(vertex, &mut values);
+        ply.write_vertex(&values)?;
+    }
+
+    let mut base_vertex = 0u32;
+    for part in parts {
+        for face in part.indices.chunks_exact(3) {
+            ply.write_face([
+                face[0].into() + base_vertex,
+                face[1].into() + base_vertex,
+                face[2].into() + base_vertex,
+            ])?;
+        }
+
+        base_vertex += part.vertices.len() as u32;
+    }
+
+    Ok(())
+}
+
+/// Writes a PLY with a vertex element and a triangle face element, the vertices have to be written before the faces
+struct PlyWriter<'a, W: Write> {
+    out: &'a mut W,
+    format: PlyFormat,
+}
+
+impl<'a, W: Write> PlyWriter<'a, W> {
+    /// The values passed to `write_vertex` have to match `vertex_properties` in order and type
+    fn new(
+        out: &'a mut W,
+        format: PlyFormat,
+        comments: &[String],
+        vertex_count: usize,
+        vertex_properties: &[(&str, PlyType)],
+        face_count: usize,
+    ) -> io::Result<Self> {
+        writeln!(out, "ply")?;
+        match format {
+            PlyFormat::Ascii => writeln!(out, "format ascii 1.0")?,
+            PlyFormat::BinaryLittleEndian => writeln!(out, "format binary_little_endian 1.0")?,
+        }
+        for comment in comments {
+            writeln!(out, "comment {}", comment)?;
+        }
+
+        writeln!(out, "element vertex {}", vertex_count)?;
+        for (name, property_type) in vertex_properties {
+            writeln!(out, "property {} {}", property_type.name(), name)?;
+        }
+        writeln!(out, "element face {}", face_count)?;
+        writeln!(out, "property list uchar uint vertex_indices")?;
+        writeln!(out, "end_header")?;
+
+        Ok(PlyWriter { out, format })
+    }
+
+    fn write_vertex(&mut self, values: &[PlyValue]) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => {
+                for (i, value) in values.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { " " };
+                    match value {
+                        PlyValue::Float(value) => write!(self.out, "{}{}", separator, value)?,
+                        PlyValue::UChar(value) => write!(self.out, "{}{}", separator, value)?,
+                    }
+                }
+                writeln!(self.out)
+            }
+            PlyFormat::BinaryLittleEndian => {
+                for value in values {
+                    match value {
+                        PlyValue::Float(value) => self.out.write_all(&value.to_le_bytes())?,
+                        PlyValue::UChar(value) => self.out.write_all(&[*value])?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_face(&mut self, face: [u32; 3]) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => writeln!(self.out, "3 {} {} {}", face[0], face[1], face[2]),
+            PlyFormat::BinaryLittleEndian => {
+                let mut buffer = [3u8; 13];
+                buffer[1..5].copy_from_slice(&face[0].to_le_bytes());
+                buffer[5..9].copy_from_slice(&face[1].to_le_bytes());
+                buffer[9..13].copy_from_slice(&face[2].to_le_bytes());
+
+                self.out.write_all(&buffer)
+            }
+        }
+    }
+}
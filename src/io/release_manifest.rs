@@ -1,41 +1,56 @@
+use crate::error::{Error, Result};
 use crate::io::binary_reader::BinaryReader;
+use crate::utilities::checksum::xxhash64;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use std::fs::File;
+use std::collections::HashMap;
 use std::io;
-use std::io::{Cursor, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::Path;
 
 extern crate zstd;
 
+#[derive(PartialEq, Debug)]
 pub struct ReleaseManifest {
     release_id: u64,
+    signature: Vec<u8>,
     bundles: Vec<ReleaseManifestBundle>,
     languages: Vec<ReleaseManifestLanguage>,
     files: Vec<ReleaseManifestFile>,
     directories: Vec<ReleaseManifestDirectory>,
 }
 
-struct ReleaseManifestBody {
+pub(crate) struct ReleaseManifestBody {
     bundles: Vec<ReleaseManifestBundle>,
     languages: Vec<ReleaseManifestLanguage>,
     files: Vec<ReleaseManifestFile>,
     directories: Vec<ReleaseManifestDirectory>,
 }
 
+#[derive(PartialEq, Debug)]
 pub struct ReleaseManifestBundle {
     id: u64,
     chunks: Vec<ReleaseManifestBundleChunk>,
 }
 
+#[derive(PartialEq, Debug)]
 pub struct ReleaseManifestBundleChunk {
     compressed_size: u32,
     uncompressed_size: u32,
     id: u64,
+    offset: u64,
 }
 
+#[derive(PartialEq, Debug)]
 pub struct ReleaseManifestLanguage {
     id: u32,
     name: String,
 }
 
+#[derive(PartialEq, Debug)]
 pub struct ReleaseManifestFile {
     name: String,
     link: String,
@@ -46,6 +61,7 @@ pub struct ReleaseManifestFile {
     chunk_ids: Vec<u64>,
 }
 
+#[derive(PartialEq, Debug)]
 pub struct ReleaseManifestDirectory {
     name: String,
     id: u64,
@@ -53,25 +69,80 @@ pub struct ReleaseManifestDirectory {
 }
 
 impl ReleaseManifest {
-    pub fn read_from_file(file_location: &Path) -> io::Result<Self> {
-        ReleaseManifest::read(&mut BinaryReader::from_location(file_location))
+    pub fn new(
+        release_id: u64,
+        bundles: Vec<ReleaseManifestBundle>,
+        languages: Vec<ReleaseManifestLanguage>,
+        files: Vec<ReleaseManifestFile>,
+        directories: Vec<ReleaseManifestDirectory>,
+    ) -> Self {
+        ReleaseManifest {
+            release_id,
+            signature: Vec::new(),
+            bundles,
+            languages,
+            files,
+            directories,
+        }
+    }
+    pub fn read_from_file(file_location: &Path) -> Result<Self> {
+        ReleaseManifest::read(&mut BinaryReader::from_location(file_location)?)
     }
-    pub fn read_from_buffer(buffer: Cursor<Vec<u8>>) -> io::Result<Self> {
+    pub fn read_from_buffer(buffer: Cursor<Vec<u8>>) -> Result<Self> {
         ReleaseManifest::read(&mut BinaryReader::from_buffer(buffer))
     }
-    pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
+    pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> Result<Self> {
+        ReleaseManifest::read_with_progress(reader, &mut |_, _| {})
+    }
+    /// `progress` is called with (done, total) after every bundle, language, file and directory
+    pub fn read_with_progress<T: Read + Seek>(
+        reader: &mut BinaryReader<T>,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<Self> {
+        let (release_id, signature, body) = ReleaseManifest::read_content(reader)?;
+        let body = ReleaseManifest::read_body(&body, progress)?;
+
+        Ok(ReleaseManifest {
+            release_id,
+            signature,
+            bundles: body.bundles,
+            languages: body.languages,
+            files: body.files,
+            directories: body.directories,
+        })
+    }
+    /// Same as `read_from_file` but parses the file and directory tables on multiple threads
+    #[cfg(feature = "rayon")]
+    pub fn read_from_file_parallel(file_location: &Path) -> Result<Self> {
+        let mut reader = BinaryReader::from_file(File::open(file_location)?);
+        let (release_id, signature, body) = ReleaseManifest::read_content(&mut reader)?;
+
+        let mut reader = BinaryReader::new(Cursor::new(&body[..]));
+        let (bundles_offset, languages_offset, files_offset, directories_offset) =
+            ReleaseManifest::read_body_offsets(&mut reader)?;
+
+        Ok(ReleaseManifest {
+            release_id,
+            signature,
+            bundles: ReleaseManifest::read_body_bundles(bundles_offset, &mut reader, &mut || {})?,
+            languages: ReleaseManifest::read_body_languages(languages_offset, &mut reader, &mut || {})?,
+            files: ReleaseManifest::read_body_table_parallel(&body, files_offset, ReleaseManifestFile::read)?,
+            directories: ReleaseManifest::read_body_table_parallel(&body, directories_offset, ReleaseManifestDirectory::read)?,
+        })
+    }
+    fn read_content<T: Read + Seek>(reader: &mut BinaryReader<T>) -> Result<(u64, Vec<u8>, Vec<u8>)> {
         let magic = reader.read_string(4)?;
         if &magic != "RMAN" {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Incorrect file signature",
-            ));
+            return Err(Error::InvalidMagic {
+                expected: String::from("RMAN"),
+                found: magic,
+            });
         }
 
         let major = reader.read_u8()?;
         let minor = reader.read_u8()?;
         if major != 2 || minor != 0 {
-            return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+            return Err(Error::UnsupportedVersion { major: major as u32, minor: minor as u32 });
         }
 
         let unknown = reader.read_u8()?;
@@ -92,102 +163,141 @@ impl ReleaseManifest {
             &mut uncompressed_manifest_body,
         )?;
 
-        let signature: Vec<u8> = reader.read_bytes(256)?;
-        let body = ReleaseManifest::read_body(&mut uncompressed_manifest_body)?;
+        let signature = reader.read_bytes(256)?;
 
-        Ok(ReleaseManifest {
-            release_id,
-            bundles: body.bundles,
-            languages: body.languages,
-            files: body.files,
-            directories: body.directories,
+        Ok((release_id, signature, uncompressed_manifest_body.into_inner()))
+    }
+    pub(crate) fn read_body(body: &[u8], progress: &mut dyn FnMut(u64, u64)) -> io::Result<ReleaseManifestBody> {
+        let mut reader = BinaryReader::new(Cursor::new(body));
+        let (bundles_offset, languages_offset, files_offset, directories_offset) =
+            ReleaseManifest::read_body_offsets(&mut reader)?;
+
+        let mut total = 0u64;
+        for offset in &[bundles_offset, languages_offset, files_offset, directories_offset] {
+            reader.seek(SeekFrom::Start(*offset))?;
+            total += reader.read_u32()? as u64;
+        }
+
+        let mut done = 0u64;
+        let mut on_record = || {
+            done += 1;
+            progress(done, total);
+        };
+
+        Ok(ReleaseManifestBody {
+            bundles: ReleaseManifest::read_body_bundles(bundles_offset, &mut reader, &mut on_record)?,
+            languages: ReleaseManifest::read_body_languages(languages_offset, &mut reader, &mut on_record)?,
+            files: ReleaseManifest::read_body_files(files_offset, &mut reader, &mut on_record)?,
+            directories: ReleaseManifest::read_body_directories(directories_offset, &mut reader, &mut on_record)?,
         })
     }
-    fn read_body(body_buffer: &mut Cursor<Vec<u8>>) -> io::Result<ReleaseManifestBody> {
-        let mut reader = BinaryReader::from_buffer(body_buffer.to_owned());
+    fn read_body_offsets<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<(u64, u64, u64, u64)> {
         reader.seek(SeekFrom::Start(0))?;
 
         let header_offset = reader.read_u32()? as u64;
 
         reader.seek(SeekFrom::Start(header_offset))?;
         let offset_table_offset = reader.read_u32()? as u64;
-        let bundles_offset = reader.position() + reader.read_u32()? as u64;
-        let languages_offset = reader.position() + reader.read_u32()? as u64;
-        let files_offset = reader.position() + reader.read_u32()? as u64;
-        let directories_offset = reader.position() + reader.read_u32()? as u64;
-        let key_header_offset = reader.position() + reader.read_u32()? as u64;
-        let unknown_offset = reader.position() + reader.read_u32()? as u64;
+        let bundles_offset = read_relative_offset(reader)?;
+        let languages_offset = read_relative_offset(reader)?;
+        let files_offset = read_relative_offset(reader)?;
+        let directories_offset = read_relative_offset(reader)?;
+        let key_header_offset = read_relative_offset(reader)?;
+        let unknown_offset = read_relative_offset(reader)?;
+
+        Ok((bundles_offset, languages_offset, files_offset, directories_offset))
+    }
+    #[cfg(feature = "rayon")]
+    fn read_body_table_parallel<'a, V: Send>(
+        body: &'a [u8],
+        offset: u64,
+        read_record: fn(&mut BinaryReader<Cursor<&'a [u8]>>) -> io::Result<V>,
+    ) -> io::Result<Vec<V>> {
+        let mut reader = BinaryReader::new(Cursor::new(body));
+        reader.seek(SeekFrom::Start(offset))?;
 
-        Ok(ReleaseManifestBody {
-            bundles: ReleaseManifest::read_body_bundles(bundles_offset, &mut reader)?,
-            languages: ReleaseManifest::read_body_languages(languages_offset, &mut reader)?,
-            files: ReleaseManifest::read_body_files(files_offset, &mut reader)?,
-            directories: ReleaseManifest::read_body_directories(directories_offset, &mut reader)?,
-        })
+        // Every record is at an independent offset so each thread can use its own reader
+        let record_count = reader.read_count(4)?;
+        let record_offsets = reader.read_vec(record_count, read_relative_offset)?;
+
+        record_offsets
+            .par_iter()
+            .map_init(
+                || BinaryReader::new(Cursor::new(body)),
+                |reader, record_offset| {
+                    reader.seek(SeekFrom::Start(*record_offset))?;
+                    read_record(reader)
+                },
+            )
+            .collect()
     }
     fn read_body_bundles<T: Read + Seek>(
         offset: u64,
         reader: &mut BinaryReader<T>,
+        on_record: &mut dyn FnMut(),
     ) -> io::Result<Vec<ReleaseManifestBundle>> {
         reader.seek(SeekFrom::Start(offset))?;
 
-        let bundle_count = reader.read_u32()?;
-        let mut bundles: Vec<ReleaseManifestBundle> = Vec::with_capacity(bundle_count as usize);
-        for i in 0..bundle_count {
-            let bundle_offset = reader.read_u32()? as u64;
-            let return_offset = reader.position();
+        let bundle_count = reader.read_count(4)?;
+        reader.read_vec(bundle_count, |reader| {
+            let bundle_offset = read_relative_offset(reader)?;
+            let return_offset = reader.position()?;
 
-            reader.seek(SeekFrom::Start(bundle_offset + return_offset - 4))?;
-            bundles.push(ReleaseManifestBundle::read(reader)?);
+            reader.seek(SeekFrom::Start(bundle_offset))?;
+            let bundle = ReleaseManifestBundle::read(reader)?;
             reader.seek(SeekFrom::Start(return_offset))?;
-        }
+            on_record();
 
-        Ok(bundles)
+            Ok(bundle)
+        })
     }
-    fn read_body_languages<T: Read + Seek>(offset: u64, reader: &mut BinaryReader<T>) -> io::Result<Vec<ReleaseManifestLanguage>> {
+    fn read_body_languages<T: Read + Seek>(offset: u64, reader: &mut BinaryReader<T>, on_record: &mut dyn FnMut()) -> io::Result<Vec<ReleaseManifestLanguage>> {
         reader.seek(SeekFrom::Start(offset))?;
 
-        let language_count = reader.read_u32()?;
+        let language_count = reader.read_count(4)?;
         let mut languages: Vec<ReleaseManifestLanguage> = Vec::with_capacity(language_count as usize);
         for i in 0..language_count {
-            let language_offset = reader.read_u32()? as u64;
-            let return_offset = reader.position();
+            let language_offset = read_relative_offset(reader)?;
+            let return_offset = reader.position()?;
 
-            reader.seek(SeekFrom::Start(language_offset + return_offset - 4))?;
+            reader.seek(SeekFrom::Start(language_offset))?;
             languages.push(ReleaseManifestLanguage::read(reader)?);
             reader.seek(SeekFrom::Start(return_offset))?;
+            on_record();
         }
 
         Ok(languages)
     }
-    fn read_body_files<T: Read + Seek>(offset: u64, reader: &mut BinaryReader<T>) -> io::Result<Vec<ReleaseManifestFile>> {
+    fn read_body_files<T: Read + Seek>(offset: u64, reader: &mut BinaryReader<T>, on_record: &mut dyn FnMut()) -> io::Result<Vec<ReleaseManifestFile>> {
         reader.seek(SeekFrom::Start(offset))?;
 
-        let file_count = reader.read_u32()?;
+        let file_count = reader.read_count(4)?;
         let mut files: Vec<ReleaseManifestFile> = Vec::with_capacity(file_count as usize);
         for i in 0..file_count {
-            let file_offset = reader.read_u32()? as u64;
-            let return_offset = reader.position();
+            let file_offset = read_relative_offset(reader)?;
+            let return_offset = reader.position()?;
 
-            reader.seek(SeekFrom::Start(file_offset + return_offset - 4))?;
+            reader.seek(SeekFrom::Start(file_offset))?;
             files.push(ReleaseManifestFile::read(reader)?);
             reader.seek(SeekFrom::Start(return_offset))?;
+            on_record();
         }
 
         Ok(files)
     }
-    fn read_body_directories<T: Read + Seek>(offset: u64, reader: &mut BinaryReader<T>) -> io::Result<Vec<ReleaseManifestDirectory>> {
+    fn read_body_directories<T: Read + Seek>(offset: u64, reader: &mut BinaryReader<T>, on_record: &mut dyn FnMut()) -> io::Result<Vec<ReleaseManifestDirectory>> {
         reader.seek(SeekFrom::Start(offset))?;
 
-        let directory_count = reader.read_u32()?;
+        let directory_count = reader.read_count(4)?;
         let mut directories: Vec<ReleaseManifestDirectory> = Vec::with_capacity(directory_count as usize);
         for i in 0..directory_count {
-            let directory_offset = reader.read_u32()? as u64;
-            let return_offset = reader.position();
+            let directory_offset = read_relative_offset(reader)?;
+            let return_offset = reader.position()?;
 
-            reader.seek(SeekFrom::Start(directory_offset + return_offset - 4))?;
+            reader.seek(SeekFrom::Start(directory_offset))?;
             directories.push(ReleaseManifestDirectory::read(reader)?);
             reader.seek(SeekFrom::Start(return_offset))?;
+            on_record();
         }
 
         Ok(directories)
@@ -196,42 +306,136 @@ impl ReleaseManifest {
     pub fn release_id(&self) -> u64 {
         self.release_id
     }
+    /// The raw 256 byte RSA signature following the compressed body, it isn't verified.
+    /// Empty for manifests created with `new`
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
     pub fn bundles(&self) -> &[ReleaseManifestBundle] { &self.bundles }
     pub fn languages(&self) -> &[ReleaseManifestLanguage] { &self.languages }
     pub fn files(&self) -> &[ReleaseManifestFile] { &self.files }
     pub fn directories(&self) -> &[ReleaseManifestDirectory] { &self.directories }
+
+    /// Looks the chunk up across all bundles
+    pub fn chunk_by_id(&self, id: u64) -> Option<&ReleaseManifestBundleChunk> {
+        self.bundles
+            .iter()
+            .flat_map(|bundle| bundle.chunks.iter())
+            .find(|chunk| chunk.id == id)
+    }
+
+    /// Rebuilds the file from its chunks, `fetch` has to return the compressed data of the chunk with the given id
+    pub fn assemble_file(
+        &self,
+        file: &ReleaseManifestFile,
+        fetch: impl Fn(u64) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Vec<u8>> {
+        let chunks: HashMap<u64, &ReleaseManifestBundleChunk> = self
+            .bundles
+            .iter()
+            .flat_map(|bundle| bundle.chunks.iter())
+            .map(|chunk| (chunk.id, chunk))
+            .collect();
+
+        let mut data: Vec<u8> = Vec::with_capacity(file.size as usize);
+        for chunk_id in &file.chunk_ids {
+            let chunk = chunks.get(chunk_id).ok_or_else(|| {
+                io::Error::new(ErrorKind::InvalidData, format!("Chunk {:016X} isn't in any bundle", chunk_id))
+            })?;
+
+            let compressed = fetch(*chunk_id)?;
+            if compressed.len() != chunk.compressed_size as usize {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Chunk {:016X} is {} bytes, expected {}", chunk_id, compressed.len(), chunk.compressed_size),
+                ));
+            }
+
+            let start = data.len();
+            zstd::stream::copy_decode(compressed.as_slice(), &mut data)?;
+            if data.len() - start != chunk.uncompressed_size as usize {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Chunk {:016X} decompressed to {} bytes, expected {}", chunk_id, data.len() - start, chunk.uncompressed_size),
+                ));
+            }
+        }
+
+        if data.len() != file.size as usize {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("File {} assembled to {} bytes, expected {}", file.name, data.len(), file.size),
+            ));
+        }
+
+        Ok(data)
+    }
 }
 
 impl ReleaseManifestBundle {
+    /// The chunk offsets are computed from the order and compressed sizes of `chunks`
+    pub fn new(id: u64, mut chunks: Vec<ReleaseManifestBundleChunk>) -> Self {
+        ReleaseManifestBundle::compute_chunk_offsets(&mut chunks);
+
+        ReleaseManifestBundle { id, chunks }
+    }
     fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         reader.read_u32()?; //read offset table offset
         let header_size = reader.read_u32()?;
         let id = reader.read_u64()?;
 
         //Skip rest of header
-        reader.seek(SeekFrom::Current((header_size - 12) as i64))?;
+        let header_rest = header_size
+            .checked_sub(12)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Bundle header is too small"))?;
+        reader.seek(SeekFrom::Current(header_rest as i64))?;
 
-        let chunk_count = reader.read_u32()?;
+        let chunk_count = reader.read_count(4)?;
         let mut chunks: Vec<ReleaseManifestBundleChunk> = Vec::with_capacity(chunk_count as usize);
         for i in 0..chunk_count {
-            let chunk_offset = reader.read_u32()? as u64;
-            let return_offset = reader.position();
+            let chunk_offset = read_relative_offset(reader)?;
+            let return_offset = reader.position()?;
 
-            reader.seek(SeekFrom::Start(chunk_offset + return_offset - 4))?;
+            reader.seek(SeekFrom::Start(chunk_offset))?;
             chunks.push(ReleaseManifestBundleChunk::read(reader)?);
             reader.seek(SeekFrom::Start(return_offset))?;
         }
 
+        ReleaseManifestBundle::compute_chunk_offsets(&mut chunks);
+
         Ok(ReleaseManifestBundle { id, chunks })
     }
+    /// Chunks are stored back to back in the bundle
+    fn compute_chunk_offsets(chunks: &mut [ReleaseManifestBundleChunk]) {
+        let mut offset = 0u64;
+        for chunk in chunks {
+            chunk.offset = offset;
+            offset += chunk.compressed_size as u64;
+        }
+    }
 
     pub fn id(&self) -> u64 {
         self.id
     }
     pub fn chunks(&self) -> &[ReleaseManifestBundleChunk] { &self.chunks }
+    /// The name of the bundle on the CDN
+    pub fn download_path(&self) -> String {
+        format!("{:016X}.bundle", self.id)
+    }
+    pub fn chunk_offset(&self, id: u64) -> Option<u64> {
+        self.chunks.iter().find(|chunk| chunk.id == id).map(|chunk| chunk.offset)
+    }
 }
 
 impl ReleaseManifestBundleChunk {
+    pub fn new(id: u64, compressed_size: u32, uncompressed_size: u32) -> Self {
+        ReleaseManifestBundleChunk {
+            compressed_size,
+            uncompressed_size,
+            id,
+            offset: 0,
+        }
+    }
     fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         reader.read_u32()?; // offset table offset
 
@@ -239,12 +443,22 @@ impl ReleaseManifestBundleChunk {
             compressed_size: reader.read_u32()?,
             uncompressed_size: reader.read_u32()?,
             id: reader.read_u64()?,
+            offset: 0,
         })
     }
 
     pub fn id(&self) -> u64 { self.id }
     pub fn compressed_size(&self) -> u32 { self.compressed_size }
     pub fn uncompressed_size(&self) -> u32 { self.uncompressed_size }
+    /// Offset of the compressed data within the bundle
+    pub fn offset(&self) -> u64 { self.offset }
+    /// Byte range of the compressed data within the bundle, for HTTP range requests
+    pub fn byte_range(&self) -> Range<u64> { self.offset..self.offset + self.compressed_size as u64 }
+
+    /// Checks downloaded compressed chunk data against its size and its id, which is the xxHash64 of the data
+    pub fn verify(&self, data: &[u8]) -> bool {
+        data.len() == self.compressed_size as usize && xxhash64(data) == self.id
+    }
 }
 
 impl ReleaseManifestLanguage {
@@ -252,10 +466,10 @@ impl ReleaseManifestLanguage {
         reader.read_u32()?; //offset table offset
         let id = reader.read_u32()?;
 
-        let name_offset = reader.read_u32()? as u64;
-        let return_offset = reader.position();
+        let name_offset = read_relative_offset(reader)?;
+        let return_offset = reader.position()?;
 
-        reader.seek(SeekFrom::Start(name_offset + return_offset - 4))?;
+        reader.seek(SeekFrom::Start(name_offset))?;
         let name = reader.read_sized_string()?;
         reader.seek(SeekFrom::Start(return_offset))?;
 
@@ -267,16 +481,35 @@ impl ReleaseManifestLanguage {
 }
 
 impl ReleaseManifestFile {
+    pub fn new(
+        name: String,
+        link: String,
+        id: u64,
+        directory_id: u64,
+        size: u32,
+        language_ids: Vec<u32>,
+        chunk_ids: Vec<u64>,
+    ) -> Self {
+        ReleaseManifestFile {
+            name,
+            link,
+            id,
+            directory_id,
+            size,
+            language_ids,
+            chunk_ids,
+        }
+    }
     fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         reader.read_u32()?; //offset table offset
 
-        let file_offset = reader.position();
+        let file_offset = reader.position()?;
         let flags = reader.read_u32()?;
         let file_type = flags >> 24;
         let name_offset = if flags == 0x00010200 || file_type != 0 {
-            reader.read_u32()? as u64
+            reader.read_u32()? as i64
         } else {
-            (flags - 4) as u64
+            flags as i64 - 4
         };
 
         let structure_size = reader.read_u32()?;
@@ -305,16 +538,13 @@ impl ReleaseManifestFile {
 
         let unknown1 = reader.read_u32()?;
 
-        let chunk_count = reader.read_u32()?;
-        let mut chunk_ids: Vec<u64> = Vec::with_capacity(chunk_count as usize);
-        for i in 0..chunk_count {
-            chunk_ids.push(reader.read_u64()?);
-        }
+        let chunk_count = reader.read_count(8)?;
+        let chunk_ids = reader.read_vec(chunk_count, |reader| reader.read_u64())?;
 
-        reader.seek(SeekFrom::Start(file_offset + name_offset + 4))?;
+        reader.seek(SeekFrom::Start(offset_from(file_offset, name_offset + 4)?))?;
         let name = reader.read_sized_string()?;
 
-        reader.seek(SeekFrom::Start(file_offset + link_offset + 12))?;
+        reader.seek(SeekFrom::Start(offset_from(file_offset, link_offset as i64 + 12)?))?;
         let link = reader.read_sized_string()?;
 
         Ok(ReleaseManifestFile {
@@ -340,11 +570,9 @@ impl ReleaseManifestFile {
 impl ReleaseManifestDirectory {
     fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         let offset_table_offset = reader.read_i32()?;
-        let directory_offset = reader.position();
+        let directory_offset = reader.position()?;
 
-        reader.seek(SeekFrom::Start(
-            (directory_offset as i64 - offset_table_offset as i64) as u64,
-        ))?;
+        reader.seek(SeekFrom::Start(offset_from(directory_offset, -(offset_table_offset as i64))?))?;
 
         let id_offset = reader.read_u16()? as u64;
         let parent_id_offset = reader.read_u16()? as u64;
@@ -363,7 +591,7 @@ impl ReleaseManifestDirectory {
             0
         };
 
-        reader.seek(SeekFrom::Start(directory_offset + name_offset))?;
+        reader.seek(SeekFrom::Start(offset_from(directory_offset, name_offset as i64)?))?;
         let name = reader.read_sized_string()?;
 
         Ok(ReleaseManifestDirectory {
@@ -377,3 +605,26 @@ impl ReleaseManifestDirectory {
     pub fn id(&self) -> u64 { self.id }
     pub fn parent_id(&self) -> u64 { self.parent_id }
 }
+
+// Offsets in malformed files must not wrap around, seeking past the end is fine since the next read fails
+fn offset_from(base: u64, offset: i64) -> io::Result<u64> {
+    let target = if offset < 0 {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    } else {
+        base.checked_add(offset as u64)
+    };
+
+    target.ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Offset {} from {} is out of range", offset, base),
+        )
+    })
+}
+/// Reads an offset relative to its own position and returns the position it points to
+fn read_relative_offset<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<u64> {
+    let position = reader.position()?;
+    let offset = reader.read_u32()?;
+
+    offset_from(position, offset as i64)
+}
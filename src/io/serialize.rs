@@ -0,0 +1,39 @@
+use crate::io::binary_reader::BinaryReader;
+use crate::io::binary_writer::BinaryWriter;
+use crate::structures::box3d::Box3D;
+use crate::structures::quaternion::Quaternion;
+use crate::structures::render_bucket_grid::RenderBucket;
+use crate::structures::sphere::Sphere;
+use crate::structures::vector2::Vector2;
+use crate::structures::vector3::Vector3;
+use crate::structures::vector4::Vector4;
+use std::io;
+use std::io::{Read, Seek, Write};
+
+/// Structures with a single fixed binary layout, colors aren't included since every format encodes them differently
+pub trait BinDeserialize: Sized {
+    fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
+}
+pub trait BinSerialize {
+    fn write<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()>;
+}
+
+// The inherent read/write stay so callers don't need the traits in scope
+macro_rules! impl_bin_serialize {
+    ($($structure:ty),*) => {
+        $(
+            impl BinDeserialize for $structure {
+                fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+                    <$structure>::read(reader)
+                }
+            }
+            impl BinSerialize for $structure {
+                fn write<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()> {
+                    <$structure>::write(self, writer)
+                }
+            }
+        )*
+    };
+}
+
+impl_bin_serialize!(Vector2, Vector3, Vector4, Quaternion, Box3D, Sphere, RenderBucket);
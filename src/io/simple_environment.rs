@@ -1,5 +1,6 @@
 use std::io;
 use std::io::{Seek, Read, Cursor, SeekFrom};
+use crate::error::{Error, Result};
 use crate::io::binary_reader::BinaryReader;
 use crate::utilities::version::Version;
 use crate::structures::vector3::Vector3;
@@ -18,6 +19,7 @@ use crate::structures::vector2::Vector2;
 
 #[derive(Debug)]
 pub struct SimpleEnvironment {
+    materials: Vec<SimpleEnvironmentMaterial>,
     meshes: Vec<SimpleEnvironmentMesh>
 }
 
@@ -104,35 +106,38 @@ enum SimpleEnvironmentMeshGeometryType {
     Complex
 }
 
-#[derive(FromPrimitive, ToPrimitive)]
+/// The environment quality setting, as stored in the mesh `quality` field
+#[derive(FromPrimitive, ToPrimitive, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SimpleEnvironmentQuality {
-    VeryLow = 0,
-    Low = 1,
-    Medium = 2,
-    High = 3,
-    VeryHigh = 4
+    VeryLow = -1,
+    Low = 0,
+    Medium = 1,
+    High = 2,
+    VeryHigh = 3
 }
 
 impl SimpleEnvironment {
-    pub fn read_file(path: &Path) -> io::Result<Self> {
-        SimpleEnvironment::read(&mut BinaryReader::from_location(path))
+    pub fn read_file(path: &Path) -> Result<Self> {
+        SimpleEnvironment::read(&mut BinaryReader::from_location(path)?)
     }
-    pub fn read_buffer(buffer: Cursor<Vec<u8>>) -> io::Result<Self> {
+    pub fn read_buffer(buffer: Cursor<Vec<u8>>) -> Result<Self> {
         SimpleEnvironment::read(&mut BinaryReader::from_buffer(buffer))
     }
-    fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+    fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> Result<Self> {
         let magic = reader.read_string(4)?;
         if &magic != "NVR\0" {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid magic"));
+            return Err(Error::InvalidMagic {
+                expected: String::from("NVR\0"),
+                found: magic,
+            });
         }
 
         let version = Version {
             major: reader.read_u16()? as u8,
             minor: reader.read_u16()? as u8
         };
-        if (version.major != 8 && version.minor != 1) &&
-            (version.major != 9 && version.minor != 1) {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported version"));
+        if version != Version::new(8, 1) && version != Version::new(9, 1) {
+            return Err(Error::UnsupportedVersion { major: version.major as u32, minor: version.minor as u32 });
         }
 
         let material_count = reader.read_u32()? as usize;
@@ -148,9 +153,22 @@ impl SimpleEnvironment {
                                                     &materials, &vertex_buffers, &index_buffers)?;
 
         Ok(SimpleEnvironment {
+            materials,
             meshes
         })
     }
+
+    pub fn materials(&self) -> &[SimpleEnvironmentMaterial] {
+        &self.materials
+    }
+    pub fn meshes(&self) -> &[SimpleEnvironmentMesh] {
+        &self.meshes
+    }
+    /// The meshes which are drawn when the environment quality is set to `quality`
+    pub fn meshes_for_quality(&self, quality: SimpleEnvironmentQuality) -> impl Iterator<Item = &SimpleEnvironmentMesh> {
+        self.meshes.iter().filter(move |mesh| mesh.is_drawn_at(quality))
+    }
+
     fn read_materials<R: Read + Seek>(reader: &mut BinaryReader<R>, material_count: usize, version: Version)
         -> io::Result<Vec<SimpleEnvironmentMaterial>>
     {
@@ -167,7 +185,7 @@ impl SimpleEnvironment {
         let mut vertex_buffers: Vec<SimpleEnvironmentVertexBuffer> = Vec::with_capacity(vertex_buffer_count);
         for _ in 0..vertex_buffer_count {
             let size = reader.read_u32()?;
-            let offset = reader.position();
+            let offset = reader.position()?;
 
             vertex_buffers.push(SimpleEnvironmentVertexBuffer{
                 size,
@@ -229,7 +247,7 @@ impl SimpleEnvironmentMaterial {
         let material_type = SimpleEnvironmentMaterialType::from_u32(reader.read_u32()?)
             .ok_or(io::Error::new(io::ErrorKind::InvalidData, "Invalid Material Type"))?;
 
-        if version.major == 8 && version.minor == 1 {
+        if version == Version::new(8, 1) {
             let diffuse_color = LinSrgba::read_rgba_f32(reader)?;
             let diffuse_texture = reader.read_padded_string(260)?;
 
@@ -271,21 +289,35 @@ impl SimpleEnvironmentMaterial {
         }
     }
 
-    fn contains_ground_keyword(texture: &str) -> bool {
-        texture.contains("_floor") ||
-            texture.contains("_dirt") ||
-            texture.contains("grass") ||
-            texture.contains("RiverBed") ||
-            texture.contains("_project") ||
-            texture.contains("tile_")
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn material_type(&self) -> &SimpleEnvironmentMaterialType {
+        &self.material_type
+    }
+    pub fn flags(&self) -> SimpleEnvironmentMaterialFlags {
+        self.flags
+    }
+    pub fn channels(&self) -> &[SimpleEnvironmentChannel] {
+        &self.channels
     }
 
-    fn is_ground(&self) -> bool {
+    /// A material is ground if it's flagged as such and its diffuse texture looks like a ground texture
+    pub fn is_ground(&self) -> bool {
         self.flags.contains(SimpleEnvironmentMaterialFlags::GROUND) &&
-            SimpleEnvironmentMaterial::contains_ground_keyword(&self.channels[0].texture)
+            contains_ground_keyword(&self.channels[0].texture)
     }
 }
 
+pub fn contains_ground_keyword(texture: &str) -> bool {
+    texture.contains("_floor") ||
+        texture.contains("_dirt") ||
+        texture.contains("grass") ||
+        texture.contains("RiverBed") ||
+        texture.contains("_project") ||
+        texture.contains("tile_")
+}
+
 impl SimpleEnvironmentChannel {
     pub fn new(color: LinSrgba, texture: String, transform: Mat4) -> Self {
         SimpleEnvironmentChannel {
@@ -295,6 +327,16 @@ impl SimpleEnvironmentChannel {
         }
     }
 
+    pub fn color(&self) -> LinSrgba {
+        self.color
+    }
+    pub fn texture(&self) -> &str {
+        &self.texture
+    }
+    pub fn transform(&self) -> Mat4 {
+        self.transform
+    }
+
     fn read<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
         let color = LinSrgba::read_rgba_f32(reader)?;
         let texture = reader.read_padded_string(260)?;
@@ -391,7 +433,7 @@ impl SimpleEnvironmentMesh {
         -> io::Result<Self>
     {
         let quality = reader.read_i32()?;
-        let flags = if version.major == 9 && version.minor == 1 { reader.read_u32()? } else { 0 };
+        let flags = if version == Version::new(9, 1) { reader.read_u32()? } else { 0 };
         let bounding_sphere = Sphere::read(reader)?;
         let bounding_box = Box3D::read(reader)?;
 
@@ -416,9 +458,42 @@ impl SimpleEnvironmentMesh {
             complex_geometry
         })
     }
+
+    /// The lowest environment quality the mesh is drawn at. The field isn't a bitfield but a threshold,
+    /// any value outside of -1..=3 gives `None`, most meshes store -100 to be drawn at every quality
+    pub fn quality(&self) -> Option<SimpleEnvironmentQuality> {
+        SimpleEnvironmentQuality::from_i32(self.quality)
+    }
+    pub fn is_drawn_at(&self, quality: SimpleEnvironmentQuality) -> bool {
+        self.quality <= quality as i32
+    }
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+    pub fn material(&self) -> &str {
+        &self.material
+    }
+    /// The complex geometry is what gets rendered, the simple one only has positions
+    pub fn geometry_for_lod(&self, complex: bool) -> &SimpleEnvironmentMeshGeometry {
+        if complex {
+            &self.complex_geometry
+        } else {
+            &self.simple_geometry
+        }
+    }
 }
 
 impl SimpleEnvironmentMeshGeometry {
+    pub fn vertex_type(&self) -> SimpleEnvironmentVertexType {
+        self.vertex_type
+    }
+    pub fn vertices(&self) -> &[SimpleEnvironmentVertex] {
+        &self.vertices
+    }
+    pub fn indices(&self) -> &[u16] {
+        &self.indices
+    }
+
     fn read<R: Read + Seek>(reader: &mut BinaryReader<R>,
                             version: Version,
                             material: &SimpleEnvironmentMaterial,
@@ -430,7 +505,7 @@ impl SimpleEnvironmentMeshGeometry {
         let vertex_buffer = reader.read_u32()? as usize;
         let first_vertex = reader.read_u32()? as usize;
         let vertex_count = reader.read_u32()? as usize;
-        let return_offset = reader.position();
+        let return_offset = reader.position()?;
 
         let vertex_type = match geometry_type {
             SimpleEnvironmentMeshGeometryType::Simple => SimpleEnvironmentVertexType::Position,
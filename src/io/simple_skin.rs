@@ -1,18 +1,36 @@
+use crate::error::{Error, Result};
 use crate::io::binary_reader::BinaryReader;
 use crate::io::binary_writer::BinaryWriter;
+use crate::io::obj::{ObjCorner, ObjFile};
+use crate::io::ply;
+use crate::io::ply::{PlyFormat, PlyPart, PlyType, PlyValue};
+use crate::io::static_object::{StaticObject, StaticObjectSubmesh, StaticObjectVertex};
 use crate::structures::box3d::Box3D;
 use crate::structures::color::LinSrgbaExt;
 use crate::structures::sphere::Sphere;
 use crate::structures::vector2::Vector2;
 use crate::structures::vector3::Vector3;
+use crate::structures::vector4::Vector4;
+use crate::utilities::simplify;
+use crate::utilities::triangles;
+use crate::utilities::triangles::Triangles;
+use crate::utilities::uv_convention::UvConvention;
+use crate::utilities::version::Version;
+use crate::utilities::vertex_cache;
+use crate::utilities::vertex_cache::CacheOptimization;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use glam::{Mat4, Vec3};
 use palette::LinSrgba;
+use std::collections::HashMap;
 use std::io;
-use std::io::{Cursor, Error, ErrorKind, Read, Seek, Write};
+use std::io::{Cursor, ErrorKind, Read, Seek, Write};
 use std::ops::SubAssign;
 use std::path::Path;
 
 #[derive(Debug)]
 pub struct SimpleSkin {
+    version: Version,
     submeshes: Vec<SimpleSkinSubmesh>,
     bounding_box: Box3D,
     bounding_sphere: Sphere,
@@ -32,6 +50,10 @@ pub struct SimpleSkinSubmesh {
 }
 
 #[derive(Copy, Clone, Debug)]
+/// Vertex types by their size in bytes:
+/// - 0 (52): position (3 f32), influences (4 u8), weights (4 f32), normal (3 f32), uv (2 f32)
+/// - 1 (56): type 0 followed by an RGBA u8 color
+/// - 2 (72): type 1 followed by a tangent (4 f32, `w` being the bitangent sign)
 pub struct SimpleSkinVertex {
     pub position: Vector3,
     pub influences: [u8; 4],
@@ -39,47 +61,126 @@ pub struct SimpleSkinVertex {
     pub normal: Vector3,
     pub uv: Vector2,
     pub color: Option<LinSrgba>,
+    pub tangent: Option<Vector4>,
 }
 
+/// LODs of the same skin, ordered from the most detailed one by triangle count
+#[derive(Debug)]
+pub struct SimpleSkinLodChain {
+    lods: Vec<SimpleSkin>,
+}
+
+// Splits the shared vertex and index buffers into the submeshes, sequentially or in parallel
+type AssignSubmeshData = fn(&mut [SimpleSkinSubmesh], &[SimpleSkinVertex], &[u16]) -> io::Result<()>;
+
 impl SimpleSkin {
     pub fn new(submeshes: Vec<SimpleSkinSubmesh>) -> Self {
         SimpleSkin {
+            version: Version::new(4, 1),
             submeshes,
             bounding_box: Box3D::zero(),
             bounding_sphere: Sphere::zero(),
         }
     }
 
-    pub fn read_from_file(file_location: &Path) -> io::Result<Self> {
-        SimpleSkin::read(&mut BinaryReader::from_location(file_location))
+    pub fn read_from_file(file_location: &Path) -> Result<Self> {
+        SimpleSkin::read(&mut BinaryReader::from_location(file_location)?)
     }
-    pub fn read_from_buffer(buffer: Cursor<Vec<u8>>) -> io::Result<Self> {
+    pub fn read_from_buffer(buffer: Cursor<Vec<u8>>) -> Result<Self> {
         SimpleSkin::read(&mut BinaryReader::from_buffer(buffer))
     }
-    fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
+    /// Creates a submesh for each material, or group if the faces have no material.
+    /// Every vertex is fully weighted to the first bone, normals are computed for submeshes where the OBJ has none
+    pub fn read_obj(file_location: &Path) -> io::Result<Self> {
+        let obj = ObjFile::read_from_file(file_location)?;
+
+        let mut submeshes: Vec<SimpleSkinSubmesh> = Vec::with_capacity(obj.groups.len());
+        for group in &obj.groups {
+            let mut vertices: Vec<SimpleSkinVertex> = Vec::new();
+            let mut indices: Vec<u16> = Vec::with_capacity(group.triangles.len() * 3);
+            let mut vertex_lookup: HashMap<ObjCorner, u16> = HashMap::new();
+            let mut missing_normals = false;
+
+            for corner in group.triangles.iter().flat_map(|triangle| triangle.iter()) {
+                let index = match vertex_lookup.get(corner) {
+                    Some(index) => *index,
+                    None => {
+                        if vertices.len() > std::u16::MAX as usize {
+                            return Err(io::Error::new(
+                                ErrorKind::InvalidData,
+                                format!("Group {} has more vertices than a submesh can index", group.name),
+                            ));
+                        }
+
+                        let uv = match corner.uv {
+                            Some(uv) => UvConvention::BottomLeft.convert(obj.uvs[uv]),
+                            None => Vector2::zero(),
+                        };
+                        let normal = match corner.normal {
+                            Some(normal) => obj.normals[normal],
+                            None => {
+                                missing_normals = true;
+                                Vector3::zero()
+                            }
+                        };
+
+                        let index = vertices.len() as u16;
+                        vertices.push(SimpleSkinVertex::new_basic(
+                            obj.positions[corner.position],
+                            [0, 0, 0, 0],
+                            [1.0, 0.0, 0.0, 0.0],
+                            normal,
+                            uv,
+                        ));
+                        vertex_lookup.insert(*corner, index);
+                        index
+                    }
+                };
+
+                indices.push(index);
+            }
+
+            let mut submesh = SimpleSkinSubmesh::new(group.name.clone(), vertices, indices);
+            if missing_normals {
+                submesh.recompute_normals();
+            }
+            submeshes.push(submesh);
+        }
+
+        Ok(SimpleSkin::new(submeshes))
+    }
+    /// Same as `read_from_file` but splits the vertex and index buffers into the submeshes on multiple threads
+    #[cfg(feature = "rayon")]
+    pub fn read_from_file_parallel(file_location: &Path) -> Result<Self> {
+        SimpleSkin::read_with(&mut BinaryReader::from_location(file_location)?, SimpleSkin::assign_submesh_data_parallel)
+    }
+    fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> Result<Self> {
+        SimpleSkin::read_with(reader, SimpleSkin::assign_submesh_data)
+    }
+    fn read_with<T: Read + Seek>(
+        reader: &mut BinaryReader<T>,
+        assign_submesh_data: AssignSubmeshData,
+    ) -> Result<Self> {
         let magic = reader.read_u32()?;
         if magic != 0x00112233 {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Incorrect file signature",
-            ));
+            return Err(Error::InvalidMagic {
+                expected: String::from("0x00112233"),
+                found: format!("{:#010x}", magic),
+            });
         }
 
         let major = reader.read_u16()?;
         let minor = reader.read_u16()?;
         if major != 2 && major != 4 && minor != 1 {
-            return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+            return Err(Error::UnsupportedVersion { major: major as u32, minor: minor as u32 });
         }
 
-        let submesh_count = reader.read_u32()?;
-        let mut submeshes: Vec<SimpleSkinSubmesh> = Vec::with_capacity(submesh_count as usize);
-        for i in 0..submesh_count {
-            submeshes.push(SimpleSkinSubmesh::read(reader)?);
-        }
+        let submesh_count = reader.read_count(80)?;
+        let mut submeshes = reader.read_vec(submesh_count, SimpleSkinSubmesh::read)?;
 
         let flags = if major == 4 { reader.read_u32()? } else { 0 };
-        let index_count = reader.read_u32()?;
-        let vertex_count = reader.read_u32()?;
+        let index_count = reader.read_count(2)?;
+        let vertex_count = reader.read_count(52)?;
         let vertex_size = if major == 4 { reader.read_u32()? } else { 52 };
         let vertex_type = if major == 4 { reader.read_u32()? } else { 0 };
         let bounding_box = if major == 4 {
@@ -93,64 +194,71 @@ impl SimpleSkin {
             Sphere::zero()
         };
 
-        if (vertex_type == 0 && vertex_size != 52) || (vertex_type == 1 && vertex_size != 56) {
-            return Err(Error::new(
+        if SimpleSkinVertex::size_of_type(vertex_type) != Some(vertex_size) {
+            return Err(io::Error::new(
                 ErrorKind::InvalidData,
-                "Vertex size doesn't match with the vertex size",
-            ));
+                format!("Vertex size {} doesn't match vertex type {}", vertex_size, vertex_type),
+            ).into());
         }
 
-        let mut indices: Vec<u16> = Vec::with_capacity(index_count as usize);
-        let mut vertices: Vec<SimpleSkinVertex> = Vec::with_capacity(vertex_count as usize);
+        let indices = reader.read_vec(index_count, |reader| reader.read_u16())?;
+        let vertices = reader.read_vec(vertex_count, |reader| {
+            SimpleSkinVertex::read(vertex_type, reader)
+        })?;
 
-        for i in 0..index_count {
-            indices.push(reader.read_u16()?);
-        }
-        for i in 0..vertex_count {
-            vertices.push(SimpleSkinVertex::read(vertex_type, reader)?);
-        }
-
-        // Now we need to assign data to submeshes
-        for submesh in &mut submeshes {
-            let mut submesh_vertices: Vec<SimpleSkinVertex> =
-                Vec::with_capacity(submesh.vertex_count as usize);
-            let mut submesh_indices: Vec<u16> = Vec::with_capacity(submesh.index_count as usize);
-            let mut min_index = std::u16::MAX;
-
-            for i in 0..submesh.vertex_count {
-                submesh_vertices.push(vertices[(i + submesh.start_vertex) as usize]);
-            }
-            for i in 0..submesh.index_count {
-                let index = indices[(i + submesh.start_index) as usize];
-                if min_index > index {
-                    min_index = index;
-                }
-
-                submesh_indices.push(index);
-            }
-
-            //Normalize indices
-            for index in &mut submesh_indices {
-                index.sub_assign(min_index);
-            }
-
-            submesh.set_data(submesh_vertices, submesh_indices);
-        }
+        assign_submesh_data(&mut submeshes, &vertices, &indices)?;
 
         Ok(SimpleSkin {
+            version: Version::new(major as u8, minor as u8),
             submeshes,
             bounding_box,
             bounding_sphere,
         })
     }
 
-    pub fn write_to_file(&mut self, file_location: &Path) -> io::Result<()> {
-        self.write(&mut BinaryWriter::from_location(file_location))
+    fn assign_submesh_data(
+        submeshes: &mut [SimpleSkinSubmesh],
+        vertices: &[SimpleSkinVertex],
+        indices: &[u16],
+    ) -> io::Result<()> {
+        for submesh in submeshes {
+            submesh.assign_data(vertices, indices)?;
+        }
+
+        Ok(())
+    }
+    #[cfg(feature = "rayon")]
+    fn assign_submesh_data_parallel(
+        submeshes: &mut [SimpleSkinSubmesh],
+        vertices: &[SimpleSkinVertex],
+        indices: &[u16],
+    ) -> io::Result<()> {
+        // Every submesh copies its own range of the flat buffers
+        submeshes
+            .par_iter_mut()
+            .map(|submesh| submesh.assign_data(vertices, indices))
+            .collect()
+    }
+
+    pub fn write_to_file(&mut self, file_location: &Path) -> Result<()> {
+        let mut writer = BinaryWriter::from_location(file_location)?;
+        self.write(&mut writer)?;
+
+        Ok(writer.flush()?)
+    }
+    pub fn write_to_buffer(&mut self, buffer: Cursor<Vec<u8>>) -> Result<()> {
+        let mut writer = BinaryWriter::from_buffer(buffer);
+        self.write(&mut writer)?;
+
+        Ok(writer.flush()?)
     }
-    pub fn write_to_buffer(&mut self, buffer: Cursor<Vec<u8>>) -> io::Result<()> {
-        self.write(&mut BinaryWriter::from_buffer(buffer))
+    pub fn write_to_vec(&mut self) -> Result<Vec<u8>> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        self.write(&mut writer)?;
+
+        Ok(writer.into_inner()?.into_inner())
     }
-    fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+    fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> Result<()> {
         writer.write_u32(0x00112233)?; // Magic
         writer.write_u16(4)?; // Major
         writer.write_u16(1)?; // Minor
@@ -158,23 +266,20 @@ impl SimpleSkin {
 
         let mut index_offset = 0u32;
         let mut vertex_offset = 0u32;
-        let mut contains_vertex_color = false;
+        let mut vertex_type = 0u32;
         for submesh in &self.submeshes {
             submesh.write(vertex_offset, index_offset, writer)?;
 
             vertex_offset += submesh.vertices.len() as u32;
             index_offset += submesh.indices.len() as u32;
-
-            if submesh.contains_vertex_color() {
-                contains_vertex_color = true;
-            }
+            vertex_type = vertex_type.max(submesh.vertex_type());
         }
 
         writer.write(0u32)?; // Flags
         writer.write(index_offset)?; // Vertex Count
         writer.write(vertex_offset)?; // Index Count
-        writer.write(if contains_vertex_color { 56 } else { 52 })?; // Vertex Size
-        writer.write(contains_vertex_color as u32)?; // Vertex Type
+        writer.write(SimpleSkinVertex::size_of_type(vertex_type).unwrap())?; // Vertex Size
+        writer.write(vertex_type)?; // Vertex Type
 
         self.bounding_box().write(writer)?;
         self.bounding_sphere().write(writer)?;
@@ -187,16 +292,9 @@ impl SimpleSkin {
 
             index_offset += submesh.indices.len() as u16;
         }
-        for submesh in self.submeshes() {
-            for vertex in submesh.vertices() {
-                if vertex.color.is_none() && contains_vertex_color {
-                    // Copy vertex so we don't modify mesh data but save a correct file
-                    let mut color_vertex = *vertex;
-                    color_vertex.color = Option::from(LinSrgba::new(0.0, 0.0, 0.0, 0.0));
-                    color_vertex.write(writer)?;
-                } else {
-                    vertex.write(writer)?;
-                }
+        for submesh in &self.submeshes {
+            for vertex in &submesh.vertices {
+                vertex.write(vertex_type, writer)?;
             }
         }
 
@@ -221,59 +319,344 @@ impl SimpleSkin {
     }
 
     pub fn submeshes(&mut self) -> &mut [SimpleSkinSubmesh] { &mut self.submeshes }
+    /// The version the skin was read as, 4.1 for skins created in memory
+    pub fn version(&self) -> Version {
+        self.version
+    }
+    pub fn triangle_count(&self) -> usize {
+        self.submeshes.iter().map(|submesh| submesh.indices.len() / 3).sum()
+    }
 
-    pub fn central_point(&mut self) -> Vector3 {
-        let bounds = self.bounding_box();
+    /// Writes the submeshes as a single PLY mesh with position, normal, UV and, if any vertex has one, color.
+    /// The faces of each submesh are listed in a `material` comment
+    pub fn write_ply<W: Write>(&self, out: &mut W, format: PlyFormat) -> io::Result<()> {
+        let has_color = self.all_vertices().any(|vertex| vertex.color.is_some());
 
-        Vector3 {
-            x: 0.5 * (bounds.max.x - bounds.min.x),
-            y: 0.5 * (bounds.max.y - bounds.min.y),
-            z: 0.5 * (bounds.max.z - bounds.min.z),
+        let mut properties = vec![
+            ("x", PlyType::Float),
+            ("y", PlyType::Float),
+            ("z", PlyType::Float),
+            ("nx", PlyType::Float),
+            ("ny", PlyType::Float),
+            ("nz", PlyType::Float),
+            ("s", PlyType::Float),
+            ("t", PlyType::Float),
+        ];
+        if has_color {
+            properties.extend_from_slice(&ply::COLOR_PROPERTIES);
         }
+
+        let parts: Vec<PlyPart<_, _>> = self
+            .submeshes
+            .iter()
+            .map(|submesh| PlyPart {
+                name: Some(&submesh.name),
+                vertices: &submesh.vertices,
+                indices: &submesh.indices,
+            })
+            .collect();
+
+        ply::write_parts(out, format, &[], &parts, &properties, |vertex, values| {
+            let uv = vertex.flipped_uv();
+            values.extend_from_slice(&[
+                PlyValue::Float(vertex.position.x),
+                PlyValue::Float(vertex.position.y),
+                PlyValue::Float(vertex.position.z),
+                PlyValue::Float(vertex.normal.x),
+                PlyValue::Float(vertex.normal.y),
+                PlyValue::Float(vertex.normal.z),
+                PlyValue::Float(uv.x),
+                PlyValue::Float(uv.y),
+            ]);
+            if has_color {
+                values.extend_from_slice(&ply::color_values(vertex.color));
+            }
+        })
     }
-    pub fn bounding_box(&mut self) -> Box3D {
-        if self.bounding_box == Box3D::ZERO {
-            let mut min = Vector3::new(std::f32::INFINITY, std::f32::INFINITY, std::f32::INFINITY);
-            let mut max = Vector3::new(
-                std::f32::NEG_INFINITY,
-                std::f32::NEG_INFINITY,
-                std::f32::NEG_INFINITY,
-            );
 
-            for submesh in &mut self.submeshes {
-                for vertex in submesh.vertices() {
-                    if min.x > vertex.position.x {
-                        min.x = vertex.position.x
-                    };
-                    if min.y > vertex.position.y {
-                        min.y = vertex.position.y
-                    };
-                    if min.z > vertex.position.z {
-                        min.z = vertex.position.z
-                    };
-                    if max.x < vertex.position.x {
-                        max.x = vertex.position.x
-                    };
-                    if max.y < vertex.position.y {
-                        max.y = vertex.position.y
-                    };
-                    if max.z < vertex.position.z {
-                        max.z = vertex.position.z
-                    };
+    /// Drops the skinning data, submesh names are used as the materials
+    pub fn to_static_object(&mut self, name: String) -> StaticObject {
+        let submeshes = self
+            .submeshes
+            .iter()
+            .map(|submesh| {
+                let vertices = submesh
+                    .vertices
+                    .iter()
+                    .map(|vertex| StaticObjectVertex {
+                        position: vertex.position,
+                        uv: vertex.uv,
+                        color: vertex.color,
+                    })
+                    .collect();
+                let indices = submesh.indices.iter().map(|index| *index as u32).collect();
+
+                StaticObjectSubmesh::new(submesh.name.clone(), vertices, indices)
+            })
+            .collect();
+
+        StaticObject::new(name, submeshes)
+    }
+
+    /// Checks the mesh for data the game won't load and returns every violation found,
+    /// influences are always below 256 since they're stored as `u8`
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        const WEIGHT_EPSILON: f32 = 0.001;
+
+        let mut errors: Vec<String> = Vec::new();
+        let mut vertex_count = 0u64;
+        for submesh in &self.submeshes {
+            if submesh.indices.len() % 3 != 0 {
+                errors.push(format!(
+                    "Submesh {}: index count {} is not a multiple of 3",
+                    submesh.name,
+                    submesh.indices.len()
+                ));
+            }
+
+            for (i, index) in submesh.indices.iter().enumerate() {
+                if *index as usize >= submesh.vertices.len() {
+                    errors.push(format!(
+                        "Submesh {}: index {} ({}) is out of range for {} vertices",
+                        submesh.name,
+                        i,
+                        index,
+                        submesh.vertices.len()
+                    ));
+                }
+            }
+
+            for (i, vertex) in submesh.vertices.iter().enumerate() {
+                let weight_sum: f32 = vertex.weights.iter().sum();
+                let all_zero = vertex.weights.iter().all(|weight| *weight == 0.0);
+                if !all_zero && (weight_sum - 1.0).abs() > WEIGHT_EPSILON {
+                    errors.push(format!(
+                        "Submesh {}: weights of vertex {} sum to {}",
+                        submesh.name, i, weight_sum
+                    ));
+                }
+            }
+
+            vertex_count += submesh.vertices.len() as u64;
+        }
+
+        if vertex_count > std::u32::MAX as u64 {
+            errors.push(format!("Vertex count {} doesn't fit in u32", vertex_count));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Moves the submesh at `order[i]` to index `i`, `order` has to contain every submesh index exactly once
+    pub fn reorder_submeshes(&mut self, order: &[usize]) -> io::Result<()> {
+        let mut used = vec![false; self.submeshes.len()];
+        let is_permutation = order.len() == used.len()
+            && order
+                .iter()
+                .all(|index| *index < used.len() && !std::mem::replace(&mut used[*index], true));
+        if !is_permutation {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Submesh order isn't a permutation of the submesh indices",
+            ));
+        }
+
+        let mut submeshes: Vec<Option<SimpleSkinSubmesh>> = self.submeshes.drain(..).map(Some).collect();
+        self.submeshes = order
+            .iter()
+            .map(|index| submeshes[*index].take().expect("Every index is used once"))
+            .collect();
+
+        Ok(())
+    }
+    /// Fails if there's no submesh called `old` or another one is already called `new`
+    pub fn rename_submesh(&mut self, old: &str, new: &str) -> io::Result<()> {
+        if old != new && self.submeshes.iter().any(|submesh| submesh.name == new) {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("Submesh {} already exists", new),
+            ));
+        }
+
+        match self.submeshes.iter_mut().find(|submesh| submesh.name == old) {
+            Some(submesh) => {
+                submesh.name = new.to_string();
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Submesh {} doesn't exist", old),
+            )),
+        }
+    }
+
+    /// Replaces the named submeshes with a single one placed where the first of them was
+    pub fn merge_submeshes(&mut self, names: &[String], new_name: String) -> io::Result<()> {
+        if names.is_empty() {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "No submeshes to merge"));
+        }
+
+        let mut merge_indices: Vec<usize> = Vec::with_capacity(names.len());
+        for name in names {
+            match self.submeshes.iter().position(|submesh| &submesh.name == name) {
+                Some(index) if merge_indices.contains(&index) => {}
+                Some(index) => merge_indices.push(index),
+                None => {
+                    return Err(io::Error::new(
+                        ErrorKind::NotFound,
+                        format!("Submesh {} doesn't exist", name),
+                    ))
+                }
+            }
+        }
+
+        let vertex_count: usize = merge_indices
+            .iter()
+            .map(|index| self.submeshes[*index].vertices.len())
+            .sum();
+        if vertex_count > std::u16::MAX as usize + 1 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Merged submesh would exceed the 16-bit index range",
+            ));
+        }
+
+        // Same as when writing, vertices without color get black and without tangent get zero
+        // if any submesh has them
+        let vertex_type = merge_indices
+            .iter()
+            .map(|index| self.submeshes[*index].vertex_type())
+            .max()
+            .unwrap_or(0);
+
+        let mut vertices: Vec<SimpleSkinVertex> = Vec::with_capacity(vertex_count);
+        let mut indices: Vec<u16> = Vec::new();
+        for index in &merge_indices {
+            let submesh = &self.submeshes[*index];
+            let index_offset = vertices.len() as u16;
+
+            indices.extend(submesh.indices.iter().map(|index| *index + index_offset));
+            vertices.extend(submesh.vertices.iter().map(|vertex| {
+                let mut vertex = *vertex;
+                if vertex_type >= 1 && vertex.color.is_none() {
+                    vertex.color = Some(LinSrgba::new(0.0, 0.0, 0.0, 0.0));
                 }
+                if vertex_type >= 2 && vertex.tangent.is_none() {
+                    vertex.tangent = Some(Vector4::zero());
+                }
+
+                vertex
+            }));
+        }
+
+        merge_indices.sort_unstable();
+        for index in merge_indices.iter().rev() {
+            self.submeshes.remove(*index);
+        }
+
+        let insert_index = *merge_indices.first().unwrap_or(&self.submeshes.len());
+        self.submeshes.insert(
+            insert_index,
+            SimpleSkinSubmesh::new(new_name, vertices, indices),
+        );
+
+        Ok(())
+    }
+
+    /// Moves the triangles of `submesh` with any vertex weighted to `influence` into a new submesh named
+    /// `{submesh}_{influence}` and returns it, the skin keeps the rest. Vertices used by both sides are duplicated
+    pub fn split_submesh_by_influence(&mut self, submesh: &str, influence: u8) -> io::Result<SimpleSkinSubmesh> {
+        let source = match self.submeshes.iter_mut().find(|existing| existing.name == submesh) {
+            Some(source) => source,
+            None => {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("Submesh {} doesn't exist", submesh),
+                ))
             }
+        };
+        source.triangles()?;
+
+        let is_influenced = |vertex: &SimpleSkinVertex| {
+            vertex
+                .influences
+                .iter()
+                .zip(vertex.weights.iter())
+                .any(|(vertex_influence, weight)| *vertex_influence == influence && *weight > 0.0)
+        };
+        let (split, kept): (Vec<&[u16]>, Vec<&[u16]>) = source
+            .indices
+            .chunks_exact(3)
+            .partition(|triangle| triangle.iter().any(|index| is_influenced(&source.vertices[*index as usize])));
+
+        let (split_vertices, split_indices) = SimpleSkinSubmesh::extract_triangles(&source.vertices, &split);
+        let (kept_vertices, kept_indices) = SimpleSkinSubmesh::extract_triangles(&source.vertices, &kept);
+        source.set_data(kept_vertices, kept_indices);
+
+        Ok(SimpleSkinSubmesh::new(
+            format!("{}_{}", submesh, influence),
+            split_vertices,
+            split_indices,
+        ))
+    }
+
+    /// Welds every submesh, returns the total vertex count before and after welding
+    pub fn weld_all(&mut self, epsilon: f32) -> (usize, usize) {
+        let mut before = 0;
+        let mut after = 0;
+
+        for submesh in &mut self.submeshes {
+            let (submesh_before, submesh_after) = submesh.weld(epsilon);
+
+            before += submesh_before;
+            after += submesh_after;
+        }
+
+        (before, after)
+    }
+    pub fn transform(&mut self, matrix: Mat4) {
+        for submesh in &mut self.submeshes {
+            submesh.transform(matrix);
+        }
+
+        self.bounding_box = Box3D::ZERO;
+        self.bounding_sphere = Sphere::ZERO;
+    }
+    pub fn normalize_all_weights(&mut self) {
+        for vertex in self.all_vertices_mut() {
+            vertex.normalize_weights();
+        }
+    }
+
+    /// Every vertex of every submesh, in submesh order
+    pub fn all_vertices(&self) -> impl Iterator<Item = &SimpleSkinVertex> + Clone {
+        self.submeshes.iter().flat_map(|submesh| submesh.vertices.iter())
+    }
+    /// Every vertex of every submesh, the cached bounds are reset since the positions may change
+    pub fn all_vertices_mut(&mut self) -> impl Iterator<Item = &mut SimpleSkinVertex> {
+        self.bounding_box = Box3D::ZERO;
+        self.bounding_sphere = Sphere::ZERO;
+
+        self.submeshes.iter_mut().flat_map(|submesh| submesh.vertices.iter_mut())
+    }
 
-            self.bounding_box = Box3D::new(min, max);
+    pub fn central_point(&mut self) -> Vector3 {
+        self.bounding_box().center()
+    }
+    pub fn bounding_box(&mut self) -> Box3D {
+        if self.bounding_box == Box3D::ZERO {
+            self.bounding_box = Box3D::from_points(self.all_vertices().map(|vertex| vertex.position));
         }
 
         self.bounding_box
     }
     pub fn bounding_sphere(&mut self) -> Sphere {
         if self.bounding_sphere == Sphere::ZERO {
-            let bounds = self.bounding_box();
-            let central_point = self.central_point();
-            self.bounding_sphere =
-                Sphere::new(central_point, Vector3::distance(central_point, bounds.max));
+            self.bounding_sphere = Sphere::from_points(self.all_vertices().map(|vertex| vertex.position));
         }
 
         self.bounding_sphere
@@ -292,6 +675,42 @@ impl SimpleSkinSubmesh {
             index_count: 0,
         }
     }
+    fn assign_data(&mut self, vertices: &[SimpleSkinVertex], indices: &[u16]) -> io::Result<()> {
+        let vertex_end = self.start_vertex as u64 + self.vertex_count as u64;
+        let index_end = self.start_index as u64 + self.index_count as u64;
+        if vertex_end > vertices.len() as u64 || index_end > indices.len() as u64 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Submesh data range is out of bounds",
+            ));
+        }
+
+        let mut submesh_vertices: Vec<SimpleSkinVertex> =
+            Vec::with_capacity(self.vertex_count as usize);
+        let mut submesh_indices: Vec<u16> = Vec::with_capacity(self.index_count as usize);
+        let mut min_index = std::u16::MAX;
+
+        for i in 0..self.vertex_count {
+            submesh_vertices.push(vertices[(i + self.start_vertex) as usize]);
+        }
+        for i in 0..self.index_count {
+            let index = indices[(i + self.start_index) as usize];
+            if min_index > index {
+                min_index = index;
+            }
+
+            submesh_indices.push(index);
+        }
+
+        //Normalize indices
+        for index in &mut submesh_indices {
+            index.sub_assign(min_index);
+        }
+
+        self.set_data(submesh_vertices, submesh_indices);
+
+        Ok(())
+    }
     fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<SimpleSkinSubmesh> {
         Ok(SimpleSkinSubmesh {
             name: reader.read_padded_string(64)?,
@@ -323,18 +742,192 @@ impl SimpleSkinSubmesh {
         self.vertices = vertices;
         self.indices = indices;
     }
-    fn contains_vertex_color(&self) -> bool {
+    /// Copies the vertices the triangles use in order of first use and reindexes the triangles to them
+    fn extract_triangles(vertices: &[SimpleSkinVertex], triangles: &[&[u16]]) -> (Vec<SimpleSkinVertex>, Vec<u16>) {
+        let mut remap: Vec<Option<u16>> = vec![None; vertices.len()];
+        let mut extracted_vertices: Vec<SimpleSkinVertex> = Vec::new();
+        let mut extracted_indices: Vec<u16> = Vec::with_capacity(triangles.len() * 3);
+        for index in triangles.iter().flat_map(|triangle| triangle.iter()) {
+            let new_index = *remap[*index as usize].get_or_insert_with(|| {
+                extracted_vertices.push(vertices[*index as usize]);
+                extracted_vertices.len() as u16 - 1
+            });
+
+            extracted_indices.push(new_index);
+        }
+
+        (extracted_vertices, extracted_indices)
+    }
+
+    /// Replaces the vertex normals with the area weighted average of the adjacent face normals
+    pub fn recompute_normals(&mut self) {
+        let mut normals = vec![Vector3::zero(); self.vertices.len()];
+        for face in self.indices.chunks_exact(3) {
+            let face = [face[0] as usize, face[1] as usize, face[2] as usize];
+            if face.iter().any(|index| *index >= self.vertices.len()) {
+                continue;
+            }
+
+            let a = self.vertices[face[0]].position;
+            let edge1: Vec3 = (self.vertices[face[1]].position - a).into();
+            let edge2: Vec3 = (self.vertices[face[2]].position - a).into();
+            let face_normal: Vector3 = edge1.cross(edge2).into();
+
+            for index in &face {
+                normals[*index] = normals[*index] + face_normal;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normal.normalize();
+        }
+    }
+
+    pub fn transform(&mut self, matrix: Mat4) {
+        // Normals need the inverse transpose so non-uniform scaling doesn't skew them
+        let normal_matrix = matrix.inverse().transpose();
+
+        for vertex in &mut self.vertices {
+            vertex.position = matrix.transform_point3(vertex.position.into()).into();
+
+            let mut normal = normal_matrix.transform_vector3(vertex.normal.into());
+            if normal.length() > 0.0 {
+                normal = normal.normalize();
+            }
+            vertex.normal = normal.into();
+        }
+    }
+
+    /// Merges vertices which are equal within `epsilon` and remaps the indices to the kept ones,
+    /// returns the vertex count before and after welding
+    pub fn weld(&mut self, epsilon: f32) -> (usize, usize) {
+        let vertex_count = self.vertices.len();
+        let cell_size = if epsilon > 0.0 { epsilon } else { std::f32::EPSILON };
+
+        // Vertices are bucketed by position so we only compare against neighbouring cells
+        let mut grid: HashMap<(i64, i64, i64), Vec<u16>> = HashMap::new();
+        let mut welded_vertices: Vec<SimpleSkinVertex> = Vec::with_capacity(vertex_count);
+        let mut remap: Vec<u16> = Vec::with_capacity(vertex_count);
+
         for vertex in &self.vertices {
-            if vertex.color.is_some() {
-                return true;
+            let cell = (
+                (vertex.position.x / cell_size).floor() as i64,
+                (vertex.position.y / cell_size).floor() as i64,
+                (vertex.position.z / cell_size).floor() as i64,
+            );
+
+            let mut welded_index: Option<u16> = None;
+            'search: for x in cell.0 - 1..=cell.0 + 1 {
+                for y in cell.1 - 1..=cell.1 + 1 {
+                    for z in cell.2 - 1..=cell.2 + 1 {
+                        if let Some(candidates) = grid.get(&(x, y, z)) {
+                            for candidate in candidates {
+                                if welded_vertices[*candidate as usize].equals(vertex, epsilon) {
+                                    welded_index = Some(*candidate);
+                                    break 'search;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            match welded_index {
+                Some(index) => remap.push(index),
+                None => {
+                    let index = welded_vertices.len() as u16;
+
+                    welded_vertices.push(*vertex);
+                    grid.entry(cell).or_default().push(index);
+                    remap.push(index);
+                }
             }
         }
 
-        return false;
+        for index in &mut self.indices {
+            *index = remap[*index as usize];
+        }
+
+        self.vertices = welded_vertices;
+
+        (vertex_count, self.vertices.len())
+    }
+    // The smallest vertex type which can hold the data of all vertices
+    fn vertex_type(&self) -> u32 {
+        self.vertices
+            .iter()
+            .map(|vertex| {
+                if vertex.tangent.is_some() {
+                    2
+                } else if vertex.color.is_some() {
+                    1
+                } else {
+                    0
+                }
+            })
+            .max()
+            .unwrap_or(0)
     }
 
     pub fn vertices(&mut self) -> &mut [SimpleSkinVertex] { &mut self.vertices }
     pub fn indices(&mut self) -> &mut [u16] { &mut self.indices }
+    pub fn triangles(&self) -> io::Result<Triangles<'_, SimpleSkinVertex, u16>> {
+        Triangles::new(&self.vertices, &self.indices)
+    }
+    /// Removes degenerate triangles and optionally the vertices left unused, returns the number of triangles removed
+    pub fn clean(&mut self, remove_unused_vertices: bool) -> usize {
+        let removed = triangles::remove_degenerate_triangles(&self.vertices, &mut self.indices, |vertex| vertex.position);
+        if remove_unused_vertices {
+            triangles::remove_unused_vertices(&mut self.vertices, &mut self.indices);
+        }
+
+        removed
+    }
+    /// Flips triangles which face away from the average of their vertex normals, or from `expected_up` if those
+    /// cancel out, returns the number of triangles flipped
+    pub fn fix_winding(&mut self, expected_up: Vector3) -> usize {
+        triangles::fix_winding(&self.vertices, &mut self.indices, |vertex| vertex.position, |corners| {
+            let normal = corners[0].normal + corners[1].normal + corners[2].normal;
+            if normal.length() > 0.0 {
+                normal
+            } else {
+                expected_up
+            }
+        })
+    }
+    /// Reverses the winding of every triangle, for meshes from tools with the opposite handedness
+    pub fn flip_winding(&mut self) {
+        triangles::flip_winding(&mut self.indices);
+    }
+    /// Reorders the triangles for the GPU vertex cache, the vertices and the triangles themselves are untouched
+    pub fn optimize_index_order(&mut self) -> CacheOptimization {
+        vertex_cache::optimize_index_order(&mut self.indices)
+    }
+    /// Collapses edges by their quadric error until at most `target_triangle_count` triangles are left,
+    /// the kept vertices are interpolated with `SimpleSkinVertex::lerp`. Collapses which would flip a triangle
+    /// or move a vertex on an open edge or UV seam are skipped, so the result can have more triangles than the target.
+    /// Unused vertices are removed, returns the number of triangles left
+    pub fn simplify(&mut self, target_triangle_count: usize) -> usize {
+        let triangle_count = simplify::simplify_vertices(
+            &mut self.vertices,
+            &mut self.indices,
+            target_triangle_count,
+            |vertex| vertex.position,
+            SimpleSkinVertex::lerp,
+        );
+        triangles::remove_unused_vertices(&mut self.vertices, &mut self.indices);
+
+        triangle_count
+    }
+    /// Expands the indexed triangles into a flat vertex list
+    pub fn to_triangle_soup(&self) -> io::Result<Vec<SimpleSkinVertex>> {
+        let mut vertices: Vec<SimpleSkinVertex> = Vec::with_capacity(self.indices.len());
+        for triangle in self.triangles()? {
+            vertices.extend(triangle.iter().map(|vertex| **vertex));
+        }
+
+        Ok(vertices)
+    }
 }
 
 impl SimpleSkinVertex {
@@ -352,6 +945,7 @@ impl SimpleSkinVertex {
             normal,
             uv,
             color: Option::None,
+            tangent: Option::None,
         }
     }
     pub fn new_color(
@@ -369,49 +963,237 @@ impl SimpleSkinVertex {
             normal,
             uv,
             color: Option::Some(color),
+            tangent: Option::None,
+        }
+    }
+    /// The UV with a bottom left origin
+    pub fn flipped_uv(&self) -> Vector2 {
+        UvConvention::BottomLeft.convert(self.uv)
+    }
+    /// Interpolates every attribute from `a` (0.0) to `b` (1.0), the influences of both are merged keeping the 4 heaviest
+    pub fn lerp(a: &SimpleSkinVertex, b: &SimpleSkinVertex, t: f32) -> SimpleSkinVertex {
+        let mut influences: Vec<(u8, f32)> = Vec::with_capacity(8);
+        for (vertex, factor) in [(a, 1.0 - t), (b, t)].iter() {
+            for (influence, weight) in vertex.influences.iter().zip(vertex.weights.iter()) {
+                match influences.iter_mut().find(|(existing, _)| existing == influence) {
+                    Some((_, total)) => *total += weight * factor,
+                    None => influences.push((*influence, weight * factor)),
+                }
+            }
+        }
+        influences.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        influences.resize(4, (0, 0.0));
+
+        let color = match (a.color, b.color) {
+            (Some(a), Some(b)) => Some(LinSrgba::new(
+                a.color.red + (b.color.red - a.color.red) * t,
+                a.color.green + (b.color.green - a.color.green) * t,
+                a.color.blue + (b.color.blue - a.color.blue) * t,
+                a.alpha + (b.alpha - a.alpha) * t,
+            )),
+            (color, None) | (None, color) => color,
+        };
+        let tangent = match (a.tangent, b.tangent) {
+            (Some(a), Some(b)) => {
+                let direction = Vector3::lerp(Vector3::new(a.x, a.y, a.z), Vector3::new(b.x, b.y, b.z), t).normalize();
+                let sign = if t < 0.5 { a.w } else { b.w };
+                Some(Vector4::new(direction.x, direction.y, direction.z, sign))
+            }
+            (tangent, None) | (None, tangent) => tangent,
+        };
+
+        let mut vertex = SimpleSkinVertex {
+            position: Vector3::lerp(a.position, b.position, t),
+            influences: [influences[0].0, influences[1].0, influences[2].0, influences[3].0],
+            weights: [influences[0].1, influences[1].1, influences[2].1, influences[3].1],
+            normal: Vector3::lerp(a.normal, b.normal, t).normalize(),
+            uv: Vector2::lerp(a.uv, b.uv, t),
+            color,
+            tangent,
+        };
+        vertex.normalize_weights();
+
+        vertex
+    }
+    /// Clamps negative weights, makes them sum up to 1.0 and sorts the influences by descending weight
+    pub fn normalize_weights(&mut self) {
+        for weight in &mut self.weights {
+            if *weight < 0.0 || weight.is_nan() {
+                *weight = 0.0;
+            }
+        }
+
+        let weight_sum: f32 = self.weights.iter().sum();
+        if weight_sum == 0.0 {
+            self.weights = [1.0, 0.0, 0.0, 0.0];
+        } else {
+            for weight in &mut self.weights {
+                *weight /= weight_sum;
+            }
+        }
+
+        let mut pairs = [(0u8, 0f32); 4];
+        for (pair, (influence, weight)) in pairs.iter_mut().zip(self.influences.iter().zip(self.weights.iter())) {
+            *pair = (*influence, *weight);
+        }
+        pairs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        for ((influence, weight), pair) in self.influences.iter_mut().zip(self.weights.iter_mut()).zip(pairs.iter()) {
+            *influence = pair.0;
+            *weight = pair.1;
+        }
+    }
+
+    pub fn equals(&self, other: &SimpleSkinVertex, epsilon: f32) -> bool {
+        let near = |a: f32, b: f32| (a - b).abs() <= epsilon;
+
+        let colors_equal = match (self.color, other.color) {
+            (Some(a), Some(b)) => {
+                near(a.color.red, b.color.red)
+                    && near(a.color.green, b.color.green)
+                    && near(a.color.blue, b.color.blue)
+                    && near(a.alpha, b.alpha)
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        let tangents_equal = match (self.tangent, other.tangent) {
+            (Some(a), Some(b)) => a.approx_eq(&b, epsilon),
+            (None, None) => true,
+            _ => false,
+        };
+
+        self.influences == other.influences
+            && near(self.position.x, other.position.x)
+            && near(self.position.y, other.position.y)
+            && near(self.position.z, other.position.z)
+            && near(self.normal.x, other.normal.x)
+            && near(self.normal.y, other.normal.y)
+            && near(self.normal.z, other.normal.z)
+            && near(self.uv.x, other.uv.x)
+            && near(self.uv.y, other.uv.y)
+            && self
+                .weights
+                .iter()
+                .zip(other.weights.iter())
+                .all(|(a, b)| near(*a, *b))
+            && colors_equal
+            && tangents_equal
+    }
+
+    /// Size in bytes of a vertex type, `None` for unknown types
+    pub fn size_of_type(vertex_type: u32) -> Option<u32> {
+        match vertex_type {
+            0 => Some(52),
+            1 => Some(56),
+            2 => Some(72),
+            _ => None,
         }
     }
+
     fn read<T: Read + Seek>(vertex_type: u32, reader: &mut BinaryReader<T>) -> io::Result<Self> {
         Ok(SimpleSkinVertex {
             position: Vector3::read(reader)?,
-            influences: [
-                reader.read_u8()?,
-                reader.read_u8()?,
-                reader.read_u8()?,
-                reader.read_u8()?,
-            ],
-            weights: [
-                reader.read_f32()?,
-                reader.read_f32()?,
-                reader.read_f32()?,
-                reader.read_f32()?,
-            ],
+            influences: reader.read_u8_array::<4>()?,
+            weights: reader.read_f32_array::<4>()?,
             normal: Vector3::read(reader)?,
             uv: Vector2::read(reader)?,
-            color: if vertex_type == 1 {
+            color: if vertex_type >= 1 {
                 Option::Some(LinSrgba::read_rgba_u8(reader)?)
             } else {
                 Option::None
             },
+            tangent: if vertex_type >= 2 {
+                Option::Some(Vector4::read(reader)?)
+            } else {
+                Option::None
+            },
         })
     }
 
-    fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+    /// Missing color is written as black and a missing tangent as zero if `vertex_type` needs them
+    fn write<T: Write + Seek>(&self, vertex_type: u32, writer: &mut BinaryWriter<T>) -> io::Result<()> {
         self.position.write(writer)?;
 
         for i in 0..4 {
             writer.write(self.influences[i])?;
         }
-        for i in 0..4 {
-            writer.write(self.weights[i])?;
-        }
+        writer.write_f32_slice(&self.weights)?;
 
         self.normal.write(writer)?;
         self.uv.write(writer)?;
 
-        match self.color {
-            Some(x) => x.write_rgba_u8(writer),
-            None => Ok(()),
+        if vertex_type >= 1 {
+            self.color.unwrap_or_else(|| LinSrgba::new(0.0, 0.0, 0.0, 0.0)).write_rgba_u8(writer)?;
         }
+        if vertex_type >= 2 {
+            self.tangent.unwrap_or_else(Vector4::zero).write(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SimpleSkinLodChain {
+    pub fn new(mut lods: Vec<SimpleSkin>) -> Self {
+        lods.sort_by_key(|lod| std::cmp::Reverse(lod.triangle_count()));
+
+        SimpleSkinLodChain { lods }
+    }
+
+    pub fn read_chain(file_locations: &[&Path]) -> Result<Self> {
+        let lods = file_locations
+            .iter()
+            .map(|file_location| SimpleSkin::read_from_file(file_location))
+            .collect::<Result<Vec<SimpleSkin>>>()?;
+
+        Ok(SimpleSkinLodChain::new(lods))
+    }
+
+    /// Level 0 is the most detailed one
+    pub fn lod(&self, level: usize) -> Option<&SimpleSkin> {
+        self.lods.get(level)
+    }
+    pub fn lod_mut(&mut self, level: usize) -> Option<&mut SimpleSkin> {
+        self.lods.get_mut(level)
+    }
+    pub fn lods(&self) -> &[SimpleSkin] {
+        &self.lods
+    }
+
+    /// Decimates the most detailed LOD and inserts the result by its triangle count, returns its level
+    pub fn add_generated_lod(&mut self, target_ratio: f32) -> Option<usize> {
+        let lod = SimpleSkinLodChain::generate_lod(self.lods.first()?, target_ratio);
+        let triangle_count = lod.triangle_count();
+        let level = self
+            .lods
+            .iter()
+            .position(|existing| existing.triangle_count() < triangle_count)
+            .unwrap_or(self.lods.len());
+
+        self.lods.insert(level, lod);
+        Some(level)
+    }
+
+    /// Simplifies every submesh of `source` down to about `target_ratio` of its triangles.
+    /// Open edges and UV seams are kept in place, so small or heavily split submeshes may not reach the ratio
+    pub fn generate_lod(source: &SimpleSkin, target_ratio: f32) -> SimpleSkin {
+        let target_ratio = target_ratio.clamp(0.0, 1.0);
+        let submeshes = source
+            .submeshes
+            .iter()
+            .map(|submesh| {
+                let positions: Vec<Vector3> = submesh.vertices.iter().map(|vertex| vertex.position).collect();
+                let target_triangle_count = ((submesh.indices.len() / 3) as f32 * target_ratio).ceil() as usize;
+
+                let mut vertices = submesh.vertices.clone();
+                let mut indices = simplify::simplify(&positions, &submesh.indices, target_triangle_count);
+                triangles::remove_unused_vertices(&mut vertices, &mut indices);
+
+                SimpleSkinSubmesh::new(submesh.name.clone(), vertices, indices)
+            })
+            .collect();
+
+        SimpleSkin::new(submeshes)
     }
 }
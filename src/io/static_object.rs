@@ -1,13 +1,24 @@
+use crate::error::{Error, Result};
 use crate::io::binary_reader::BinaryReader;
+use crate::io::binary_writer::BinaryWriter;
+use crate::io::obj::ObjFile;
+use crate::io::ply;
+use crate::io::ply::{PlyFormat, PlyPart, PlyType, PlyValue};
+use crate::io::simple_skin::{SimpleSkin, SimpleSkinSubmesh, SimpleSkinVertex};
 use crate::structures::box3d::Box3D;
 use crate::structures::color::LinSrgbaExt;
 use crate::structures::vector2::Vector2;
 use crate::structures::vector3::Vector3;
+use crate::utilities::triangles;
+use crate::utilities::triangles::Triangles;
+use crate::utilities::uv_convention::UvConvention;
+use crate::utilities::version::Version;
 use bitflags;
+use glam::Mat4;
 use palette::LinSrgba;
 use std::collections::HashMap;
 use std::io;
-use std::io::{Cursor, Error, ErrorKind, Read, Seek};
+use std::io::{Cursor, ErrorKind, Read, Seek, Write};
 use std::ops::SubAssign;
 use std::path::Path;
 
@@ -21,6 +32,7 @@ bitflags! {
 #[derive(Debug)]
 pub struct StaticObject {
     pub name: String,
+    version: Version,
     submeshes: Vec<StaticObjectSubmesh>,
     bounding_box: Box3D,
 }
@@ -31,7 +43,7 @@ pub struct StaticObjectSubmesh {
     vertices: Vec<StaticObjectVertex>,
     indices: Vec<u32>,
 }
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 pub struct StaticObjectVertex {
     pub position: Vector3,
     pub uv: Vector2,
@@ -45,30 +57,46 @@ struct StaticObjectFace {
 }
 
 impl StaticObject {
-    pub fn read_scb_from_file(file_location: &Path) -> io::Result<Self> {
-        StaticObject::read_scb(&mut BinaryReader::from_location(file_location))
+    pub fn new(name: String, submeshes: Vec<StaticObjectSubmesh>) -> Self {
+        let bounding_box = Box3D::from_points(
+            submeshes
+                .iter()
+                .flat_map(|submesh| submesh.vertices.iter())
+                .map(|vertex| vertex.position),
+        );
+
+        StaticObject {
+            name,
+            version: Version::new(3, 2),
+            submeshes,
+            bounding_box,
+        }
     }
-    pub fn read_scb_from_buffer(buffer: Cursor<Vec<u8>>) -> io::Result<Self> {
+
+    pub fn read_scb_from_file(file_location: &Path) -> Result<Self> {
+        StaticObject::read_scb(&mut BinaryReader::from_location(file_location)?)
+    }
+    pub fn read_scb_from_buffer(buffer: Cursor<Vec<u8>>) -> Result<Self> {
         StaticObject::read_scb(&mut BinaryReader::from_buffer(buffer))
     }
-    pub fn read_scb<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
+    pub fn read_scb<T: Read + Seek>(reader: &mut BinaryReader<T>) -> Result<Self> {
         let magic = reader.read_string(8)?;
         if &magic != "r3d2Mesh" {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Incorrect file signature",
-            ));
+            return Err(Error::InvalidMagic {
+                expected: String::from("r3d2Mesh"),
+                found: magic,
+            });
         }
 
         let major = reader.read_u16()?;
         let minor = reader.read_u16()?;
         if (major != 3 && major != 2) || (minor != 1 && minor != 2) {
-            return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+            return Err(Error::UnsupportedVersion { major: major as u32, minor: minor as u32 });
         }
 
         let name = reader.read_padded_string(128)?;
-        let vertex_count = reader.read_u32()?;
-        let face_count = reader.read_u32()?;
+        let vertex_count = reader.read_count(12)?;
+        let face_count = reader.read_count(100)?;
         let flags = StaticObjectFlags::from_bits(reader.read_u32()?);
         let bounding_box = Box3D::read(reader)?;
         let has_vertex_colors = if major == 3 && minor == 2 {
@@ -77,33 +105,226 @@ impl StaticObject {
             false
         };
 
-        let mut vertices: Vec<Vector3> = Vec::with_capacity(vertex_count as usize);
-        let mut vertex_colors: Vec<LinSrgba> = Vec::default();
-        for i in 0..vertex_count {
-            vertices.push(Vector3::read(reader)?);
+        let vertices = reader.read_vec_of::<Vector3>(vertex_count)?;
+        let vertex_colors = if has_vertex_colors {
+            reader.read_vec(vertex_count, LinSrgba::read_rgba_u8)?
+        } else {
+            Vec::default()
+        };
+
+        let central_point = Vector3::read(reader)?;
+
+        let faces = reader.read_vec(face_count, StaticObjectFace::read_binary)?;
+
+        Ok(StaticObject {
+            name,
+            version: Version::new(major as u8, minor as u8),
+            submeshes: StaticObject::create_submeshes(&vertices, &vertex_colors, &faces),
+            bounding_box,
+        })
+    }
+
+    /// Creates a submesh for each material, or group if the faces have no material.
+    /// Vertices are split where the OBJ gives a position different UVs so the per-corner UVs are kept
+    pub fn read_obj(file_location: &Path) -> io::Result<Self> {
+        let obj = ObjFile::read_from_file(file_location)?;
+
+        let mut vertices: Vec<Vector3> = Vec::new();
+        let mut faces: Vec<StaticObjectFace> = Vec::new();
+        for group in &obj.groups {
+            // Keeping the vertices of each group together gives every submesh its own vertex range
+            let mut vertex_lookup: HashMap<(usize, Option<usize>), u32> = HashMap::new();
+            for triangle in &group.triangles {
+                let mut indices = [0u32; 3];
+                let mut uvs = [Vector2::zero(); 3];
+                for (i, corner) in triangle.iter().enumerate() {
+                    indices[i] = *vertex_lookup.entry((corner.position, corner.uv)).or_insert_with(|| {
+                        vertices.push(obj.positions[corner.position]);
+                        vertices.len() as u32 - 1
+                    });
+                    if let Some(uv) = corner.uv {
+                        uvs[i] = UvConvention::BottomLeft.convert(obj.uvs[uv]);
+                    }
+                }
+
+                faces.push(StaticObjectFace { indices, material: group.name.clone(), uvs });
+            }
+        }
+
+        let name = file_location
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(StaticObject::new(name, StaticObject::create_submeshes(&vertices, &[], &faces)))
+    }
+
+    pub fn write_scb_to_file(&self, file_location: &Path) -> Result<()> {
+        let mut writer = BinaryWriter::from_location(file_location)?;
+        self.write_scb(&mut writer)?;
+
+        Ok(writer.flush()?)
+    }
+    pub fn write_scb_to_vec(&self) -> Result<Vec<u8>> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        self.write_scb(&mut writer)?;
+
+        Ok(writer.into_inner()?.into_inner())
+    }
+    /// Writes version 3.2, the vertex colors are only written if any vertex has one
+    pub fn write_scb<T: Write + Seek>(&self, writer: &mut BinaryWriter<T>) -> Result<()> {
+        if self.name.len() > 128 {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "Name is longer than 128 bytes").into());
+        }
+        if let Some(submesh) = self.submeshes.iter().find(|submesh| submesh.name.len() > 64) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Material {} is longer than 64 bytes", submesh.name),
+            )
+            .into());
+        }
+
+        let has_vertex_colors = self
+            .submeshes
+            .iter()
+            .any(|submesh| submesh.vertices.iter().any(|vertex| vertex.color.is_some()));
+        let flags = if has_vertex_colors { StaticObjectFlags::VERTEX_COLORS } else { StaticObjectFlags::empty() };
+
+        writer.write_string("r3d2Mesh")?;
+        writer.write_u16(3)?; // Major
+        writer.write_u16(2)?; // Minor
+        writer.write_padded_string(&self.name, 128)?;
+        writer.write_u32(self.submeshes.iter().map(|submesh| submesh.vertices.len() as u32).sum())?;
+        writer.write_u32(self.submeshes.iter().map(|submesh| submesh.indices.len() as u32 / 3).sum())?;
+        writer.write_u32(flags.bits())?;
+        self.bounding_box.write(writer)?;
+        writer.write_u32(has_vertex_colors as u32)?;
+
+        for vertex in self.submeshes.iter().flat_map(|submesh| submesh.vertices.iter()) {
+            vertex.position.write(writer)?;
         }
         if has_vertex_colors {
-            vertex_colors.reserve(vertex_count as usize);
+            for vertex in self.submeshes.iter().flat_map(|submesh| submesh.vertices.iter()) {
+                vertex.color.unwrap_or_else(|| LinSrgba::new(1.0, 1.0, 1.0, 1.0)).write_rgba_u8(writer)?;
+            }
+        }
 
-            for i in 0..vertex_count {
-                vertex_colors.push(LinSrgba::read_rgba_u8(reader)?);
+        self.bounding_box.center().write(writer)?; // Central point
+
+        let mut base_vertex = 0u32;
+        for submesh in &self.submeshes {
+            for face in submesh.indices.chunks_exact(3) {
+                for index in face {
+                    writer.write_u32(index + base_vertex)?;
+                }
+                writer.write_padded_string(&submesh.name, 64)?;
+                for index in face {
+                    submesh.vertices[*index as usize].uv.write(writer)?;
+                }
             }
+
+            base_vertex += submesh.vertices.len() as u32;
         }
 
-        let central_point = Vector3::read(reader)?;
+        Ok(())
+    }
 
-        let mut faces: Vec<StaticObjectFace> = Vec::with_capacity(face_count as usize);
-        for i in 0..face_count {
-            faces.push(StaticObjectFace::read_binary(reader)?);
+    pub fn transform(&mut self, matrix: Mat4) {
+        for submesh in &mut self.submeshes {
+            submesh.transform(matrix);
         }
 
-        Ok(StaticObject {
-            name,
-            submeshes: StaticObject::create_submeshes(&vertices, &vertex_colors, &faces),
-            bounding_box,
+        self.bounding_box = Box3D::from_points(
+            self.submeshes
+                .iter()
+                .flat_map(|submesh| submesh.vertices.iter())
+                .map(|vertex| vertex.position),
+        );
+    }
+
+    pub fn submeshes(&mut self) -> &mut [StaticObjectSubmesh] { &mut self.submeshes }
+    /// The version the object was read as, 3.2 for objects created in memory
+    pub fn version(&self) -> Version { self.version }
+
+    /// Writes the submeshes as a single PLY mesh with position, UV and, if any vertex has one, color.
+    /// The faces of each submesh are listed in a `material` comment
+    pub fn write_ply<W: Write>(&self, out: &mut W, format: PlyFormat) -> io::Result<()> {
+        let has_color = self
+            .submeshes
+            .iter()
+            .any(|submesh| submesh.vertices.iter().any(|vertex| vertex.color.is_some()));
+
+        let mut properties = vec![
+            ("x", PlyType::Float),
+            ("y", PlyType::Float),
+            ("z", PlyType::Float),
+            ("s", PlyType::Float),
+            ("t", PlyType::Float),
+        ];
+        if has_color {
+            properties.extend_from_slice(&ply::COLOR_PROPERTIES);
+        }
+
+        let parts: Vec<PlyPart<_, _>> = self
+            .submeshes
+            .iter()
+            .map(|submesh| PlyPart {
+                name: Some(&submesh.name),
+                vertices: &submesh.vertices,
+                indices: &submesh.indices,
+            })
+            .collect();
+
+        ply::write_parts(out, format, &[], &parts, &properties, |vertex, values| {
+            let uv = vertex.flipped_uv();
+            values.extend_from_slice(&[
+                PlyValue::Float(vertex.position.x),
+                PlyValue::Float(vertex.position.y),
+                PlyValue::Float(vertex.position.z),
+                PlyValue::Float(uv.x),
+                PlyValue::Float(uv.y),
+            ]);
+            if has_color {
+                values.extend_from_slice(&ply::color_values(vertex.color));
+            }
         })
     }
 
+    /// Creates a skin with every vertex fully weighted to the first bone and normals computed from the faces
+    pub fn to_simple_skin(&self) -> io::Result<SimpleSkin> {
+        let mut submeshes: Vec<SimpleSkinSubmesh> = Vec::with_capacity(self.submeshes.len());
+        for submesh in &self.submeshes {
+            if submesh.vertices.len() > std::u16::MAX as usize + 1 {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Submesh {} has too many vertices for 16-bit indices", submesh.name),
+                ));
+            }
+
+            let vertices = submesh
+                .vertices
+                .iter()
+                .map(|vertex| SimpleSkinVertex {
+                    position: vertex.position,
+                    influences: [0, 0, 0, 0],
+                    weights: [1.0, 0.0, 0.0, 0.0],
+                    normal: Vector3::zero(),
+                    uv: vertex.uv,
+                    color: vertex.color,
+                    tangent: None,
+                })
+                .collect();
+            let indices = submesh.indices.iter().map(|index| *index as u16).collect();
+
+            let mut skin_submesh = SimpleSkinSubmesh::new(submesh.name.clone(), vertices, indices);
+            skin_submesh.recompute_normals();
+
+            submeshes.push(skin_submesh);
+        }
+
+        Ok(SimpleSkin::new(submeshes))
+    }
+
     fn create_submeshes(
         vertices: &[Vector3],
         vertex_colors: &[LinSrgba],
@@ -141,11 +362,12 @@ impl StaticObject {
             }
 
             // Build vertex list
-            let vertex_count = max_vertex - min_vertex;
+            let vertex_count = max_vertex - min_vertex + 1;
             let mut submesh_vertices: Vec<StaticObjectVertex> =
                 Vec::with_capacity(vertex_count as usize);
-            for i in min_vertex..vertex_count {
-                let uv = *uv_map.get(&i).unwrap();
+            for i in min_vertex..=max_vertex {
+                // Vertices inside the range which no face of this submesh uses don't get a UV
+                let uv = uv_map.get(&i).copied().unwrap_or(Vector2::zero());
 
                 if has_vertex_colors {
                     submesh_vertices.push(StaticObjectVertex::new_color(
@@ -204,12 +426,48 @@ impl StaticObjectSubmesh {
         self.indices = indices;
     }
 
+    pub fn transform(&mut self, matrix: Mat4) {
+        for vertex in &mut self.vertices {
+            vertex.position = matrix.transform_point3(vertex.position.into()).into();
+        }
+    }
+
     pub fn vertices(&mut self) -> &mut [StaticObjectVertex] {
         &mut self.vertices
     }
     pub fn indices(&mut self) -> &mut Vec<u32> {
         &mut self.indices
     }
+    pub fn triangles(&self) -> io::Result<Triangles<'_, StaticObjectVertex, u32>> {
+        Triangles::new(&self.vertices, &self.indices)
+    }
+    /// Removes degenerate triangles and optionally the vertices left unused, returns the number of triangles removed
+    pub fn clean(&mut self, remove_unused_vertices: bool) -> usize {
+        let removed = triangles::remove_degenerate_triangles(&self.vertices, &mut self.indices, |vertex| vertex.position);
+        if remove_unused_vertices {
+            triangles::remove_unused_vertices(&mut self.vertices, &mut self.indices);
+        }
+
+        removed
+    }
+    /// Static objects have no vertex normals, so triangles are flipped to face `expected_up`.
+    /// That suits flat meshes like ground decals, use `flip_winding` for closed ones. Returns the number of triangles flipped
+    pub fn fix_winding(&mut self, expected_up: Vector3) -> usize {
+        triangles::fix_winding(&self.vertices, &mut self.indices, |vertex| vertex.position, |_| expected_up)
+    }
+    /// Reverses the winding of every triangle, for meshes from tools with the opposite handedness
+    pub fn flip_winding(&mut self) {
+        triangles::flip_winding(&mut self.indices);
+    }
+    /// Expands the indexed triangles into a flat vertex list
+    pub fn to_triangle_soup(&self) -> io::Result<Vec<StaticObjectVertex>> {
+        let mut vertices: Vec<StaticObjectVertex> = Vec::with_capacity(self.indices.len());
+        for triangle in self.triangles()? {
+            vertices.extend(triangle.iter().map(|vertex| **vertex));
+        }
+
+        Ok(vertices)
+    }
 }
 
 impl StaticObjectVertex {
@@ -227,6 +485,11 @@ impl StaticObjectVertex {
             color: Option::Some(color),
         }
     }
+
+    /// The UV with a bottom left origin
+    pub fn flipped_uv(&self) -> Vector2 {
+        UvConvention::BottomLeft.convert(self.uv)
+    }
 }
 
 impl StaticObjectFace {
@@ -1,16 +1,26 @@
+use crate::error::{Error, Result};
 use crate::io::binary_reader::BinaryReader;
 use crate::io::binary_writer::BinaryWriter;
+use crate::io::ply;
+use crate::io::ply::{PlyFormat, PlyPart, PlyType, PlyValue};
 use crate::structures::box3d::Box3D;
 use crate::structures::render_bucket_grid::RenderBucketGrid;
 use crate::structures::sphere::Sphere;
 use crate::structures::vector2::Vector2;
 use crate::structures::vector3::Vector3;
+use crate::utilities::triangles;
+use crate::utilities::triangles::Triangles;
+use crate::utilities::uv_convention::UvConvention;
+use crate::utilities::version::Version;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::io;
-use std::io::{Cursor, Error, ErrorKind, Read, Seek, Write};
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
 use std::string::String;
 
 pub struct WorldGeometry {
+    version: Version,
     models: Vec<WorldGeometryModel>,
     bucket_grid: RenderBucketGrid,
 }
@@ -34,41 +44,39 @@ pub struct WorldGeometryVertex {
 impl WorldGeometry {
     pub fn new(models: Vec<WorldGeometryModel>, bucket_grid_template: RenderBucketGrid) -> Self {
         WorldGeometry {
+            version: Version::new(5, 0),
             models,
             bucket_grid: bucket_grid_template,
         }
     }
-    pub fn read_from_file(file_location: &Path) -> io::Result<Self> {
-        WorldGeometry::read(&mut BinaryReader::from_location(file_location))
+    pub fn read_from_file(file_location: &Path) -> Result<Self> {
+        WorldGeometry::read(&mut BinaryReader::from_location(file_location)?)
     }
-    pub fn read_from_buffer(buffer: Cursor<Vec<u8>>) -> io::Result<Self> {
+    pub fn read_from_buffer(buffer: Cursor<Vec<u8>>) -> Result<Self> {
         WorldGeometry::read(&mut BinaryReader::from_buffer(buffer))
     }
-    pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
+    pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> Result<Self> {
         let magic: String = reader.read_string(4)?;
         if &magic != "WGEO" {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Incorrect file signature",
-            ));
+            return Err(Error::InvalidMagic {
+                expected: String::from("WGEO"),
+                found: magic,
+            });
         }
 
         let version: u32 = reader.read_u32()?;
         if version != 5 && version != 4 {
-            return Err(Error::new(ErrorKind::InvalidData, "Unsupported version"));
+            return Err(Error::UnsupportedVersion { major: version, minor: 0 });
         }
+        let major = u8::try_from(version).map_err(|_| Error::UnsupportedVersion { major: version, minor: 0 })?;
 
         Ok(WorldGeometry {
+            version: Version::new(major, 0),
             models: {
-                let model_count: u32 = reader.read_u32()?;
+                let model_count = reader.read_count(372)?;
                 let face_count: u32 = reader.read_u32()?;
-                let mut models: Vec<WorldGeometryModel> = Vec::with_capacity(model_count as usize);
-
-                for i in 0..model_count {
-                    models.push(WorldGeometryModel::read(reader)?);
-                }
 
-                models
+                reader.read_vec(model_count, WorldGeometryModel::read)?
             },
             bucket_grid: {
                 if version == 5 {
@@ -80,15 +88,38 @@ impl WorldGeometry {
         })
     }
 
-    pub fn write_to_file(&mut self, file_location: &Path) -> io::Result<()> {
-        self.write(&mut BinaryWriter::from_location(file_location))
+    pub fn write_to_file(&mut self, file_location: &Path) -> Result<()> {
+        let mut writer = BinaryWriter::from_location(file_location)?;
+        self.write(&mut writer)?;
+
+        Ok(writer.flush()?)
+    }
+    pub fn write_to_buffer(&mut self, buffer: Cursor<Vec<u8>>) -> Result<()> {
+        let mut writer = BinaryWriter::from_buffer(buffer);
+        self.write(&mut writer)?;
+
+        Ok(writer.flush()?)
     }
-    pub fn write_to_buffer(&mut self, buffer: Cursor<Vec<u8>>) -> io::Result<()> {
-        self.write(&mut BinaryWriter::from_buffer(buffer))
+    pub fn write_to_vec(&mut self) -> Result<Vec<u8>> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        self.write(&mut writer)?;
+
+        Ok(writer.into_inner()?.into_inner())
     }
-    pub fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+    /// Writes the version the geometry was read as, or 5 if it was created in memory
+    pub fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> Result<()> {
+        let version = self.version.major as u32;
+
+        self.write_version(writer, version)
+    }
+    /// Version 4 files don't have a bucket grid
+    pub fn write_version<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>, version: u32) -> Result<()> {
+        if version != 5 && version != 4 {
+            return Err(Error::UnsupportedVersion { major: version, minor: 0 });
+        }
+
         writer.write_string("WGEO")?;
-        writer.write_u32(5)?; // Version
+        writer.write_u32(version)?;
         writer.write_u32(self.models.len() as u32)?; // Model Count
 
         let face_count = {
@@ -106,7 +137,9 @@ impl WorldGeometry {
             model.write(writer)?;
         }
 
-        self.bucket_grid.write(writer)?;
+        if version == 5 {
+            self.bucket_grid.write(writer)?;
+        }
 
         Ok(())
     }
@@ -118,8 +151,93 @@ impl WorldGeometry {
         self.models.remove(index);
     }
 
+    /// Maps each material to the indices of the models using it
+    pub fn material_stats(&self) -> HashMap<String, Vec<usize>> {
+        let mut material_stats: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, model) in self.models.iter().enumerate() {
+            material_stats.entry(model.material.clone()).or_default().push(i);
+        }
+
+        material_stats
+    }
+    pub fn unique_textures(&self) -> HashSet<String> {
+        self.models.iter().map(|model| model.texture.clone()).collect()
+    }
+
+    /// Concatenates all models into one mesh, offsetting each model's indices by the vertices before it
+    pub fn merged_mesh(&self) -> (Vec<WorldGeometryVertex>, Vec<u32>) {
+        let mut vertices: Vec<WorldGeometryVertex> = Vec::with_capacity(self.total_vertex_count());
+        let mut indices: Vec<u32> = Vec::with_capacity(self.total_face_count() * 3);
+        for model in &self.models {
+            let base_vertex = vertices.len() as u32;
+
+            vertices.extend_from_slice(&model.vertices);
+            indices.extend(model.indices.iter().map(|index| index + base_vertex));
+        }
+
+        (vertices, indices)
+    }
+
+    /// Streams the models as a single PLY mesh with position and UV per vertex, in the same order as `merged_mesh`.
+    /// UVs are flipped to a bottom left origin like most mesh tools expect. `out` should be buffered
+    pub fn write_ply<W: Write>(&self, out: &mut W, format: PlyFormat) -> io::Result<()> {
+        let parts: Vec<PlyPart<_, _>> = self
+            .models
+            .iter()
+            .map(|model| PlyPart { name: None, vertices: &model.vertices, indices: &model.indices })
+            .collect();
+
+        ply::write_parts(
+            out,
+            format,
+            &[String::from("Exported from a League of Legends WGEO")],
+            &parts,
+            &[
+                ("x", PlyType::Float),
+                ("y", PlyType::Float),
+                ("z", PlyType::Float),
+                ("s", PlyType::Float),
+                ("t", PlyType::Float),
+            ],
+            |vertex, values| {
+                let uv = UvConvention::BottomLeft.convert(vertex.uv);
+                values.extend_from_slice(&[
+                    PlyValue::Float(vertex.position.x),
+                    PlyValue::Float(vertex.position.y),
+                    PlyValue::Float(vertex.position.z),
+                    PlyValue::Float(uv.x),
+                    PlyValue::Float(uv.y),
+                ]);
+            },
+        )
+    }
+
+    pub fn total_vertex_count(&self) -> usize {
+        self.models.iter().map(|model| model.vertices.len()).sum()
+    }
+    pub fn total_face_count(&self) -> usize {
+        self.models.iter().map(|model| model.indices.len() / 3).sum()
+    }
+
+    /// Applies `remap` to the UVs of every model, e.g. to move them into an atlas
+    pub fn remap_uvs(&mut self, remap: impl Fn(Vector2) -> Vector2) {
+        for model in &mut self.models {
+            for vertex in &mut model.vertices {
+                vertex.uv = remap(vertex.uv);
+            }
+        }
+    }
+
+    pub fn version(&self) -> Version { self.version }
+    pub fn set_version(&mut self, version: Version) { self.version = version; }
     pub fn models(&self) -> &[WorldGeometryModel] { &self.models }
     pub fn bucket_grid(&self) -> &RenderBucketGrid { &self.bucket_grid }
+    /// Has to be called after editing the models, otherwise the game culls them using the old grid
+    pub fn rebuild_bucket_grid(&mut self, buckets_per_side: u16) -> io::Result<()> {
+        self.bucket_grid = RenderBucketGrid::build(&self.models, buckets_per_side)?;
+
+        Ok(())
+    }
 }
 
 impl WorldGeometryModel {
@@ -143,24 +261,17 @@ impl WorldGeometryModel {
         let material = reader.read_padded_string(64)?;
         let bounding_sphere = Sphere::read(reader)?;
         let bounding_box = Box3D::read(reader)?;
-        let vertex_count = reader.read_u32()?;
-        let index_count = reader.read_u32()?;
+        let vertex_count = reader.read_count(20)?;
+        let index_count = reader.read_count(2)?;
 
-        let mut vertices: Vec<WorldGeometryVertex> = Vec::with_capacity(vertex_count as usize);
-        for i in 0..vertex_count {
-            vertices.push(WorldGeometryVertex::read(reader)?);
-        }
+        let vertices = reader.read_vec(vertex_count, WorldGeometryVertex::read)?;
 
-        let mut indices: Vec<u32> = Vec::with_capacity(index_count as usize);
-        if index_count <= 65536 {
-            for i in 0..index_count {
-                indices.push(reader.read_u16()? as u32);
-            }
+        let indices = if index_count <= 65536 {
+            reader.read_vec(index_count, |reader| Ok(reader.read_u16()? as u32))?
         } else {
-            for i in 0..index_count {
-                indices.push(reader.read_u32()?);
-            }
-        }
+            reader.check_count(index_count, 4)?;
+            reader.read_vec(index_count, |reader| reader.read_u32())?
+        };
 
         Ok(WorldGeometryModel {
             texture,
@@ -200,64 +311,70 @@ impl WorldGeometryModel {
     }
 
     pub fn central_point(&mut self) -> Vector3 {
-        let bounds = self.bounding_box();
-
-        Vector3 {
-            x: 0.5 * (bounds.max.x - bounds.min.x),
-            y: 0.5 * (bounds.max.y - bounds.min.y),
-            z: 0.5 * (bounds.max.z - bounds.min.z),
-        }
+        self.bounding_box().center()
     }
     pub fn bounding_box(&mut self) -> Box3D {
-        if self.bounding_box == Box3D::ZERO && !self.vertices.is_empty() {
-            let mut min = Vector3::new(
-                self.vertices[0].position.x,
-                std::f32::NEG_INFINITY,
-                self.vertices[0].position.z,
-            );
-            let mut max = Vector3::new(
-                self.vertices[0].position.x,
-                std::f32::INFINITY,
-                self.vertices[0].position.z,
-            );
-
-            for vertex in &self.vertices {
-                if min.x > vertex.position.x {
-                    min.x = vertex.position.x
-                };
-                if min.y > vertex.position.y {
-                    min.y = vertex.position.y
-                };
-                if min.z > vertex.position.z {
-                    min.z = vertex.position.z
-                };
-                if max.x < vertex.position.x {
-                    max.x = vertex.position.x
-                };
-                if max.y < vertex.position.y {
-                    max.y = vertex.position.y
-                };
-                if max.z < vertex.position.z {
-                    max.z = vertex.position.z
-                };
-            }
-
-            self.bounding_box = Box3D::new(min, max);
+        if self.bounding_box == Box3D::ZERO {
+            self.bounding_box =
+                Box3D::from_points(self.vertices.iter().map(|vertex| vertex.position));
         }
 
         self.bounding_box
     }
     pub fn bounding_sphere(&mut self) -> Sphere {
         if self.bounding_sphere == Sphere::ZERO {
-            let bounds = self.bounding_box();
-            let central_point = self.central_point();
-            self.bounding_sphere = Sphere::new(central_point, Vector3::distance(central_point, bounds.max));
+            self.bounding_sphere = Sphere::from_points(self.vertices.iter().map(|vertex| vertex.position));
         }
 
         self.bounding_sphere
     }
+    /// Returns (min, max) of the vertex UVs, or zero if there are no vertices
+    pub fn uv_bounds(&self) -> (Vector2, Vector2) {
+        let mut uvs = self.vertices.iter().map(|vertex| vertex.uv);
+        match uvs.next() {
+            Some(first) => uvs.fold((first, first), |(min, max), uv| (Vector2::min(min, uv), Vector2::max(max, uv))),
+            None => (Vector2::ZERO, Vector2::ZERO),
+        }
+    }
+
     pub fn vertices(&self) -> &[WorldGeometryVertex] { &self.vertices }
+    /// Resets the cached bounds since the positions may change
+    pub fn vertices_mut(&mut self) -> &mut [WorldGeometryVertex] {
+        self.bounding_box = Box3D::ZERO;
+        self.bounding_sphere = Sphere::ZERO;
+
+        &mut self.vertices
+    }
     pub fn indices(&self) -> &[u32] { &self.indices }
+    pub fn indices_mut(&mut self) -> &mut Vec<u32> {
+        self.bounding_box = Box3D::ZERO;
+        self.bounding_sphere = Sphere::ZERO;
+
+        &mut self.indices
+    }
+    pub fn triangles(&self) -> io::Result<Triangles<'_, WorldGeometryVertex, u32>> {
+        Triangles::new(&self.vertices, &self.indices)
+    }
+    /// Removes degenerate triangles and optionally the vertices left unused, returns the number of triangles removed
+    pub fn clean(&mut self, remove_unused_vertices: bool) -> usize {
+        let removed = triangles::remove_degenerate_triangles(&self.vertices, &mut self.indices, |vertex| vertex.position);
+        if remove_unused_vertices {
+            triangles::remove_unused_vertices(&mut self.vertices, &mut self.indices);
+            self.bounding_box = Box3D::ZERO;
+            self.bounding_sphere = Sphere::ZERO;
+        }
+
+        removed
+    }
+    /// Expands the indexed triangles into a flat vertex list
+    pub fn to_triangle_soup(&self) -> io::Result<Vec<WorldGeometryVertex>> {
+        let mut vertices: Vec<WorldGeometryVertex> = Vec::with_capacity(self.indices.len());
+        for triangle in self.triangles()? {
+            vertices.extend(triangle.iter().map(|vertex| **vertex));
+        }
+
+        Ok(vertices)
+    }
 
     pub fn set_model_data(&mut self, vertices: Vec<WorldGeometryVertex>, indices: Vec<u32>) {
         self.vertices = vertices;
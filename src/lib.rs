@@ -1,3 +1,4 @@
+pub mod error;
 pub mod io;
 pub mod structures;
 pub mod utilities;
@@ -9,16 +10,59 @@ extern crate num_derive;
 
 #[cfg(test)]
 mod tests {
-    use crate::io::bin::{BinReader, BinWriter};
-    use crate::io::release_manifest::ReleaseManifest;
-    use crate::io::simple_skin::SimpleSkin;
-    use crate::io::static_object::StaticObject;
-    use crate::io::world_geometry::WorldGeometry;
+    use crate::error::Error;
+    use crate::io::bin::{BinContainer, BinMap, BinPathSegment, BinStructure, ColorFormat, BinReader, BinTree, BinValue, BinValueType, BinWriter};
+    use crate::io::binary_reader::BinaryReader;
+    use crate::io::detect::{AnyAsset, FileKind};
+    use crate::io::ply::PlyFormat;
+    use crate::io::binary_writer::BinaryWriter;
+    use crate::io::release_manifest::{ReleaseManifest, ReleaseManifestBundle, ReleaseManifestBundleChunk, ReleaseManifestFile};
+    use crate::io::simple_skin::{SimpleSkin, SimpleSkinLodChain, SimpleSkinSubmesh, SimpleSkinVertex};
+    use crate::io::static_object::{StaticObject, StaticObjectSubmesh, StaticObjectVertex};
+    use crate::io::world_geometry::{WorldGeometry, WorldGeometryModel, WorldGeometryVertex};
+    use std::collections::HashMap;
     use std::fs::File;
+    use glam::{Mat4, Vec3};
+    use palette::LinSrgba;
     use std::io;
-    use std::io::{Read, Write};
-    use std::path::Path;
-    use crate::io::simple_environment::SimpleEnvironment;
+    use std::io::{Cursor, ErrorKind, Read, Write};
+    use std::path::{Path, PathBuf};
+    use crate::io::simple_environment::{contains_ground_keyword, SimpleEnvironment, SimpleEnvironmentQuality, SimpleEnvironmentVertex};
+    use crate::structures::box3d::Box3D;
+    use crate::structures::render_bucket_grid::{RenderBucket, RenderBucketGrid};
+    use crate::structures::color::{linear_to_srgb, srgb_to_linear, LinSrgbaExt};
+    use crate::structures::matrix44::Mat4Ext;
+    use crate::structures::quaternion::Quaternion;
+    use crate::structures::sphere::Sphere;
+    use crate::structures::vector2::Vector2;
+    use crate::structures::vector3::Vector3;
+    use crate::structures::vector4::Vector4;
+    use crate::utilities::checksum::{crc32, xxhash64};
+    use crate::utilities::convert::{convert_dir, OutputKind};
+    use crate::utilities::hash_table::HashTable;
+    use crate::utilities::hashing::fnv1a_lc;
+    use crate::utilities::uv_convention::UvConvention;
+    use crate::utilities::version::Version;
+
+    /// A path in a temporary directory of this test process, so parallel runs don't share files
+    fn temp_path(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("rusty_league_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        directory.join(name)
+    }
+
+    /// skin0.bin with `fields` set on its first entry, under their own names
+    fn skin0_with_fields(fields: Vec<BinValue>) -> BinTree {
+        let mut bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin")).unwrap();
+        let path = bin.entries()[0].path();
+        let entry = bin.entry_mut(path).unwrap();
+        for field in fields {
+            entry.set_field(field.name(), field);
+        }
+
+        bin
+    }
 
     #[test]
     fn test_wgeo() {
@@ -31,6 +75,13 @@ mod tests {
         let mut models = world_geometry.models();
 
         assert_eq!(models.len(), 367);
+
+        for model in models {
+            let triangles = model.triangles();
+
+            assert!(triangles.is_ok());
+            assert_eq!(triangles.unwrap().count(), model.indices().len() / 3);
+        }
     }
 
     #[test]
@@ -87,6 +138,2224 @@ mod tests {
     #[test]
     fn test_nvr() -> io::Result<()> {
         let nvr = SimpleEnvironment::read_file(Path::new("test_files/room.nvr"))?;
+        assert!(!nvr.materials().is_empty());
+        assert_eq!(nvr.materials()[0].channels().len(), 8);
+
+        assert!(contains_ground_keyword("Maps/Room_floor.dds"));
+        assert!(!contains_ground_keyword("Maps/Room_wall.dds"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_weld() {
+        let vertex = |x: f32| {
+            SimpleSkinVertex::new_basic(
+                Vector3::new(x, 0.0, 0.0),
+                [0, 0, 0, 0],
+                [1.0, 0.0, 0.0, 0.0],
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector2::new(0.0, 0.0),
+            )
+        };
+
+        let mut submesh = SimpleSkinSubmesh::new(
+            String::from("test"),
+            vec![vertex(0.0), vertex(1.0), vertex(2.0), vertex(0.00001), vertex(2.0), vertex(3.0)],
+            vec![0, 1, 2, 3, 4, 5],
+        );
+
+        assert_eq!(submesh.weld(0.001), (6, 4));
+        assert_eq!(submesh.indices(), &[0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn test_simple_skin_normalize_weights() {
+        let mut vertex = SimpleSkinVertex::new_basic(
+            Vector3::zero(),
+            [3, 7, 0, 0],
+            [0.0, 0.5 * 2.0, 0.5 * 2.0, 0.0],
+            Vector3::zero(),
+            Vector2::zero(),
+        );
+
+        vertex.normalize_weights();
+
+        let weight_sum: f32 = vertex.weights.iter().sum();
+        assert!((weight_sum - 1.0).abs() < 0.0001);
+        assert_eq!(vertex.weights, [0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(vertex.influences, [7, 0, 3, 0]);
+    }
+
+    #[test]
+    fn test_box3d_merge() {
+        let a = Box3D::new(Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let b = Box3D::new(Vector3::new(0.0, -2.0, 0.0), Vector3::new(0.5, 0.5, 3.0));
+
+        let merged = a.merge(&b);
+        assert_eq!(merged.min, Vector3::new(-1.0, -2.0, 0.0));
+        assert_eq!(merged.max, Vector3::new(1.0, 1.0, 3.0));
+
+        let from_points = Box3D::from_points(vec![a.min, a.max, b.min, b.max]);
+        assert_eq!(from_points, merged);
+        assert_eq!(Box3D::from_points(Vec::new()), Box3D::ZERO);
+    }
+
+    #[test]
+    fn test_box3d_contains() {
+        let bounds = Box3D::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+
+        assert!(bounds.contains(Vector3::zero()));
+        assert!(bounds.contains(Vector3::new(1.0, -1.0, 1.0)));
+        assert!(!bounds.contains(Vector3::new(0.0, 1.5, 0.0)));
+        assert_eq!(bounds.center(), Vector3::zero());
+        assert_eq!(bounds.size(), Vector3::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn test_simple_skin_bounding_sphere() {
+        let positions = vec![
+            Vector3::new(10.0, 20.0, 30.0),
+            Vector3::new(14.0, 22.0, 31.0),
+            Vector3::new(12.0, 25.0, 35.0),
+        ];
+        let vertices = positions
+            .iter()
+            .map(|position| {
+                SimpleSkinVertex::new_basic(
+                    *position,
+                    [0, 0, 0, 0],
+                    [1.0, 0.0, 0.0, 0.0],
+                    Vector3::zero(),
+                    Vector2::zero(),
+                )
+            })
+            .collect();
+
+        let mut simple_skin = SimpleSkin::new(vec![SimpleSkinSubmesh::new(
+            String::from("test"),
+            vertices,
+            vec![0, 1, 2],
+        )]);
+
+        let bounding_sphere = simple_skin.bounding_sphere();
+        assert_eq!(simple_skin.central_point(), Vector3::new(12.0, 22.5, 32.5));
+        for position in &positions {
+            assert!(Vector3::distance(bounding_sphere.center, *position) <= bounding_sphere.radius);
+        }
+    }
+
+    #[test]
+    fn test_wgeo_model_bounding_sphere() {
+        let positions = vec![
+            Vector3::new(-5.0, 1.0, 100.0),
+            Vector3::new(-3.0, 4.0, 104.0),
+            Vector3::new(-1.0, 2.0, 102.0),
+        ];
+        let vertices = positions
+            .iter()
+            .map(|position| WorldGeometryVertex::new(*position, Vector2::zero()))
+            .collect();
+
+        let mut model =
+            WorldGeometryModel::new(String::new(), String::new(), vertices, vec![0, 1, 2]);
+
+        let bounding_sphere = model.bounding_sphere();
+        assert_eq!(model.central_point(), Vector3::new(-3.0, 2.5, 102.0));
+        for position in &positions {
+            assert!(Vector3::distance(bounding_sphere.center, *position) <= bounding_sphere.radius);
+        }
+    }
+
+    #[test]
+    fn test_color_u8_roundtrip() -> io::Result<()> {
+        let bytes: Vec<u8> = (0..=255u8).collect();
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes.clone()));
+        let colors = (0..64)
+            .map(|_| LinSrgba::read_rgba_u8(&mut reader))
+            .collect::<io::Result<Vec<LinSrgba>>>()?;
+
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        for color in &colors {
+            color.write_rgba_u8(&mut writer)?;
+        }
+
+        assert_eq!(writer.into_inner()?.into_inner(), bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_entries_iter() -> io::Result<()> {
+        let tree = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+
+        let mut reader = BinaryReader::from_file(File::open("test_files/skin0.bin")?);
+        let entries = BinReader::entries_iter(&mut reader)?.collect::<Result<Vec<_>, Error>>()?;
+        assert_eq!(entries.len(), tree.entries().len());
+        for (entry, tree_entry) in entries.iter().zip(tree.entries()) {
+            assert_eq!(entry.path(), tree_entry.path());
+            assert_eq!(entry.class(), tree_entry.class());
+        }
+
+        let last = tree.entries().last().unwrap();
+        let mut reader = BinaryReader::from_file(File::open("test_files/skin0.bin")?);
+        let found = BinReader::find_entry(&mut reader, last.path())?;
+        assert_eq!(found.map(|entry| entry.path()), Some(last.path()));
+
+        let mut reader = BinaryReader::from_file(File::open("test_files/skin0.bin")?);
+        assert!(BinReader::find_entry(&mut reader, 0)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_entry_lookup() -> io::Result<()> {
+        let bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+
+        for entry in bin.entries() {
+            let found = bin.entry(entry.path());
+
+            assert!(found.is_some());
+            assert_eq!(found.unwrap().path(), entry.path());
+        }
+
+        let class = bin.entries()[0].class();
+        assert!(bin.entries_of_class(class).all(|entry| entry.class() == class));
+        assert!(bin.entries_of_class(class).count() >= 1);
+
+        // Parsed trees can be shared across threads
+        fn assert_sync<T: Sync>(_: &T) {}
+        assert_sync(&bin);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_entry_set_field() -> io::Result<()> {
+        let mut bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        let path = bin.entries()[0].path();
+        let entry = bin.entry_mut(path).unwrap();
+        let value_count = entry.values().len();
+
+        entry.set_field(0x12345678, BinValue::UInt32 { name: 0x12345678, value: 1 });
+        entry.set_field(0x12345678, BinValue::UInt32 { name: 0x12345678, value: 2 });
+        assert_eq!(entry.values().len(), value_count + 1);
+        assert_eq!(
+            entry.field(0x12345678),
+            Some(&BinValue::UInt32 { name: 0x12345678, value: 2 })
+        );
+
+        assert!(entry.remove_field(0x12345678).is_some());
+        assert!(entry.remove_field(0x12345678).is_none());
+        assert_eq!(entry.values().len(), value_count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_pretty_print() -> io::Result<()> {
+        let bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        let entry = &bin.entries()[0];
+
+        let mut names = HashTable::new();
+        names.insert(entry.class() as u64, String::from("TestClass"));
+
+        let output = bin.pretty_print(&names);
+        assert!(output.contains(&format!("{:#010x}: TestClass {{", entry.path())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_patch_header() -> io::Result<()> {
+        let mut bytes = Vec::new();
+        File::open("test_files/skin0.bin")?.read_to_end(&mut bytes)?;
+
+        let mut patch_bytes = Vec::new();
+        patch_bytes.extend_from_slice(b"PTCH");
+        patch_bytes.extend_from_slice(&3u32.to_le_bytes());
+        patch_bytes.extend_from_slice(&7u32.to_le_bytes());
+        patch_bytes.extend_from_slice(&bytes);
+
+        let bin = BinReader::read_tree_buffer(Cursor::new(bytes))?;
+        let patch_bin = BinReader::read_tree_buffer(Cursor::new(patch_bytes.clone()))?;
+        assert!(!bin.is_patch());
+        assert!(patch_bin.is_patch());
+        assert_eq!(bin.patch_header(), None);
+        assert_eq!(patch_bin.patch_header(), Some(0x0000_0007_0000_0003));
+        assert_eq!(patch_bin.dependencies(), bin.dependencies());
+        assert_eq!(patch_bin.entries().len(), bin.entries().len());
+        for (patch_entry, entry) in patch_bin.entries().iter().zip(bin.entries()) {
+            assert_eq!(patch_entry.path(), entry.path());
+            assert_eq!(patch_entry.class(), entry.class());
+            assert_eq!(patch_entry.values(), entry.values());
+        }
+
+        let written_bytes = BinWriter::write_tree_to_vec(&patch_bin)?;
+        assert_eq!(&written_bytes[0..12], &patch_bytes[0..12]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bogus_counts() {
+        let bogus_count = 0xFFFFFFFFu32.to_le_bytes();
+
+        let mut simple_skin = Vec::new();
+        simple_skin.extend_from_slice(&0x00112233u32.to_le_bytes());
+        simple_skin.extend_from_slice(&4u16.to_le_bytes());
+        simple_skin.extend_from_slice(&1u16.to_le_bytes());
+        simple_skin.extend_from_slice(&bogus_count);
+        let error = SimpleSkin::read_from_buffer(Cursor::new(simple_skin))
+            .err()
+            .map(|error| error.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidData));
+
+        let mut world_geometry = Vec::new();
+        world_geometry.extend_from_slice(b"WGEO");
+        world_geometry.extend_from_slice(&5u32.to_le_bytes());
+        world_geometry.extend_from_slice(&bogus_count);
+        let error = WorldGeometry::read_from_buffer(Cursor::new(world_geometry))
+            .err()
+            .map(|error| error.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidData));
+
+        // Empty model list followed by a bucket grid with a bogus vertex count
+        let mut bucket_grid = Vec::new();
+        bucket_grid.extend_from_slice(b"WGEO");
+        bucket_grid.extend_from_slice(&5u32.to_le_bytes());
+        bucket_grid.extend_from_slice(&0u32.to_le_bytes());
+        bucket_grid.extend_from_slice(&0u32.to_le_bytes());
+        bucket_grid.extend_from_slice(&[0; 32]);
+        bucket_grid.extend_from_slice(&[0; 4]);
+        bucket_grid.extend_from_slice(&bogus_count);
+        let error = WorldGeometry::read_from_buffer(Cursor::new(bucket_grid))
+            .err()
+            .map(|error| error.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidData));
+
+        let mut static_object = Vec::new();
+        static_object.extend_from_slice(b"r3d2Mesh");
+        static_object.extend_from_slice(&3u16.to_le_bytes());
+        static_object.extend_from_slice(&2u16.to_le_bytes());
+        static_object.extend_from_slice(&[0; 128]);
+        static_object.extend_from_slice(&bogus_count);
+        let error = StaticObject::read_scb_from_buffer(Cursor::new(static_object))
+            .err()
+            .map(|error| error.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_triangle_soup() {
+        let vertex = |x: f32| WorldGeometryVertex::new(Vector3::new(x, 0.0, 0.0), Vector2::zero());
+        let vertices = vec![vertex(0.0), vertex(1.0), vertex(2.0), vertex(3.0)];
+
+        let indices = vec![0, 1, 2, 2, 1, 3];
+        let model = WorldGeometryModel::new(String::new(), String::new(), vertices.clone(), indices);
+        let soup = model.to_triangle_soup().unwrap();
+        assert_eq!(soup.len(), 6);
+        assert_eq!(soup[3].position, Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(soup[5].position, Vector3::new(3.0, 0.0, 0.0));
+
+        let model = WorldGeometryModel::new(String::new(), String::new(), vertices, vec![0, 1, 4]);
+        let error = model.to_triangle_soup().err().map(|error| error.kind());
+        assert_eq!(error, Some(ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_simple_skin_merge_submeshes() {
+        let vertex = |x: f32| {
+            SimpleSkinVertex::new_basic(
+                Vector3::new(x, 0.0, 0.0),
+                [0, 0, 0, 0],
+                [1.0, 0.0, 0.0, 0.0],
+                Vector3::zero(),
+                Vector2::zero(),
+            )
+        };
+        let submesh = |name: &str| {
+            SimpleSkinSubmesh::new(
+                String::from(name),
+                vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+                vec![0, 1, 2],
+            )
+        };
+
+        let mut simple_skin = SimpleSkin::new(vec![submesh("a"), submesh("b"), submesh("c")]);
+        let names = vec![String::from("c"), String::from("a")];
+        assert!(simple_skin.merge_submeshes(&names, String::from("merged")).is_ok());
+
+        let submeshes = simple_skin.submeshes();
+        assert_eq!(submeshes.len(), 2);
+        assert_eq!(submeshes[0].name, "merged");
+        assert_eq!(submeshes[1].name, "b");
+        assert_eq!(submeshes[0].vertices().len(), 6);
+        assert_eq!(submeshes[0].indices(), &[0, 1, 2, 3, 4, 5]);
+
+        let names = vec![String::from("missing")];
+        assert!(simple_skin.merge_submeshes(&names, String::new()).is_err());
+    }
+
+    #[test]
+    fn test_simple_skin_transform() {
+        let vertex = SimpleSkinVertex::new_basic(
+            Vector3::new(1.0, 2.0, 0.0),
+            [0, 0, 0, 0],
+            [1.0, 0.0, 0.0, 0.0],
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector2::new(0.25, 0.75),
+        );
+        let submesh = SimpleSkinSubmesh::new(String::from("test"), vec![vertex], vec![]);
+        let mut simple_skin = SimpleSkin::new(vec![submesh]);
+
+        simple_skin.transform(Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2));
+
+        let vertex = simple_skin.submeshes()[0].vertices()[0];
+        assert!(Vector3::distance(vertex.position, Vector3::new(0.0, 2.0, -1.0)) < 0.0001);
+        assert!(Vector3::distance(vertex.normal, Vector3::new(0.0, 0.0, -1.0)) < 0.0001);
+        assert_eq!(vertex.uv, Vector2::new(0.25, 0.75));
+        assert!(simple_skin.bounding_box().contains(vertex.position));
+    }
+
+    #[test]
+    fn test_checksum() -> io::Result<()> {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(crc32(b""), 0);
+
+        assert_eq!(xxhash64(b""), 0xEF46DB3751D8E999);
+        assert_eq!(xxhash64(b"abc"), 0x44BC2CF5AD770999);
+        assert_eq!(xxhash64(b"Nobody inspects the spammish repetition"), 0xFBCEA83C8A378BF1);
+
+        let mut reader = BinaryReader::from_file(File::open("test_files/skin0.bin")?);
+        let mut bytes = Vec::new();
+        File::open("test_files/skin0.bin")?.read_to_end(&mut bytes)?;
+        assert_eq!(xxhash64(&reader.read_all()?), xxhash64(&bytes));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_reader_borrowed_slice() -> io::Result<()> {
+        let bytes = [0x33, 0x22, 0x11, 0x00, 0xFF];
+        let mut reader = BinaryReader::new(Cursor::new(&bytes[..]));
+
+        assert_eq!(reader.read_u32()?, 0x00112233);
+        assert_eq!(reader.read_u8()?, 0xFF);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "memmap")]
+    fn test_release_manifest_mmap() -> io::Result<()> {
+        let path = Path::new("test_files/C944A5BD0686C600.manifest");
+
+        let buffered = ReleaseManifest::read(&mut BinaryReader::from_location(path)?)?;
+        let mapped = ReleaseManifest::read(&mut BinaryReader::from_mmap(path)?)?;
+        assert_eq!(buffered.release_id(), mapped.release_id());
+        assert_eq!(buffered.files().len(), mapped.files().len());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_release_manifest_parallel() -> io::Result<()> {
+        let path = Path::new("test_files/C944A5BD0686C600.manifest");
+
+        let sequential = ReleaseManifest::read_from_file(path)?;
+        let parallel = ReleaseManifest::read_from_file_parallel(path)?;
+        assert_eq!(parallel, sequential);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_progress() -> io::Result<()> {
+        let mut calls: Vec<(u64, u64)> = Vec::new();
+        let manifest = ReleaseManifest::read_with_progress(
+            &mut BinaryReader::from_file(File::open("test_files/C944A5BD0686C600.manifest")?),
+            &mut |done, total| calls.push((done, total)),
+        )?;
+        let record_count = manifest.bundles().len() + manifest.languages().len() + manifest.files().len() + manifest.directories().len();
+        assert_eq!(calls.len(), record_count);
+        assert!(calls.iter().enumerate().all(|(i, call)| *call == (i as u64 + 1, record_count as u64)));
+
+        let mut last = (0, 0);
+        let tree = BinReader::read_tree_with_progress(
+            &mut BinaryReader::from_file(File::open("test_files/skin0.bin")?),
+            &mut |done, total| last = (done, total),
+        )?;
+        assert_eq!(last, (tree.entries().len() as u64, tree.entries().len() as u64));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_bucket_grid_query() -> io::Result<()> {
+        let world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        let mut bucket_grid = world_geometry.bucket_grid().clone();
+        let bounds = bucket_grid.bounds();
+
+        assert!(bucket_grid.bucket_at(bounds.min.x - 1.0, bounds.min.z).is_none());
+        assert!(bucket_grid.bucket_at(bounds.max.x, bounds.max.z).is_some());
+
+        let center = bounds.center();
+        let bucket = *bucket_grid.bucket_at(center.x, center.z).unwrap();
+        let face_count = bucket.inside_face_count() as usize + bucket.sticking_out_face_count() as usize;
+        assert_eq!(bucket_grid.faces_in_bucket(&bucket).count(), face_count);
+
+        let mut total_face_count = 0;
+        for bucket in bucket_grid.buckets().iter().flatten() {
+            for face in bucket_grid.faces_in_bucket(bucket) {
+                assert!(face.iter().all(|index| (*index as usize) < bucket_grid.vertices().len()));
+                total_face_count += 1;
+            }
+        }
+        assert_eq!(total_face_count, bucket_grid.indices().len() / 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_bucket_grid_positive_bounds() -> io::Result<()> {
+        let mut bytes: Vec<u8> = Vec::new();
+        for value in &[100.0f32, 200.0, 300.0, 400.0, 0.0, 0.0, 100.0, 100.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&2u16.to_le_bytes()); // Buckets per side
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        for value in &[100.0f32, 0.0, 200.0, 300.0, 0.0, 200.0, 300.0, 0.0, 400.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in &[0u16, 1, 2] {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0; 4 * 20]);
+
+        let mut bucket_grid = RenderBucketGrid::read(&mut BinaryReader::from_buffer(Cursor::new(bytes.clone())))?;
+        assert_eq!(bucket_grid.bucket_size(), (100.0, 100.0));
+
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        bucket_grid.write(&mut writer)?;
+
+        let written_bytes = writer.into_inner()?.into_inner();
+        assert_eq!(written_bytes.len(), bytes.len());
+        assert_eq!(written_bytes[24..32], bytes[24..32]);
+
+        // A corrupt base vertex drops the faces it would overflow instead of panicking
+        let bucket = |base_vertex: u32| -> io::Result<RenderBucket> {
+            let mut bytes = vec![0; 12];
+            bytes.extend_from_slice(&base_vertex.to_le_bytes());
+            bytes.extend_from_slice(&1u16.to_le_bytes());
+            bytes.extend_from_slice(&0u16.to_le_bytes());
+            RenderBucket::read(&mut BinaryReader::from_buffer(Cursor::new(bytes)))
+        };
+        let faces: Vec<[u32; 3]> = bucket_grid.faces_in_bucket(&bucket(u32::MAX - 2)?).collect();
+        assert_eq!(faces, vec![[u32::MAX - 2, u32::MAX - 1, u32::MAX]]);
+        assert_eq!(bucket_grid.faces_in_bucket(&bucket(u32::MAX - 1)?).count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_variants() {
+        let error = SimpleSkin::read_from_buffer(Cursor::new(vec![0; 8])).unwrap_err();
+        assert!(matches!(error, Error::InvalidMagic { .. }));
+
+        let mut wgeo = b"WGEO".to_vec();
+        wgeo.extend_from_slice(&7u32.to_le_bytes());
+        match WorldGeometry::read_from_buffer(Cursor::new(wgeo)).err() {
+            Some(Error::UnsupportedVersion { major, minor }) => assert_eq!((major, minor), (7, 0)),
+            _ => panic!("expected an unsupported version error"),
+        }
+
+        // Single entry with one field of value type 127
+        let mut bin = b"PROP".to_vec();
+        bin.extend_from_slice(&1u32.to_le_bytes());
+        bin.extend_from_slice(&1u32.to_le_bytes());
+        bin.extend_from_slice(&0u32.to_le_bytes());
+        bin.extend_from_slice(&11u32.to_le_bytes());
+        bin.extend_from_slice(&0u32.to_le_bytes());
+        bin.extend_from_slice(&1u16.to_le_bytes());
+        bin.extend_from_slice(&0u32.to_le_bytes());
+        bin.push(127);
+        let error = BinReader::read_tree_buffer(Cursor::new(bin)).unwrap_err();
+        assert!(matches!(error, Error::InvalidValueType(127)));
+
+        let io_error: io::Error = error.into();
+        assert_eq!(io_error.kind(), ErrorKind::InvalidData);
+        assert_eq!(Error::from(io::Error::from(ErrorKind::NotFound)).kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let path = Path::new("test_files/does_not_exist.skn");
+
+        assert_eq!(BinaryReader::from_location(path).err().map(|error| error.kind()), Some(ErrorKind::NotFound));
+        assert_eq!(SimpleSkin::read_from_file(path).err().map(|error| error.kind()), Some(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn test_vector_ops() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(4.0, -2.0, 0.5);
+
+        assert_eq!(a + b, Vector3::new(5.0, 0.0, 3.5));
+        assert_eq!(a - b, Vector3::new(-3.0, 4.0, 2.5));
+        assert_eq!(a * 2.0, Vector3::new(2.0, 4.0, 6.0));
+        assert_eq!(-a, Vector3::new(-1.0, -2.0, -3.0));
+
+        let glam_vector: Vec3 = a.into();
+        assert_eq!(Vector3::from(glam_vector), a);
+        assert_eq!(Vector2::from(glam::Vec2::from(Vector2::new(1.0, 2.0))), Vector2::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn test_vector_lerp() {
+        let a = Vector3::new(0.0, 10.0, -4.0);
+        let b = Vector3::new(2.0, 20.0, 4.0);
+
+        assert_eq!(Vector3::lerp(a, b, 0.0), a);
+        assert_eq!(Vector3::lerp(a, b, 0.5), Vector3::new(1.0, 15.0, 0.0));
+        assert_eq!(Vector3::lerp(a, b, 1.0), b);
+        assert_eq!(Vector2::lerp(Vector2::zero(), Vector2::new(2.0, 4.0), 0.5), Vector2::new(1.0, 2.0));
+
+        assert_eq!(Vector3::min(a, b), Vector3::new(0.0, 10.0, -4.0));
+        assert_eq!(Vector3::max(a, Vector3::zero()), Vector3::new(0.0, 10.0, 0.0));
+        assert_eq!(Vector3::new(0.0, 3.0, 4.0).normalize(), Vector3::new(0.0, 0.6, 0.8));
+        assert_eq!(Vector3::zero().normalize(), Vector3::zero());
+        assert_eq!(a.scale(Vector3::new(1.0, 0.5, -1.0)), Vector3::new(0.0, 5.0, 4.0));
+    }
+
+    #[test]
+    fn test_simple_skin_validate() {
+        let vertex = |weights: [f32; 4]| {
+            SimpleSkinVertex::new_basic(Vector3::zero(), [0, 1, 0, 0], weights, Vector3::zero(), Vector2::zero())
+        };
+
+        let valid = SimpleSkin::new(vec![SimpleSkinSubmesh::new(
+            String::from("valid"),
+            vec![vertex([1.0, 0.0, 0.0, 0.0]), vertex([0.5, 0.5, 0.0, 0.0]), vertex([0.0; 4])],
+            vec![0, 1, 2],
+        )]);
+        assert_eq!(valid.validate(), Ok(()));
+
+        let invalid = SimpleSkin::new(vec![SimpleSkinSubmesh::new(
+            String::from("invalid"),
+            vec![vertex([0.5, 0.0, 0.0, 0.0]), vertex([1.0, 0.0, 0.0, 0.0])],
+            vec![0, 1, 2, 1],
+        )]);
+        let errors = invalid.validate().unwrap_err();
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_bin_validate() -> io::Result<()> {
+        let mut bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        assert_eq!(bin.validate(), Ok(()));
+
+        let path = bin.entries()[0].path();
+        let entry = bin.entry_mut(path).unwrap();
+        entry.set_field(0x1234, BinValue::Container {
+            name: 0x1234,
+            value: BinContainer::new(
+                BinValueType::UInt32,
+                vec![BinValue::UInt32 { name: 0, value: 1 }, BinValue::Float { name: 0, value: 1.0 }],
+            ),
+        });
+
+        let mut map = HashMap::new();
+        map.insert(BinValue::Hash { name: 0, value: 1 }, BinValue::String { name: 0, value: String::new() });
+        entry.set_field(0x5678, BinValue::Map {
+            name: 0x5678,
+            value: BinMap::new(BinValueType::Hash, BinValueType::Link, map),
+        });
+
+        let errors = bin.validate().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains(&format!("{:#010x}", path)));
+        assert!(errors[0].contains("0x00001234"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wgeo_version_4_roundtrip() -> io::Result<()> {
+        let mut world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        assert_eq!(world_geometry.version(), Version::new(5, 0));
+
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        world_geometry.write_version(&mut writer, 4)?;
+
+        let mut world_geometry = WorldGeometry::read_from_buffer(Cursor::new(writer.into_inner()?.into_inner()))?;
+        assert_eq!(world_geometry.version(), Version::new(4, 0));
+        assert_eq!(world_geometry.bucket_grid().buckets_per_side(), 0);
+
+        let rewritten = world_geometry.write_to_vec()?;
+
+        // Header followed by the models and nothing else
+        let model_size: u64 = world_geometry
+            .models()
+            .iter()
+            .map(|model| {
+                let index_size = if model.indices().len() <= 65536 { 2 } else { 4 };
+                372 + 20 * model.vertices().len() as u64 + index_size * model.indices().len() as u64
+            })
+            .sum();
+        assert_eq!(rewritten.len() as u64, 16 + model_size);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_dependencies() -> io::Result<()> {
+        let mut bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        bin.dependencies_mut().clear();
+
+        let referenced = String::from("DATA/Characters/Test/Test.bin");
+        assert_eq!(bin.add_dependency(referenced.clone()), 0);
+        assert_eq!(bin.add_dependency(String::from("data/characters/test/test.bin")), 0);
+        assert_eq!(bin.add_dependency(String::from("data/unused.bin")), 1);
+        assert_eq!(bin.dependencies().len(), 2);
+
+        let path = bin.entries()[0].path();
+        bin.entry_mut(path).unwrap().set_field(0x1234, BinValue::String { name: 0x1234, value: referenced.clone() });
+
+        bin.prune_unused_dependencies();
+        assert_eq!(bin.dependencies(), &vec![referenced]);
+        assert_eq!(fnv1a_lc("A"), 0xe40c292c);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_static_object_to_simple_skin() -> io::Result<()> {
+        let mut static_object =
+            StaticObject::read_scb_from_file(Path::new("test_files/aatrox_base_w_ground_ring.scb"))?;
+
+        let mut simple_skin = static_object.to_simple_skin()?;
+        assert_eq!(simple_skin.validate(), Ok(()));
+        assert_eq!(simple_skin.submeshes().len(), static_object.submeshes().len());
+
+        for (skin_submesh, submesh) in simple_skin.submeshes().iter_mut().zip(static_object.submeshes()) {
+            assert_eq!(skin_submesh.name, submesh.name);
+            assert_eq!(skin_submesh.vertices().len(), submesh.vertices().len());
+            assert_eq!(skin_submesh.indices().len(), submesh.indices().len());
+            assert!(skin_submesh.triangles().is_ok());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_to_static_object() -> io::Result<()> {
+        let mut simple_skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+
+        let mut static_object = simple_skin.to_static_object(String::from("aatrox"));
+        assert_eq!(static_object.name, "aatrox");
+        assert_eq!(static_object.submeshes().len(), simple_skin.submeshes().len());
+
+        for (submesh, skin_submesh) in static_object.submeshes().iter_mut().zip(simple_skin.submeshes()) {
+            assert_eq!(submesh.name, skin_submesh.name);
+            assert_eq!(submesh.vertices().len(), skin_submesh.vertices().len());
+            assert_eq!(submesh.indices().len(), skin_submesh.indices().len());
+            assert!(submesh.triangles().is_ok());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wgeo_material_stats() -> io::Result<()> {
+        let world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        let models = world_geometry.models();
+
+        let material_stats = world_geometry.material_stats();
+        assert_eq!(material_stats.values().map(|indices| indices.len()).sum::<usize>(), models.len());
+        for (material, indices) in &material_stats {
+            assert!(indices.iter().all(|index| &models[*index].material == material));
+        }
+
+        let unique_textures = world_geometry.unique_textures();
+        assert!(!unique_textures.is_empty() && unique_textures.len() <= models.len());
+        assert!(models.iter().all(|model| unique_textures.contains(&model.texture)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_table() -> io::Result<()> {
+        let mut hash_table = HashTable::read(Cursor::new("0000abcd Skin\n\n0x12345678: mSkinMeshProperties\n"))?;
+        assert_eq!(hash_table.get(0xabcd), Some("Skin"));
+        assert_eq!(hash_table.get(0x12345678), Some("mSkinMeshProperties"));
+
+        let game = HashTable::read(Cursor::new("0123456789abcdef assets/characters/test.dds"))?;
+        hash_table.merge(game);
+        assert_eq!(hash_table.len(), 3);
+        assert_eq!(hash_table.get(0x0123456789abcdef), Some("assets/characters/test.dds"));
+        assert_eq!(BinTree::resolve_name(&hash_table, 0xabcd), "Skin");
+        assert_eq!(BinTree::resolve_name(&hash_table, 0xabce), "0x0000abce");
+
+        let error = HashTable::read(Cursor::new("nothex name")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_color_f32() -> io::Result<()> {
+        // There's no known type id for the 16-byte form so it can't be written
+        let color = LinSrgba::new(0.25, 0.5, 0.75, 1.0);
+        let bin = skin0_with_fields(vec![BinValue::Color { name: 0x1234, value: color, format: ColorFormat::F32 }]);
+        let error = BinWriter::write_tree_to_vec(&bin).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let original_length = std::fs::metadata("test_files/skin0.bin")?.len();
+        let bin = skin0_with_fields(vec![BinValue::Color { name: 0x1234, value: color, format: ColorFormat::U8 }]);
+        let buffer = BinWriter::write_tree_to_vec(&bin)?;
+        assert_eq!(buffer.len() as u64, original_length + 4 + 1 + 4);
+
+        let path = bin.entries()[0].path();
+        let read = BinReader::read_tree_buffer(Cursor::new(buffer))?;
+        let field = read.entry(path).unwrap().values().iter().find(|value| value.name() == 0x1234).unwrap();
+        assert!(matches!(field, BinValue::Color { format: ColorFormat::U8, .. }));
+        assert_eq!(field.value_type(), BinValueType::Color);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_content_size_mismatch() -> io::Result<()> {
+        let bin = skin0_with_fields(vec![BinValue::Container {
+            name: 0x1234,
+            value: BinContainer::new(BinValueType::UInt32, vec![BinValue::UInt32 { name: 0, value: 0xDEADBEEF }]),
+        }]);
+
+        let mut buffer = BinWriter::write_tree_to_vec(&bin)?;
+        assert!(BinReader::read_tree_buffer(Cursor::new(buffer.clone())).is_ok());
+
+        // The container size sits right before its count, which precedes the value
+        let value_offset = buffer.windows(4).position(|bytes| bytes == 0xDEADBEEFu32.to_le_bytes()).unwrap();
+        buffer[value_offset - 8] += 1;
+
+        let error = BinReader::read_tree_buffer(Cursor::new(buffer)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_to_vec() -> io::Result<()> {
+        let bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        let bytes = BinWriter::write_tree_to_vec(&bin)?;
+        assert_eq!(bytes.len() as u64, std::fs::metadata("test_files/skin0.bin")?.len());
+        assert_eq!(BinReader::read_tree_buffer(Cursor::new(bytes))?.entries().len(), bin.entries().len());
+
+        let mut simple_skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        let bytes = simple_skin.write_to_vec()?;
+        let mut reread = SimpleSkin::read_from_buffer(Cursor::new(bytes))?;
+        assert_eq!(reread.submeshes().len(), simple_skin.submeshes().len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_writer_into_inner() -> io::Result<()> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        writer.write_u32(0x11223344)?;
+        writer.write_u8(0xFF)?;
+        writer.flush()?;
+
+        let buffer = writer.into_inner()?;
+        assert_eq!(buffer.into_inner(), vec![0x44, 0x33, 0x22, 0x11, 0xFF]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nvr_versions() {
+        let header = |major: u16, minor: u16| {
+            let mut buffer = b"NVR\0".to_vec();
+            buffer.extend_from_slice(&major.to_le_bytes());
+            buffer.extend_from_slice(&minor.to_le_bytes());
+            buffer.extend_from_slice(&[0u8; 20]);
+            Cursor::new(buffer)
+        };
+
+        assert!(SimpleEnvironment::read_buffer(header(8, 1)).is_ok());
+        assert!(SimpleEnvironment::read_buffer(header(9, 1)).is_ok());
+        match SimpleEnvironment::read_buffer(header(7, 1)) {
+            Err(Error::UnsupportedVersion { major: 7, minor: 1 }) => {}
+            result => panic!("Expected an unsupported version error, got {:?}", result),
+        }
+        assert!(SimpleEnvironment::read_buffer(header(9, 2)).is_err());
+    }
+
+    #[test]
+    fn test_wgeo_merged_mesh() -> io::Result<()> {
+        let world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        let models = world_geometry.models();
+
+        let vertex_count: usize = models.iter().map(|model| model.vertices().len()).sum();
+        let index_count: usize = models.iter().map(|model| model.indices().len()).sum();
+        assert_eq!(world_geometry.total_vertex_count(), vertex_count);
+        assert_eq!(world_geometry.total_face_count(), index_count / 3);
+
+        let (vertices, indices) = world_geometry.merged_mesh();
+        assert_eq!(vertices.len(), vertex_count);
+        assert_eq!(indices.len(), index_count);
+        assert!(indices.iter().all(|index| (*index as usize) < vertices.len()));
+
+        let last = models.last().unwrap();
+        assert_eq!(indices[index_count - 1] as usize, vertex_count - last.vertices().len() + *last.indices().last().unwrap() as usize);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_fixed_arrays() -> io::Result<()> {
+        let mut bytes = vec![1u8, 2, 3];
+        for i in 0..20 {
+            bytes.extend_from_slice(&(i as f32).to_le_bytes());
+        }
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+
+        assert_eq!(reader.read_u8_array::<3>()?, [1, 2, 3]);
+        let values = reader.read_f32_array::<20>()?;
+        assert!(values.iter().enumerate().all(|(i, value)| *value == i as f32));
+        assert!(reader.read_f32_array::<1>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_f32_slice() -> io::Result<()> {
+        let values: Vec<f32> = (0..20).map(|i| i as f32 * 0.5).collect();
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        assert_eq!(writer.write_f32_slice(&values)?, 80);
+
+        let mut reader = BinaryReader::from_buffer(Cursor::new(writer.into_inner()?.into_inner()));
+        assert_eq!(reader.read_f32_array::<20>()?.to_vec(), values);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mat4_decompose() {
+        let half_sqrt = std::f32::consts::FRAC_1_SQRT_2;
+        let translation = Vector3::new(1.0, 2.0, 3.0);
+        let rotation = Quaternion::new(0.0, half_sqrt, 0.0, half_sqrt);
+        let scale = Vector3::new(2.0, 3.0, 4.0);
+
+        let matrix = Mat4::from_trs(translation, rotation, scale);
+        let point: Vector3 = matrix.transform_point3(Vector3::new(1.0, 0.0, 0.0).into()).into();
+        assert!(Vector3::distance(point, Vector3::new(1.0, 2.0, 1.0)) < 0.0001);
+
+        let (decomposed_translation, decomposed_rotation, decomposed_scale) = matrix.decompose();
+        assert!(Vector3::distance(decomposed_translation, translation) < 0.0001);
+        assert!(Vector3::distance(decomposed_scale, scale) < 0.0001);
+        assert!((decomposed_rotation.y - half_sqrt).abs() < 0.0001);
+        assert!((decomposed_rotation.w - half_sqrt).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_detect_file_kind() -> io::Result<()> {
+        let files = [
+            ("test_files/aatrox.skn", FileKind::SimpleSkin),
+            ("test_files/aatrox_base_w_ground_ring.scb", FileKind::StaticObject),
+            ("test_files/room_map11.wgeo", FileKind::WorldGeometry),
+            ("test_files/room.nvr", FileKind::SimpleEnvironment),
+            ("test_files/skin0.bin", FileKind::Bin),
+            ("test_files/C944A5BD0686C600.manifest", FileKind::ReleaseManifest),
+        ];
+        for (path, kind) in files.iter() {
+            assert_eq!(FileKind::detect_file(Path::new(path))?, Some(*kind));
+        }
+
+        assert_eq!(FileKind::detect(b"RW\x03\x01"), Some(FileKind::Wad));
+        assert_eq!(FileKind::detect(b"r3d2anmd"), Some(FileKind::Animation));
+        assert_eq!(FileKind::detect(b"????"), None);
+
+        match FileKind::read_from_file(Path::new("test_files/room.nvr"))? {
+            AnyAsset::SimpleEnvironment(nvr) => assert!(!nvr.materials().is_empty()),
+            _ => panic!("Expected a SimpleEnvironment"),
+        }
+
+        let wad = temp_path("detect.wad");
+        std::fs::write(&wad, b"RW\x03\x01")?;
+        let error = FileKind::read_from_file(&wad).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wgeo_uv_atlas() {
+        let vertex = |u: f32, v: f32| WorldGeometryVertex::new(Vector3::zero(), Vector2::new(u, v));
+        let model = WorldGeometryModel::new(
+            String::from("texture.dds"),
+            String::from("material"),
+            vec![vertex(0.25, 0.5), vertex(0.75, 0.0), vertex(0.5, 1.0)],
+            vec![0, 1, 2],
+        );
+        assert_eq!(model.uv_bounds(), (Vector2::new(0.25, 0.0), Vector2::new(0.75, 1.0)));
+
+        let mut world_geometry = WorldGeometry::new(vec![model], RenderBucketGrid::empty());
+        world_geometry.remap_uvs(|uv| Vector2::new(0.5 + uv.x * 0.5, uv.y * 0.5));
+        assert_eq!(world_geometry.models()[0].uv_bounds(), (Vector2::new(0.625, 0.0), Vector2::new(0.875, 0.5)));
+    }
+
+    #[test]
+    fn test_wgeo_vertices_mut() -> io::Result<()> {
+        let world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        let mut model = world_geometry.models()[0].clone();
+        let bounding_box = model.bounding_box();
+        let bounding_sphere = Sphere::from_points(model.vertices().iter().map(|vertex| vertex.position));
+
+        for vertex in model.vertices_mut() {
+            vertex.position = vertex.position * 2.0;
+        }
+
+        let scaled_box = model.bounding_box();
+        assert_ne!(scaled_box, bounding_box);
+        assert!(Vector3::distance(scaled_box.max, bounding_box.max * 2.0) < 0.001);
+        let scaled_sphere = model.bounding_sphere();
+        assert!(model.vertices().iter().all(|vertex| scaled_sphere.contains(vertex.position)));
+        assert!((scaled_sphere.radius - bounding_sphere.radius * 2.0).abs() < bounding_sphere.radius * 0.01);
+
+        model.indices_mut().truncate(3);
+        assert_eq!(model.indices().len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uv_convention() {
+        let uv = Vector2::new(0.25, 0.75);
+        assert_eq!(UvConvention::TopLeft.convert(uv), uv);
+        assert_eq!(UvConvention::BottomLeft.convert(uv), Vector2::new(0.25, 0.25));
+
+        let vertex = SimpleSkinVertex::new_basic(Vector3::zero(), [0; 4], [1.0, 0.0, 0.0, 0.0], Vector3::zero(), uv);
+        assert_eq!(vertex.flipped_uv(), Vector2::new(0.25, 0.25));
+    }
+
+    #[test]
+    fn test_release_manifest_chunk_verify() -> io::Result<()> {
+        let data = b"compressed chunk data";
+        let chunk = ReleaseManifestBundleChunk::new(xxhash64(data), data.len() as u32, 64);
+        assert!(chunk.verify(data));
+        assert!(!chunk.verify(b"compressed chunk dat4"));
+        assert!(!chunk.verify(&data[1..]));
+
+        let release_manifest = ReleaseManifest::read_from_file(Path::new("test_files/C944A5BD0686C600.manifest"))?;
+        let bundle = release_manifest.bundles().last().unwrap();
+        let last_chunk = bundle.chunks().last().unwrap();
+        assert_eq!(release_manifest.chunk_by_id(last_chunk.id()), Some(last_chunk));
+        assert_eq!(release_manifest.chunk_by_id(0), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_manifest_assemble_file() -> io::Result<()> {
+        let parts: Vec<&[u8]> = vec![b"first chunk, ", b"second chunk"];
+        let compressed: HashMap<u64, Vec<u8>> = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| Ok((i as u64 + 1, zstd::encode_all(*part, 3)?)))
+            .collect::<io::Result<_>>()?;
+
+        let chunks = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| ReleaseManifestBundleChunk::new(i as u64 + 1, compressed[&(i as u64 + 1)].len() as u32, part.len() as u32))
+            .collect();
+        let file = ReleaseManifestFile::new(String::from("file.txt"), String::new(), 1, 0, 25, Vec::new(), vec![1, 2]);
+        let release_manifest = ReleaseManifest::new(0, vec![ReleaseManifestBundle::new(0, chunks)], Vec::new(), Vec::new(), Vec::new());
+
+        let data = release_manifest.assemble_file(&file, |id| Ok(compressed[&id].clone()))?;
+        assert_eq!(data, b"first chunk, second chunk".to_vec());
+
+        let error = release_manifest.assemble_file(&file, |_| Ok(vec![0; 4])).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_manifest_bundle_offsets() -> io::Result<()> {
+        let bundle = ReleaseManifestBundle::new(
+            0x1A2B,
+            vec![ReleaseManifestBundleChunk::new(1, 100, 200), ReleaseManifestBundleChunk::new(2, 50, 60)],
+        );
+        assert_eq!(bundle.download_path(), "0000000000001A2B.bundle");
+        assert_eq!(bundle.chunk_offset(2), Some(100));
+        assert_eq!(bundle.chunk_offset(3), None);
+        assert_eq!(bundle.chunks()[1].byte_range(), 100..150);
+
+        let release_manifest = ReleaseManifest::read_from_file(Path::new("test_files/C944A5BD0686C600.manifest"))?;
+        for bundle in release_manifest.bundles() {
+            let mut expected_offset = 0;
+            for chunk in bundle.chunks() {
+                assert_eq!(chunk.offset(), expected_offset);
+                expected_offset += chunk.compressed_size() as u64;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_typed_values() -> io::Result<()> {
+        let bin = skin0_with_fields(vec![
+            BinValue::Float { name: 0x1, value: 2.5 },
+            BinValue::String { name: 0x2, value: String::from("Characters/Aatrox") },
+            BinValue::Container2 {
+                name: 0x3,
+                value: BinContainer::new(BinValueType::Hash, vec![BinValue::Hash { name: 0, value: 0xABCD }]),
+            },
+        ]);
+        let entry = &bin.entries()[0];
+
+        assert_eq!(entry.get_f32(0x1), Some(2.5));
+        assert_eq!(entry.get_u32(0x1), None);
+        assert_eq!(entry.get_string(0x2), Some("Characters/Aatrox"));
+        assert_eq!(entry.get_f32(0x4), None);
+
+        let container = entry.get_container(0x3).unwrap();
+        assert_eq!(container.values()[0].as_hash(), Some(0xABCD));
+        assert_eq!(container.values()[0].as_link(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_navigate() -> io::Result<()> {
+        let mut names = HashMap::new();
+        names.insert(BinValue::Hash { name: 0, value: 0x99 }, BinValue::String { name: 0, value: String::from("found") });
+        let map = BinValue::Map { name: 0x30, value: BinMap::new(BinValueType::Hash, BinValueType::String, names) };
+        let optional = BinValue::Optional { name: 0x20, value_type: BinValueType::Map, value: Some(Box::new(map)) };
+        let structure = BinValue::Embedded { name: 0, value: BinStructure::new(0x1234, vec![optional]) };
+
+        let bin = skin0_with_fields(vec![BinValue::Container {
+            name: 0x10,
+            value: BinContainer::new(BinValueType::Embedded, vec![structure]),
+        }]);
+        let entry = &bin.entries()[0];
+
+        let found = entry.navigate(&[
+            BinPathSegment::Field(0x10),
+            BinPathSegment::Index(0),
+            BinPathSegment::Field(0x20),
+            BinPathSegment::Key(BinValue::Hash { name: 0, value: 0x99 }),
+        ]);
+        assert_eq!(found.and_then(|value| value.as_string()), Some("found"));
+
+        assert!(entry.navigate(&[BinPathSegment::Field(0x10), BinPathSegment::Index(1)]).is_none());
+        assert!(entry.navigate(&[BinPathSegment::Field(0x10), BinPathSegment::Field(0x20)]).is_none());
+        assert!(entry.navigate(&[BinPathSegment::Index(0)]).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_u24_and_packed_colors() -> io::Result<()> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        writer.write_u24(0x123456)?;
+        writer.write_u24_be(0x123456)?;
+        writer.write_u16(0xF800)?; // Pure red in 565
+        writer.write_u16(0x07E0)?; // Pure green in 565
+        writer.write_u16(0x801F)?; // Opaque blue in 5551
+        writer.write_u16(0x7C00)?; // Transparent red in 5551
+        let bytes = writer.into_inner()?.into_inner();
+        assert_eq!(&bytes[..6], &[0x56, 0x34, 0x12, 0x12, 0x34, 0x56]);
+
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+        assert_eq!(reader.read_u24()?, 0x123456);
+        assert_eq!(reader.read_u24_be()?, 0x123456);
+        assert_eq!(LinSrgba::read_packed_rgb565(&mut reader)?, LinSrgba::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(LinSrgba::read_packed_rgb565(&mut reader)?, LinSrgba::new(0.0, 1.0, 0.0, 1.0));
+        assert_eq!(LinSrgba::read_packed_rgba5551(&mut reader)?, LinSrgba::new(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(LinSrgba::read_packed_rgba5551(&mut reader)?, LinSrgba::new(1.0, 0.0, 0.0, 0.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_reuses_string_buffer() -> io::Result<()> {
+        let mut bytes = b"Long name\0\0\0short\0\0\0\0\0\0\0".to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5]);
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+
+        assert_eq!(reader.read_padded_string(12)?, "Long name");
+        assert_eq!(reader.read_padded_string(12)?, "short");
+
+        let mut buffer = Vec::with_capacity(16);
+        reader.read_bytes_into(&mut buffer, 3)?;
+        assert_eq!(buffer, [1, 2, 3]);
+        reader.read_bytes_into(&mut buffer, 2)?;
+        assert_eq!(buffer, [4, 5]);
+        assert!(buffer.capacity() >= 16);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sized_string_u16() -> io::Result<()> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        writer.write_sized_string_u16("Characters/Aatrox")?;
+        assert_eq!(
+            writer.write_sized_string_u16(&"a".repeat(u16::MAX as usize + 1)).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        let bytes = writer.into_inner()?.into_inner();
+        assert_eq!(&bytes[..2], &[17, 0]);
+
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+        assert_eq!(reader.read_sized_string_u16()?, "Characters/Aatrox");
+        assert_eq!(reader.position()?, 19);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_string_lossy() -> io::Result<()> {
+        let mut bytes = b"caf\xE9.dds\0\0\0".to_vec();
+        bytes.extend_from_slice(b"caf\xE9.dds\0\0\0");
+        bytes.extend_from_slice(b"caf\xE9");
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+
+        assert!(reader.strict_utf8());
+        assert_eq!(reader.read_padded_string(11).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        reader.set_strict_utf8(false);
+        assert_eq!(reader.read_padded_string(11)?, "caf\u{FFFD}.dds");
+        assert_eq!(reader.read_string_lossy(4)?, "caf\u{FFFD}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_srgb_colors() -> io::Result<()> {
+        let mut reader = BinaryReader::from_buffer(Cursor::new(vec![188, 255, 0, 188]));
+        let color = LinSrgba::read_srgba_u8(&mut reader)?;
+        assert!((color.color.red - 0.5).abs() < 0.005);
+        assert_eq!(color.color.green, 1.0);
+        assert_eq!(color.color.blue, 0.0);
+        assert_eq!(color.alpha, 188.0 / 255.0);
+
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 0.001);
+        assert!((linear_to_srgb(0.214) - 0.5).abs() < 0.001);
+
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        color.write_srgba_u8(&mut writer)?;
+        assert_eq!(writer.into_inner()?.into_inner(), vec![188, 255, 0, 188]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sphere_from_points() -> io::Result<()> {
+        let mut simple_skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        let positions: Vec<Vector3> = simple_skin
+            .submeshes()
+            .iter_mut()
+            .flat_map(|submesh| submesh.vertices().iter().map(|vertex| vertex.position).collect::<Vec<_>>())
+            .collect();
+        let bounding_box = Box3D::from_points(positions.iter().copied());
+        let bounding_sphere = Sphere::from_points(positions.iter().copied());
+
+        assert!(positions.iter().all(|position| bounding_sphere.contains(*position)));
+        assert!(bounding_sphere.radius <= Vector3::distance(bounding_box.center(), bounding_box.max));
+
+        let merged = Sphere::new(Vector3::new(-2.0, 0.0, 0.0), 1.0).merge(&Sphere::new(Vector3::new(2.0, 0.0, 0.0), 1.0));
+        assert_eq!(merged, Sphere::new(Vector3::zero(), 3.0));
+        assert_eq!(merged.merge(&Sphere::new(Vector3::new(1.0, 0.0, 0.0), 1.0)), merged);
+        assert_eq!(Sphere::from_points(Vec::new()), Sphere::ZERO);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_manifest_signature() -> crate::error::Result<()> {
+        let manifest = ReleaseManifest::read_from_file(Path::new("test_files/C944A5BD0686C600.manifest"))?;
+        let file = std::fs::read("test_files/C944A5BD0686C600.manifest")?;
+        assert_eq!(manifest.signature(), &file[file.len() - 256..]);
+
+        let mut bytes = 0x0123456789ABCDEF_u64.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&0xFEDCBA9876543210_u64.to_le_bytes());
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+        assert_eq!(reader.read_u128()?, 0xFEDCBA9876543210_0123456789ABCDEF);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_dir() -> crate::error::Result<()> {
+        let src = temp_path("convert_src");
+        let dst = temp_path("convert_dst");
+        let _ = std::fs::remove_dir_all(&src);
+        let _ = std::fs::remove_dir_all(&dst);
+        std::fs::create_dir_all(src.join("nested"))?;
+        std::fs::copy("test_files/aatrox_base_w_ground_ring.scb", src.join("nested/ring.scb"))?;
+        std::fs::write(src.join("broken.scb"), b"r3d2Mesh\x02\x00")?;
+        std::fs::write(src.join("notes.txt"), b"not a mesh")?;
+
+        let report = convert_dir(&src, &dst, FileKind::StaticObject, OutputKind::SimpleSkin)?;
+        assert_eq!(report.converted, vec![src.join("nested/ring.scb")]);
+        assert_eq!(report.skipped, vec![src.join("notes.txt")]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, src.join("broken.scb"));
+        assert!(SimpleSkin::read_from_file(&dst.join("nested/ring.skn")).is_ok());
+
+        assert!(convert_dir(&src, &dst, FileKind::StaticObject, OutputKind::Bin).is_err());
+
+        // Converting again into the same destination mustn't leave the tail of the longer previous output
+        std::fs::remove_dir_all(&src)?;
+        std::fs::create_dir_all(&src)?;
+        std::fs::copy("test_files/aatrox.skn", src.join("mesh.skn"))?;
+        convert_dir(&src, &dst, FileKind::SimpleSkin, OutputKind::SimpleSkin)?;
+        let shorter = SimpleSkin::read_from_file(&dst.join("nested/ring.skn"))?.write_to_vec()?;
+        std::fs::write(src.join("mesh.skn"), &shorter)?;
+        let report = convert_dir(&src, &dst, FileKind::SimpleSkin, OutputKind::SimpleSkin)?;
+        assert_eq!(report.converted, vec![src.join("mesh.skn")]);
+        assert_eq!(std::fs::read(dst.join("mesh.skn"))?, shorter);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&src, src.join("loop"))?;
+            let report = convert_dir(&src, &dst, FileKind::SimpleSkin, OutputKind::SimpleSkin)?;
+            assert_eq!(report.converted, vec![src.join("mesh.skn")]);
+            assert!(report.skipped.is_empty() && report.failed.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_value_sizes() -> io::Result<()> {
+        let color = LinSrgba::new(1.0, 0.5, 0.0, 1.0);
+        let mut map = HashMap::new();
+        map.insert(
+            BinValue::String { name: 0, value: String::from("key") },
+            BinValue::Color { name: 0, value: color, format: ColorFormat::U8 },
+        );
+        let structure = || BinStructure::new(0x10, vec![
+            BinValue::Vector3 { name: 0x11, value: Vector3::new(1.0, 2.0, 3.0) },
+            BinValue::String { name: 0x12, value: String::from("Characters/Aatrox") },
+        ]);
+
+        let values = vec![
+            BinValue::None { name: 1 },
+            BinValue::Boolean { name: 2, value: true },
+            BinValue::SByte { name: 3, value: -1 },
+            BinValue::Byte { name: 4, value: 1 },
+            BinValue::Int16 { name: 5, value: -1 },
+            BinValue::UInt16 { name: 6, value: 1 },
+            BinValue::Int32 { name: 7, value: -1 },
+            BinValue::UInt32 { name: 8, value: 1 },
+            BinValue::Int64 { name: 9, value: -1 },
+            BinValue::UInt64 { name: 10, value: 1 },
+            BinValue::Float { name: 11, value: 1.0 },
+            BinValue::Vector2 { name: 12, value: Vector2::new(1.0, 2.0) },
+            BinValue::Vector3 { name: 13, value: Vector3::new(1.0, 2.0, 3.0) },
+            BinValue::Vector4 { name: 14, value: Vector4::new(1.0, 2.0, 3.0, 4.0) },
+            BinValue::Matrix44 { name: 15, value: [[1.0; 4]; 4] },
+            BinValue::Color { name: 16, value: color, format: ColorFormat::U8 },
+            BinValue::String { name: 18, value: String::from("Ašatrox") },
+            BinValue::Hash { name: 19, value: 0xABCD },
+            BinValue::Container { name: 20, value: BinContainer::new(BinValueType::Float, (0..3).map(|_| BinValue::Float { name: 0, value: 1.0 }).collect()) },
+            BinValue::Container2 { name: 21, value: BinContainer::new(BinValueType::String, vec![BinValue::String { name: 0, value: String::from("a") }]) },
+            BinValue::Structure { name: 22, value: structure() },
+            BinValue::Structure { name: 23, value: BinStructure::new(0, Vec::new()) },
+            BinValue::Embedded { name: 24, value: structure() },
+            BinValue::Link { name: 25, value: 0xABCD },
+            BinValue::Optional { name: 26, value_type: BinValueType::String, value: Some(Box::new(BinValue::String { name: 0, value: String::from("some") })) },
+            BinValue::Optional { name: 27, value_type: BinValueType::Int32, value: None },
+            BinValue::Map { name: 28, value: BinMap::new(BinValueType::String, BinValueType::Color, map) },
+            BinValue::FlagsBoolean { name: 29, value: false },
+        ];
+
+        for value in &values {
+            let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            value.write(&mut writer)?;
+            assert_eq!(writer.into_inner()?.into_inner().len(), value.size(false), "{:?}", value.value_type());
+
+            let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+            value.write_value(&mut writer)?;
+            assert_eq!(writer.into_inner()?.into_inner().len(), value.size(true), "{:?}", value.value_type());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_null_terminated_string() -> io::Result<()> {
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        writer.write_null_terminated_string("Kled_Skaarl_é")?;
+        writer.write_null_terminated_string("")?;
+        writer.write_string("unterminated")?;
+        let mut reader = BinaryReader::from_buffer(Cursor::new(writer.into_inner()?.into_inner()));
+
+        assert_eq!(reader.read_null_terminated_string()?, "Kled_Skaarl_é");
+        assert_eq!(reader.read_null_terminated_string()?, "");
+        assert_eq!(reader.read_null_terminated_string().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_index_order() -> crate::error::Result<()> {
+        let mut simple_skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        let triangle_set = |indices: &[u16]| {
+            let mut triangles: Vec<[u16; 3]> = indices
+                .chunks_exact(3)
+                .map(|triangle| {
+                    let mut triangle = [triangle[0], triangle[1], triangle[2]];
+                    triangle.sort();
+                    triangle
+                })
+                .collect();
+            triangles.sort();
+            triangles
+        };
+
+        for submesh in simple_skin.submeshes() {
+            let before = triangle_set(submesh.indices());
+            let optimization = submesh.optimize_index_order();
+
+            assert_eq!(triangle_set(submesh.indices()), before);
+            assert!(optimization.acmr_after <= optimization.acmr_before);
+
+            // Interleaving the triangles 64 apart thrashes the cache
+            let triangles: Vec<[u16; 3]> = submesh.indices().chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+            let interleaved: Vec<u16> = (0..64)
+                .flat_map(|start| triangles.iter().skip(start).step_by(64))
+                .flat_map(|triangle| triangle.iter().copied())
+                .collect();
+            submesh.indices().copy_from_slice(&interleaved);
+            let scrambled = triangle_set(submesh.indices());
+            let optimization = submesh.optimize_index_order();
+
+            assert_eq!(triangle_set(submesh.indices()), scrambled);
+            assert!(optimization.acmr_after < optimization.acmr_before);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clean_degenerate_triangles() {
+        let positions = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0), // Colinear with the first two
+            Vector3::new(5.0, 5.0, 5.0), // Only used by degenerate triangles
+        ];
+        let vertices = positions.iter().map(|position| WorldGeometryVertex::new(*position, Vector2::zero())).collect();
+        let indices = vec![0, 1, 2, 0, 1, 3, 2, 2, 4, 1, 3, 2];
+        let mut model = WorldGeometryModel::new(String::new(), String::new(), vertices, indices);
+
+        assert_eq!(model.clean(true), 2);
+        assert_eq!(model.indices(), &[0, 1, 2, 1, 3, 2]);
+        assert_eq!(model.vertices().len(), 4);
+        assert_eq!(model.bounding_box().max, Vector3::new(2.0, 1.0, 0.0));
+
+        let skin_vertices = positions
+            .iter()
+            .map(|position| SimpleSkinVertex::new_basic(*position, [0; 4], [1.0, 0.0, 0.0, 0.0], Vector3::zero(), Vector2::zero()))
+            .collect();
+        let mut submesh = SimpleSkinSubmesh::new(String::from("test"), skin_vertices, vec![0, 1, 3, 4, 4, 2, 2, 1, 4]);
+
+        assert_eq!(submesh.clean(false), 2);
+        assert_eq!(submesh.indices(), &[2, 1, 4]);
+        assert_eq!(submesh.vertices().len(), 5);
+        assert_eq!(submesh.clean(true), 0);
+        assert_eq!(submesh.indices(), &[1, 0, 2]);
+        assert_eq!(submesh.vertices()[2].position, Vector3::new(5.0, 5.0, 5.0));
+    }
+
+    #[test]
+    fn test_bin_serialize_structures() -> io::Result<()> {
+        let boxes = vec![
+            Box3D::new(Vector3::zero(), Vector3::new(1.0, 2.0, 3.0)),
+            Box3D::new(Vector3::new(-1.0, -2.0, -3.0), Vector3::zero()),
+        ];
+        let sphere = Sphere::new(Vector3::new(1.0, 2.0, 3.0), 4.0);
+
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        writer.write_slice_of(&boxes)?;
+        sphere.write(&mut writer)?;
+        Quaternion::new(0.0, 0.0, 0.0, 1.0).write(&mut writer)?;
+        let bytes = writer.into_inner()?.into_inner();
+        assert_eq!(bytes.len(), 2 * 24 + 16 + 16);
+
+        let mut reader = BinaryReader::from_buffer(Cursor::new(bytes));
+        assert_eq!(reader.read_vec_of::<Box3D>(2)?, boxes);
+        assert_eq!(reader.read_vec_of::<Sphere>(1)?, vec![sphere]);
+        assert_eq!(reader.read_vec_of::<Quaternion>(1)?, vec![Quaternion::IDENTITY]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_bucket_grid_roundtrip() -> io::Result<()> {
+        let file = std::fs::read("test_files/room_map11.wgeo")?;
+        let world_geometry = WorldGeometry::read_from_buffer(Cursor::new(file.clone()))?;
+
+        let mut writer = BinaryWriter::from_buffer(Cursor::new(Vec::new()));
+        world_geometry.bucket_grid().clone().write(&mut writer)?;
+        let written = writer.into_inner()?.into_inner();
+
+        // The grid is the last thing in the file
+        let original = &file[file.len() - written.len()..];
+        assert_eq!(&written[..44], &original[..44]);
+        assert!(written == original);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let a = Vector3::new(0.1, 0.2, 0.3);
+        let b = Vector3::new(0.1 + 0.2 - 0.2, 0.2, 0.1 * 3.0);
+        assert_ne!(a, b);
+        assert!(a.approx_eq(&b, 1e-6));
+        assert!(!a.approx_eq(&Vector3::new(0.1, 0.2, 0.31), 1e-6));
+
+        assert!(Vector2::new(1.0, 2.0).approx_eq(&Vector2::new(1.0005, 2.0), 0.001));
+        assert!(!Vector4::new(1.0, 2.0, 3.0, 4.0)
+            .approx_eq(&Vector4::new(1.0, 2.0, 3.0, 4.01), 0.001));
+
+        let bounds = Box3D::new(Vector3::zero(), a);
+        assert!(bounds.approx_eq(&Box3D::new(Vector3::zero(), b), 1e-6));
+        assert!(Sphere::new(a, 1.0).approx_eq(&Sphere::new(b, 1.0 + 1e-7), 1e-6));
+        assert!(!Sphere::new(a, 1.0).approx_eq(&Sphere::new(b, 1.1), 1e-6));
+    }
+
+    #[test]
+    fn test_simple_skin_tangents() -> crate::error::Result<()> {
+        let mut simple_skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        let submesh_count = simple_skin.submeshes().len();
+        for vertex in simple_skin.submeshes()[0].vertices() {
+            vertex.tangent = Some(Vector4::new(1.0, 0.0, 0.0, -1.0));
+        }
+
+        let bytes = simple_skin.write_to_vec()?;
+        let header = 12 + 80 * submesh_count + 12;
+        assert_eq!(&bytes[header..header + 8], &[72, 0, 0, 0, 2, 0, 0, 0]);
+
+        let mut reread = SimpleSkin::read_from_buffer(Cursor::new(bytes.clone()))?;
+        let first = reread.submeshes()[0].vertices()[0];
+        assert_eq!(first.tangent, Some(Vector4::new(1.0, 0.0, 0.0, -1.0)));
+        assert!(first.color.is_some());
+        let last = reread.submeshes()[submesh_count - 1].vertices()[0];
+        assert_eq!(last.tangent, Some(Vector4::zero()));
+
+        assert_eq!(reread.write_to_vec()?, bytes);
+        assert_eq!(SimpleSkinVertex::size_of_type(3), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_world_geometry_write_ply() -> crate::error::Result<()> {
+        let world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        let vertex_count = world_geometry.total_vertex_count();
+        let face_count = world_geometry.total_face_count();
+
+        let mut ascii = Vec::new();
+        world_geometry.write_ply(&mut ascii, PlyFormat::Ascii)?;
+        let ascii = String::from_utf8(ascii).unwrap();
+        let header_end = ascii.find("end_header\n").unwrap() + "end_header\n".len();
+        let header = &ascii[..header_end];
+        assert!(header.starts_with("ply\nformat ascii 1.0\n"));
+        assert!(header.contains(&format!("element vertex {}\n", vertex_count)));
+        assert!(header.contains(&format!("element face {}\n", face_count)));
+        assert_eq!(ascii[header_end..].lines().count(), vertex_count + face_count);
+
+        let mut binary = Vec::new();
+        world_geometry.write_ply(&mut binary, PlyFormat::BinaryLittleEndian)?;
+        let header_end = binary.windows(11).position(|window| window == b"end_header\n").unwrap() + 11;
+        let header = std::str::from_utf8(&binary[..header_end]).unwrap();
+        assert!(header.contains(&format!("element vertex {}\n", vertex_count)));
+        assert!(header.contains(&format!("element face {}\n", face_count)));
+        assert_eq!(binary.len() - header_end, vertex_count * 20 + face_count * 13);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mesh_write_ply() -> crate::error::Result<()> {
+        let mut simple_skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        for submesh in simple_skin.submeshes() {
+            for vertex in submesh.vertices() {
+                vertex.color = None;
+            }
+        }
+        simple_skin.submeshes()[0].vertices()[0].color = Some(LinSrgba::new(1.0, 0.0, 0.5, 1.0));
+        let vertex_count: usize = simple_skin.submeshes().iter_mut().map(|submesh| submesh.vertices().len()).sum();
+        let face_count: usize = simple_skin.submeshes().iter_mut().map(|submesh| submesh.indices().len() / 3).sum();
+        let first_name = simple_skin.submeshes()[0].name.clone();
+        let first_faces = simple_skin.submeshes()[0].indices().len() / 3;
+
+        let mut binary = Vec::new();
+        simple_skin.write_ply(&mut binary, PlyFormat::BinaryLittleEndian)?;
+        let header_end = binary.windows(11).position(|window| window == b"end_header\n").unwrap() + 11;
+        let header = std::str::from_utf8(&binary[..header_end]).unwrap();
+        assert!(header.contains(&format!("comment material {} faces 0 {}\n", first_name, first_faces)));
+        assert!(header.contains("property float nx\n"));
+        assert!(header.contains("property uchar red\nproperty uchar green\nproperty uchar blue\nproperty uchar alpha\n"));
+        assert!(header.contains(&format!("element vertex {}\n", vertex_count)));
+        assert!(header.contains(&format!("element face {}\n", face_count)));
+        assert_eq!(binary.len() - header_end, vertex_count * (8 * 4 + 4) + face_count * 13);
+        assert_eq!(&binary[header_end + 32..header_end + 36], &[255, 0, 128, 255]);
+        assert_eq!(&binary[header_end + 36 + 32..header_end + 36 + 36], &[255, 255, 255, 255]);
+
+        let static_object = StaticObject::read_scb_from_file(Path::new("test_files/aatrox_base_w_ground_ring.scb"))?;
+        let mut ascii = Vec::new();
+        static_object.write_ply(&mut ascii, PlyFormat::Ascii)?;
+        let ascii = String::from_utf8(ascii).unwrap();
+        let header_end = ascii.find("end_header\n").unwrap() + "end_header\n".len();
+        let comments = ascii[..header_end].lines().filter(|line| line.starts_with("comment material ")).count();
+        assert!(comments > 0);
+        let vertex_line = ascii[..header_end].lines().find(|line| line.starts_with("element vertex ")).unwrap();
+        let face_line = ascii[..header_end].lines().find(|line| line.starts_with("element face ")).unwrap();
+        let vertex_count: usize = vertex_line["element vertex ".len()..].parse().unwrap();
+        let face_count: usize = face_line["element face ".len()..].parse().unwrap();
+        assert_eq!(ascii[header_end..].lines().count(), vertex_count + face_count);
+        assert!(ascii[header_end..].lines().skip(vertex_count).all(|line| line.starts_with("3 ")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_read_obj() -> io::Result<()> {
+        let path = temp_path("read_obj.obj");
+        std::fs::write(
+            &path,
+            "# quad and a triangle\n\
+             v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             vn 0 0 1\n\
+             g body\nusemtl Body\n\
+             f 1/1/1 2/2/1 3/3/1 4/4/1\n\
+             g ring\nusemtl Ring\n\
+             f -4 -3 -1\n",
+        )?;
+
+        let mut simple_skin = SimpleSkin::read_obj(&path)?;
+        assert_eq!(simple_skin.submeshes().len(), 2);
+
+        let body = &mut simple_skin.submeshes()[0];
+        assert_eq!(body.name, "Body");
+        assert_eq!(body.indices(), &[0, 1, 2, 0, 2, 3]);
+        assert_eq!(body.vertices().len(), 4);
+        let vertex = body.vertices()[1];
+        assert_eq!(vertex.uv, Vector2::new(1.0, 1.0));
+        assert_eq!(vertex.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(vertex.influences, [0, 0, 0, 0]);
+        assert_eq!(vertex.weights, [1.0, 0.0, 0.0, 0.0]);
+
+        let ring = &mut simple_skin.submeshes()[1];
+        assert_eq!(ring.name, "Ring");
+        assert_eq!(ring.indices(), &[0, 1, 2]);
+        assert_eq!(ring.vertices()[2].position, Vector3::new(0.0, 1.0, 0.0));
+        assert!(ring.vertices()[0].normal.approx_eq(&Vector3::new(0.0, 0.0, 1.0), 1e-6));
+
+        std::fs::write(&path, "v 0 0 0\nf 1 2 3\n")?;
+        assert_eq!(SimpleSkin::read_obj(&path).err().map(|error| error.kind()), Some(ErrorKind::InvalidData));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_static_object_read_obj() -> crate::error::Result<()> {
+        let path = temp_path("rusty_league_static_object.obj");
+        std::fs::write(
+            &path,
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 0.5\n\
+             usemtl Stone\n\
+             f 1/1 2/2 3/3\n\
+             f 1/5 3/3 4/4\n\
+             usemtl Grass\n\
+             f 2/1 5/2 3/3\n",
+        )?;
+
+        let static_object = StaticObject::read_obj(&path)?;
+        assert_eq!(static_object.name, "rusty_league_static_object");
+        let bytes = static_object.write_scb_to_vec()?;
+        let mut reread = StaticObject::read_scb_from_buffer(Cursor::new(bytes))?;
+        assert_eq!(reread.submeshes().len(), 2);
+
+        for submesh in reread.submeshes() {
+            match submesh.name.as_str() {
+                "Stone" => {
+                    // The first position has two different UVs, so it's split into two vertices
+                    assert_eq!(submesh.vertices().len(), 5);
+                    assert_eq!(submesh.indices().len(), 6);
+                    let uvs: Vec<Vector2> = submesh.vertices().iter().map(|vertex| vertex.uv).collect();
+                    assert!(uvs.contains(&Vector2::new(0.0, 1.0)));
+                    assert!(uvs.contains(&Vector2::new(0.5, 0.5)));
+                }
+                "Grass" => {
+                    assert_eq!(submesh.vertices().len(), 3);
+                    assert_eq!(submesh.vertices()[1].position, Vector3::new(2.0, 0.0, 0.0));
+                    assert_eq!(submesh.vertices()[1].uv, Vector2::new(1.0, 1.0));
+                }
+                name => panic!("Unexpected submesh {}", name),
+            }
+        }
+
+        let static_object = StaticObject::read_scb_from_file(Path::new("test_files/aatrox_base_w_ground_ring.scb"))?;
+        let bytes = static_object.write_scb_to_vec()?;
+        let reread = StaticObject::read_scb_from_buffer(Cursor::new(bytes.clone()))?;
+        assert_eq!(reread.write_scb_to_vec()?.len(), bytes.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_environment_quality() -> crate::error::Result<()> {
+        let nvr = SimpleEnvironment::read_file(Path::new("test_files/room.nvr"))?;
+        let mesh_count = nvr.meshes().len();
+        let always_drawn = nvr.meshes().iter().filter(|mesh| mesh.quality().is_none()).count();
+        assert!(always_drawn > 0);
+
+        let very_low = nvr.meshes_for_quality(SimpleEnvironmentQuality::VeryLow).count();
+        let low = nvr.meshes_for_quality(SimpleEnvironmentQuality::Low).count();
+        let very_high = nvr.meshes_for_quality(SimpleEnvironmentQuality::VeryHigh).count();
+        assert!(always_drawn < very_low && very_low < low && low < very_high);
+        assert_eq!(very_high, mesh_count);
+        assert!(nvr
+            .meshes_for_quality(SimpleEnvironmentQuality::Low)
+            .all(|mesh| mesh.quality().map_or(true, |quality| quality <= SimpleEnvironmentQuality::Low)));
+
+        let mesh = &nvr.meshes()[0];
+        assert!(mesh.geometry_for_lod(false).vertices().iter().all(|vertex| matches!(vertex, SimpleEnvironmentVertex::Position { .. })));
+        assert!(!mesh.geometry_for_lod(true).indices().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_simple_skin_parallel() -> crate::error::Result<()> {
+        let path = Path::new("test_files/aatrox.skn");
+
+        let mut sequential = SimpleSkin::read_from_file(path)?;
+        let mut parallel = SimpleSkin::read_from_file_parallel(path)?;
+        assert_eq!(parallel.write_to_vec()?, sequential.write_to_vec()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_bucket_grid_build() -> crate::error::Result<()> {
+        let mut world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        world_geometry.rebuild_bucket_grid(16)?;
+        let mut grid = world_geometry.bucket_grid().clone();
+        assert_eq!(grid.buckets_per_side(), 16);
+
+        let mut expected: Vec<[Vector3; 3]> = Vec::new();
+        for model in world_geometry.models() {
+            for face in model.indices().chunks_exact(3) {
+                let vertices = model.vertices();
+                expected.push([
+                    vertices[face[0] as usize].position,
+                    vertices[face[1] as usize].position,
+                    vertices[face[2] as usize].position,
+                ]);
+            }
+        }
+
+        let bounds = grid.bounds();
+        let (bucket_size_x, bucket_size_z) = grid.bucket_size();
+        let mut found: Vec<[Vector3; 3]> = Vec::new();
+        let mut max_stick_out = (0.0f32, 0.0f32);
+        for (row, bucket_row) in grid.buckets().iter().enumerate() {
+            for (column, bucket) in bucket_row.iter().enumerate() {
+                let (stick_out_x, stick_out_z) = bucket.max_stick_out();
+                let min_x = bounds.min.x + column as f32 * bucket_size_x - stick_out_x - 1e-3;
+                let max_x = bounds.min.x + (column + 1) as f32 * bucket_size_x + stick_out_x + 1e-3;
+                let min_z = bounds.min.z + row as f32 * bucket_size_z - stick_out_z - 1e-3;
+                let max_z = bounds.min.z + (row + 1) as f32 * bucket_size_z + stick_out_z + 1e-3;
+                max_stick_out = (max_stick_out.0.max(stick_out_x), max_stick_out.1.max(stick_out_z));
+
+                for (i, face) in grid.faces_in_bucket(bucket).enumerate() {
+                    let face = [
+                        grid.vertices()[face[0] as usize],
+                        grid.vertices()[face[1] as usize],
+                        grid.vertices()[face[2] as usize],
+                    ];
+                    assert!(face.iter().all(|vertex| vertex.x >= min_x && vertex.x <= max_x && vertex.z >= min_z && vertex.z <= max_z));
+                    if i < bucket.inside_face_count() as usize {
+                        assert!(face.iter().all(|vertex| vertex.x >= min_x + stick_out_x && vertex.x <= max_x - stick_out_x));
+                    }
+
+                    found.push(face);
+                }
+            }
+        }
+        assert_eq!(grid.max_stick_out(), max_stick_out);
+
+        let key = |face: &[Vector3; 3]| format!("{:?}", face);
+        expected.sort_by_cached_key(key);
+        found.sort_by_cached_key(key);
+        assert_eq!(found.len(), expected.len());
+        assert!(found == expected);
+
+        let bytes = world_geometry.write_to_vec()?;
+        let reread = WorldGeometry::read_from_buffer(Cursor::new(bytes))?;
+        assert_eq!(reread.bucket_grid().indices(), grid.indices());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_manifest_mutated_offsets() -> io::Result<()> {
+        let file = std::fs::read("test_files/C944A5BD0686C600.manifest")?;
+        let content_offset = u32::from_le_bytes([file[8], file[9], file[10], file[11]]) as usize;
+        let content_size = u32::from_le_bytes([file[12], file[13], file[14], file[15]]) as usize;
+        let body = zstd::stream::decode_all(&file[content_offset..content_offset + content_size])?;
+        assert!(ReleaseManifest::read_body(&body, &mut |_, _| {}).is_ok());
+
+        // The header, followed by the relative offsets of the bundle, language, file and directory tables
+        let read_u32 = |position: usize| u32::from_le_bytes([body[position], body[position + 1], body[position + 2], body[position + 3]]) as usize;
+        let header = read_u32(0);
+        let mut targets = vec![0, header, header + 4];
+        for table in 0..4 {
+            let position = header + 4 + table * 4;
+            targets.push(position);
+            targets.push(position + read_u32(position));
+        }
+
+        // Overwrite u32s at the offsets and counts and at random places, and only require no panics
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..48 {
+            let mut mutated = body.clone();
+            for _ in 0..1 + next() % 4 {
+                let position = if next() % 2 == 0 {
+                    targets[next() as usize % targets.len()] + (next() as usize % 16) * 4
+                } else {
+                    (next() as usize % (mutated.len() / 4)) * 4
+                };
+                let value = match next() % 4 {
+                    0 => 0xFFFF_FFFFu32,
+                    1 => 0x8000_0000,
+                    2 => next() as u32 % 64,
+                    _ => next() as u32,
+                };
+                mutated[position..position + 4].copy_from_slice(&value.to_le_bytes());
+            }
+
+            let _ = ReleaseManifest::read_body(&mutated, &mut |_, _| {});
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_any_asset() -> crate::error::Result<()> {
+        let mut asset = AnyAsset::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        assert_eq!(asset.kind(), FileKind::SimpleSkin);
+        assert!(asset.as_bin().is_none());
+        let submesh_count = asset.as_skin_mut().unwrap().submeshes().len();
+
+        let path = temp_path("any_asset.skn");
+        asset.write_to_file(&path)?;
+        let mut reread = AnyAsset::read_from_file(&path)?;
+        assert_eq!(reread.as_skin_mut().unwrap().submeshes().len(), submesh_count);
+
+        let mut asset = AnyAsset::read_from_file(Path::new("test_files/skin0.bin"))?;
+        assert_eq!(asset.kind(), FileKind::Bin);
+        let entry_count = asset.as_bin().unwrap().entries().len();
+        let path = temp_path("any_asset.bin");
+        asset.write_to_file(&path)?;
+        assert_eq!(AnyAsset::read_from_file(&path)?.as_bin().unwrap().entries().len(), entry_count);
+
+        let mut asset = AnyAsset::read_from_file(Path::new("test_files/room.nvr"))?;
+        assert!(asset.as_simple_environment().is_some());
+        let error = asset.write_to_file(&temp_path("any_asset.nvr")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() -> Result<(), serde_json::Error> {
+        fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug>(value: T) -> Result<(), serde_json::Error> {
+            let json = serde_json::to_string(&value)?;
+            assert_eq!(serde_json::from_str::<T>(&json)?, value);
+            Ok(())
+        }
+
+        round_trip(Vector2::new(1.5, -2.0))?;
+        round_trip(Vector3::new(1.5, -2.0, 3.25))?;
+        round_trip(Vector4::new(1.5, -2.0, 3.25, 0.5))?;
+        round_trip(Quaternion::new(0.0, 0.0, 0.70710677, 0.70710677))?;
+        round_trip(Box3D::new(Vector3::new(-1.0, -2.0, -3.0), Vector3::new(1.0, 2.0, 3.0)))?;
+        round_trip(Sphere::new(Vector3::new(1.0, 2.0, 3.0), 4.5))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_lod_chain() -> crate::error::Result<()> {
+        let mut chain = SimpleSkinLodChain::read_chain(&[Path::new("test_files/aatrox.skn")])?;
+        let full = chain.lod(0).unwrap().triangle_count();
+
+        assert_eq!(chain.add_generated_lod(0.5), Some(1));
+        let lod = chain.lod_mut(1).unwrap();
+        let reduced = lod.triangle_count();
+        assert!(reduced < full * 3 / 4 && reduced > 0);
+        for submesh in lod.submeshes() {
+            assert!(submesh.triangles().is_ok());
+        }
+
+        let path = temp_path("lod1.skn");
+        lod.write_to_file(&path)?;
+        let chain = SimpleSkinLodChain::read_chain(&[path.as_path(), Path::new("test_files/aatrox.skn")])?;
+        assert_eq!(chain.lod(0).unwrap().triangle_count(), full);
+        assert_eq!(chain.lod(1).unwrap().triangle_count(), reduced);
+        assert!(chain.lod(2).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_submesh_simplify() {
+        // A flat 16x16 quad grid split down the middle along a UV seam
+        let size = 16;
+        let mut vertices = Vec::new();
+        let mut vertex_index = HashMap::new();
+        let mut indices: Vec<u16> = Vec::new();
+        for z in 0..size {
+            for x in 0..size {
+                let quad = [(x, z), (x, z + 1), (x + 1, z + 1), (x + 1, z)];
+                let corners: Vec<u16> = quad
+                    .iter()
+                    .map(|(corner_x, corner_z)| {
+                        let side = x < size / 2;
+                        *vertex_index.entry((*corner_x, *corner_z, side)).or_insert_with(|| {
+                            let position = Vector3::new(*corner_x as f32 / size as f32, 0.0, *corner_z as f32 / size as f32);
+                            let uv = Vector2::new(position.x + if side { 0.0 } else { 1.0 }, position.z);
+                            let weights = [1.0 - position.x, position.x, 0.0, 0.0];
+                            vertices.push(SimpleSkinVertex::new_basic(position, [0, 1, 2, 3], weights, Vector3::new(0.0, 1.0, 0.0), uv));
+                            vertices.len() as u16 - 1
+                        })
+                    })
+                    .collect();
+                indices.extend_from_slice(&[corners[0], corners[1], corners[2], corners[0], corners[2], corners[3]]);
+            }
+        }
+
+        let original_count = indices.len() / 3;
+        let mut submesh = SimpleSkinSubmesh::new(String::from("plane"), vertices, indices);
+        let triangle_count = submesh.simplify(100);
+        assert!(triangle_count < original_count / 3);
+        assert_eq!(submesh.indices().len() / 3, triangle_count);
+
+        let mut area = 0.0;
+        let mut open_edges: HashMap<[(i32, i32); 2], i32> = HashMap::new();
+        let key = |vertex: &SimpleSkinVertex| ((vertex.position.x * 1000.0).round() as i32, (vertex.position.z * 1000.0).round() as i32);
+        for triangle in submesh.triangles().unwrap() {
+            let normal = Vector3::cross(triangle[1].position - triangle[0].position, triangle[2].position - triangle[0].position);
+            assert!(normal.y > 0.0);
+            area += normal.length() / 2.0;
+
+            for i in 0..3 {
+                let (a, b) = (key(triangle[i]), key(triangle[(i + 1) % 3]));
+                *open_edges.entry([a.min(b), a.max(b)]).or_insert(0) += 1;
+            }
+        }
+        assert!((area - 1.0).abs() < 1e-4);
+        for ([a, b], uses) in open_edges {
+            let on_outline = (a.0 == b.0 && (a.0 == 0 || a.0 == 1000)) || (a.1 == b.1 && (a.1 == 0 || a.1 == 1000));
+            assert!(uses == 2 || on_outline);
+        }
+
+        for vertex in submesh.vertices() {
+            assert!((vertex.weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+            let bone = vertex.influences.iter().position(|influence| *influence == 1).unwrap();
+            assert!((vertex.weights[bone] - vertex.position.x).abs() < 1e-4);
+            assert!((vertex.uv.x - vertex.position.x).abs() < 1e-4 || (vertex.uv.x - vertex.position.x - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_file_versions() -> crate::error::Result<()> {
+        let skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        assert_eq!(skin.version().to_string(), "4.1");
+        assert_eq!(SimpleSkin::new(Vec::new()).version(), Version::new(4, 1));
+
+        let static_object = StaticObject::read_scb_from_file(Path::new("test_files/aatrox_base_w_ground_ring.scb"))?;
+        assert_eq!(static_object.version(), Version::new(3, 2));
+
+        let world_geometry = WorldGeometry::read_from_file(Path::new("test_files/room_map11.wgeo"))?;
+        assert_eq!(format!("{}", world_geometry.version()), "5.0");
+
+        let bin = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        assert_eq!(bin.version(), Version::new(2, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_lenient_unknown_value() -> crate::error::Result<()> {
+        let entry = |fields: &[(u8, &[u8])]| {
+            let mut bin = b"PROP".to_vec();
+            bin.extend_from_slice(&2u32.to_le_bytes()); // Version
+            bin.extend_from_slice(&0u32.to_le_bytes()); // Dependency count
+            bin.extend_from_slice(&1u32.to_le_bytes()); // Entry count
+            bin.extend_from_slice(&0x1234u32.to_le_bytes()); // Entry class
+
+            let mut values = Vec::new();
+            for (i, (type_id, value)) in fields.iter().enumerate() {
+                values.extend_from_slice(&(i as u32 + 1).to_le_bytes());
+                values.push(*type_id);
+                values.extend_from_slice(value);
+            }
+            bin.extend_from_slice(&(6 + values.len() as u32).to_le_bytes());
+            bin.extend_from_slice(&0x5678u32.to_le_bytes());
+            bin.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+            bin.extend_from_slice(&values);
+
+            bin
+        };
+        let lenient_read = |bin: &[u8]| {
+            let mut reader = BinaryReader::from_buffer(Cursor::new(bin.to_vec()));
+            reader.set_lenient(true);
+            BinReader::read_tree(&mut reader)
+        };
+
+        let bin = entry(&[(7, &42u32.to_le_bytes()), (127, &[1, 2, 3])]);
+        assert!(matches!(BinReader::read_tree_buffer(Cursor::new(bin.clone())), Err(Error::InvalidValueType(127))));
+
+        let tree = lenient_read(&bin)?;
+        let unknown = &tree.entries()[0].values()[1];
+        assert_eq!(unknown, &BinValue::Unknown { name: 2, type_id: 127, raw: vec![1, 2, 3] });
+        assert_eq!(unknown.type_id(), 127);
+        assert_eq!(unknown.known_value_type(), None);
+        assert_eq!(tree.entries()[0].values()[0].known_value_type(), Some(BinValueType::UInt32));
+        assert_eq!(BinWriter::write_tree_to_vec(&tree)?, bin);
+
+        // Followed by another field its size can't be known
+        let bin = entry(&[(127, &[1, 2, 3]), (7, &42u32.to_le_bytes())]);
+        assert!(matches!(lenient_read(&bin), Err(Error::InvalidValueType(127))));
+
+        // The declared entry size can't claim more bytes than the stream has left
+        let mut bin = entry(&[(127, &[1, 2, 3])]);
+        bin[20..24].copy_from_slice(&0x7FFF_FFFFu32.to_le_bytes());
+        assert_eq!(lenient_read(&bin).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fix_winding() {
+        // Looking down from +Y the first triangle is counter-clockwise, the second one clockwise
+        let positions = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0)];
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let face_normal = |vertices: &[Vector3], triangle: &[u32]| {
+            let a = vertices[triangle[0] as usize];
+            Vector3::cross(vertices[triangle[1] as usize] - a, vertices[triangle[2] as usize] - a)
+        };
+
+        let vertices: Vec<SimpleSkinVertex> = positions
+            .iter()
+            .map(|position| SimpleSkinVertex::new_basic(*position, [0; 4], [1.0, 0.0, 0.0, 0.0], up, Vector2::zero()))
+            .collect();
+        let mut submesh = SimpleSkinSubmesh::new(String::from("skin"), vertices, vec![0, 1, 2, 0, 2, 1]);
+        assert_eq!(submesh.fix_winding(Vector3::zero()), 1);
+        assert_eq!(submesh.indices(), &[0, 1, 2, 0, 1, 2]);
+        assert_eq!(submesh.fix_winding(Vector3::zero()), 0);
+        submesh.flip_winding();
+        assert_eq!(submesh.indices(), &[0, 2, 1, 0, 2, 1]);
+
+        let vertices: Vec<StaticObjectVertex> = positions.iter().map(|position| StaticObjectVertex::new_basic(*position, Vector2::zero())).collect();
+        let mut submesh = StaticObjectSubmesh::new(String::from("static"), vertices, vec![0, 1, 2, 0, 2, 1]);
+        assert_eq!(submesh.fix_winding(up), 1);
+        for triangle in submesh.indices().clone().chunks_exact(3) {
+            assert!(face_normal(&positions, triangle).y > 0.0);
+        }
+        submesh.flip_winding();
+        assert_eq!(submesh.fix_winding(up), 2);
+    }
+
+    #[test]
+    fn test_empty_mesh_bounds() -> io::Result<()> {
+        let mut grid = RenderBucketGrid::empty();
+        assert_eq!(grid.bounds(), Box3D::ZERO);
+        let mut grid = RenderBucketGrid::build(&[], 4)?;
+        assert_eq!(grid.bounds(), Box3D::ZERO);
+
+        let mut model = WorldGeometryModel::new(String::new(), String::new(), Vec::new(), Vec::new());
+        assert_eq!(model.bounding_box(), Box3D::ZERO);
+        assert_eq!(model.bounding_sphere(), Sphere::ZERO);
+
+        let mut skin = SimpleSkin::new(Vec::new());
+        assert_eq!(skin.bounding_box(), Box3D::ZERO);
+        let mut skin = SimpleSkin::new(vec![SimpleSkinSubmesh::new(String::from("empty"), Vec::new(), Vec::new())]);
+        assert_eq!(skin.bounding_box(), Box3D::ZERO);
+        assert_eq!(skin.bounding_sphere(), Sphere::ZERO);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_reorder_submeshes() -> crate::error::Result<()> {
+        let mut skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        let submesh_data = |skin: &mut SimpleSkin| -> Vec<(String, usize, usize)> {
+            skin.submeshes()
+                .iter_mut()
+                .map(|submesh| (submesh.name.clone(), submesh.vertices().len(), submesh.indices().len()))
+                .collect()
+        };
+        let before = submesh_data(&mut skin);
+        assert!(before.len() > 1);
+        SimpleSkin::read_from_buffer(Cursor::new(skin.write_to_vec()?))?;
+
+        assert!(skin.reorder_submeshes(&[0]).is_err());
+        assert!(skin.reorder_submeshes(&vec![0; before.len()]).is_err());
+        let order: Vec<usize> = (0..before.len()).rev().collect();
+        skin.reorder_submeshes(&order)?;
+
+        assert_eq!(skin.rename_submesh("missing", "other").unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(skin.rename_submesh(&before[0].0, &before[1].0).unwrap_err().kind(), ErrorKind::AlreadyExists);
+        skin.rename_submesh(&before[0].0, "renamed")?;
+
+        let mut reread = SimpleSkin::read_from_buffer(Cursor::new(skin.write_to_vec()?))?;
+        let after = submesh_data(&mut reread);
+        for (i, (name, vertex_count, index_count)) in after.iter().enumerate() {
+            let original = &before[before.len() - 1 - i];
+            let expected_name = if original.0 == before[0].0 { "renamed" } else { &original.0 };
+            assert_eq!((name.as_str(), *vertex_count, *index_count), (expected_name, original.1, original.2));
+        }
+        assert!(reread.validate().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_split_submesh_by_influence() -> io::Result<()> {
+        // A strip of 4 quads going along X, weighted to bone 0 on the left and bone 1 on the right with a blend in the middle
+        let mut vertices = Vec::new();
+        for x in 0..5 {
+            for z in 0..2 {
+                let weight = match x {
+                    0 | 1 => 0.0,
+                    2 => 0.5,
+                    _ => 1.0,
+                };
+                let position = Vector3::new(x as f32, 0.0, z as f32);
+                vertices.push(SimpleSkinVertex::new_basic(position, [0, 1, 0, 0], [1.0 - weight, weight, 0.0, 0.0], Vector3::new(0.0, 1.0, 0.0), Vector2::zero()));
+            }
+        }
+        let mut indices: Vec<u16> = Vec::new();
+        for x in 0..4 {
+            let corner = x * 2;
+            indices.extend_from_slice(&[corner, corner + 1, corner + 3, corner, corner + 3, corner + 2]);
+        }
+
+        let mut skin = SimpleSkin::new(vec![SimpleSkinSubmesh::new(String::from("body"), vertices, indices)]);
+        assert_eq!(skin.split_submesh_by_influence("missing", 1).unwrap_err().kind(), ErrorKind::NotFound);
+
+        let mut split = skin.split_submesh_by_influence("body", 1)?;
+        assert_eq!(split.name, "body_1");
+        // The 2 quads right of the blended column plus the one left of it which touches it
+        assert_eq!(split.indices().len(), 3 * 6);
+        assert_eq!(split.vertices().len(), 8);
+        assert!(split.triangles()?.all(|triangle| triangle.iter().any(|vertex| vertex.weights[1] > 0.0)));
+
+        let body = &mut skin.submeshes()[0];
+        assert_eq!(body.indices().len(), 6);
+        assert_eq!(body.vertices().len(), 4);
+        assert!(body.triangles()?.all(|triangle| triangle.iter().all(|vertex| vertex.weights[1] == 0.0)));
+
+        // The blended column is only on the split side, the column left of it is on both
+        let shared = body.vertices().iter().filter(|vertex| vertex.position.x == 1.0).count();
+        assert_eq!(shared, 2);
+        assert_eq!(split.vertices().iter().filter(|vertex| vertex.position.x == 1.0).count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_skin_all_vertices() -> crate::error::Result<()> {
+        let mut skin = SimpleSkin::read_from_file(Path::new("test_files/aatrox.skn"))?;
+        let vertex_count: usize = skin.submeshes().iter_mut().map(|submesh| submesh.vertices().len()).sum();
+        assert_eq!(skin.all_vertices().count(), vertex_count);
+
+        // Recenter the whole model
+        let center = skin.central_point();
+        for vertex in skin.all_vertices_mut() {
+            vertex.position = vertex.position - center;
+        }
+        assert!(skin.central_point().approx_eq(&Vector3::zero(), 1e-3));
+        assert_eq!(skin.all_vertices().count(), vertex_count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bin_reference_graph() -> crate::error::Result<()> {
+        let mut tree = BinReader::read_tree_file(Path::new("test_files/skin0.bin"))?;
+        let paths: Vec<u32> = tree.entries().iter().map(|entry| entry.path()).collect();
+        assert!(paths.len() >= 3);
+
+        let graph = tree.reference_graph();
+        assert_eq!(graph.len(), paths.len());
+        let unreferenced: Vec<u32> = tree.unreferenced_entries().iter().map(|entry| entry.path()).collect();
+        for path in &unreferenced {
+            assert!(graph.iter().all(|(from, references)| from == path || !references.contains(path)));
+        }
+
+        // A link always counts, a hash only if an entry has that path
+        let (first, second, third) = (paths[0], paths[1], paths[2]);
+        let entry = tree.entry_mut(first).unwrap();
+        entry.set_field(0x1111, BinValue::Link { name: 0x1111, value: second });
+        entry.set_field(0x2222, BinValue::Hash { name: 0x2222, value: third });
+        entry.set_field(0x3333, BinValue::Hash { name: 0x3333, value: 0xdeadbeef });
+
+        let graph = tree.reference_graph();
+        assert!(graph[&first].contains(&second) && graph[&first].contains(&third));
+        assert!(!graph[&first].contains(&0xdeadbeef));
+        let unreferenced: Vec<u32> = tree.unreferenced_entries().iter().map(|entry| entry.path()).collect();
+        assert!(!unreferenced.contains(&second) && !unreferenced.contains(&third));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_reader_hexdump() -> io::Result<()> {
+        let mut data = b"PROP\x02\x00\x00\x00".to_vec();
+        data.extend(0u8..12);
+        let mut reader = BinaryReader::from_buffer(Cursor::new(data));
+        reader.read_u16()?;
+
+        let dump = reader.hexdump(64)?;
+        assert_eq!(reader.position()?, 2);
+        assert_eq!(
+            dump,
+            "00000002  4f 50 02 00 00 00 00 01  02 03 04 05 06 07 08 09  |OP..............|\n\
+             00000012  0a 0b                                             |..|\n"
+        );
+        assert_eq!(reader.read_u16()?, u16::from_le_bytes(*b"OP"));
+        assert_eq!(reader.hexdump(0)?, "");
 
         Ok(())
     }
@@ -5,4 +5,5 @@ pub mod sphere;
 pub mod vector2;
 pub mod vector3;
 pub mod vector4;
-pub mod matrix44;
\ No newline at end of file
+pub mod matrix44;
+pub mod quaternion;
\ No newline at end of file
@@ -5,6 +5,7 @@ use std::io;
 use std::io::{Read, Seek, Write};
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Box3D {
     pub min: Vector3,
     pub max: Vector3,
@@ -25,6 +26,17 @@ impl Box3D {
     pub fn new(min: Vector3, max: Vector3) -> Self {
         Box3D { min, max }
     }
+    /// Returns `Box3D::ZERO` if there are no points
+    pub fn from_points(points: impl IntoIterator<Item = Vector3>) -> Self {
+        let mut points = points.into_iter();
+
+        match points.next() {
+            Some(first) => points.fold(Box3D::new(first, first), |bounds, point| {
+                bounds.merge(&Box3D::new(point, point))
+            }),
+            None => Box3D::ZERO,
+        }
+    }
     pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         Ok(Box3D {
             min: Vector3::read(reader)?,
@@ -32,10 +44,35 @@ impl Box3D {
         })
     }
 
-    pub fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+    pub fn write<T: Write + Seek>(&self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
         self.min.write(writer)?;
         self.max.write(writer)?;
 
         Ok(())
     }
+
+    pub fn approx_eq(&self, other: &Box3D, epsilon: f32) -> bool {
+        self.min.approx_eq(&other.min, epsilon) && self.max.approx_eq(&other.max, epsilon)
+    }
+
+    pub fn merge(&self, other: &Box3D) -> Box3D {
+        Box3D {
+            min: Vector3::min(self.min, other.min),
+            max: Vector3::max(self.max, other.max),
+        }
+    }
+    pub fn contains(&self, point: Vector3) -> bool {
+        point.x >= self.min.x
+            && point.y >= self.min.y
+            && point.z >= self.min.z
+            && point.x <= self.max.x
+            && point.y <= self.max.y
+            && point.z <= self.max.z
+    }
+    pub fn center(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+    pub fn size(&self) -> Vector3 {
+        self.max - self.min
+    }
 }
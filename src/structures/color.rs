@@ -5,11 +5,26 @@ use std::io;
 use std::io::{Read, Seek, Write};
 
 pub trait LinSrgbaExt: Sized {
+    /// Reads the bytes as they are, which is correct for vertex colors and BIN color values
     fn read_rgba_u8<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
+    /// Decodes sRGB encoded bytes to linear, for material colors meant for display.
+    /// Alpha is always stored linearly
+    fn read_srgba_u8<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
     fn read_bgra_u8<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
+    fn read_rgba_u16<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
     fn read_rgba_f32<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
+    fn read_packed_rgb565<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
+    fn read_packed_rgba5551<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self>;
+
+    /// Red in the top 5 bits, then 6 bits of green and 5 of blue, as used by BC1 endpoints
+    fn from_rgb565(packed: u16) -> Self;
+    /// Alpha in the top bit, then 5 bits each of red, green and blue, like `D3DFMT_A1R5G5B5`
+    fn from_rgba5551(packed: u16) -> Self;
 
     fn write_rgba_u8<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()>;
+    /// Encodes the linear color to sRGB bytes, the inverse of `read_srgba_u8`
+    fn write_srgba_u8<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()>;
+    fn write_rgba_u16<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()>;
     fn write_rgba_f32<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()>;
 }
 pub trait LinSrgbExt: Sized {
@@ -30,6 +45,15 @@ impl LinSrgbaExt for LinSrgba {
         ))
     }
 
+    fn read_srgba_u8<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+        Ok(LinSrgba::new(
+            srgb_to_linear(reader.read_u8()? as f32 / 255.0),
+            srgb_to_linear(reader.read_u8()? as f32 / 255.0),
+            srgb_to_linear(reader.read_u8()? as f32 / 255.0),
+            reader.read_u8()? as f32 / 255.0,
+        ))
+    }
+
     fn read_bgra_u8<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
         let b = reader.read_u8()? as f32 / 255.0;
         let g = reader.read_u8()? as f32 / 255.0;
@@ -39,6 +63,15 @@ impl LinSrgbaExt for LinSrgba {
         Ok(LinSrgba::new(r, g, b, a))
     }
 
+    fn read_rgba_u16<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+        Ok(LinSrgba::new(
+            reader.read_u16()? as f32 / 65535.0,
+            reader.read_u16()? as f32 / 65535.0,
+            reader.read_u16()? as f32 / 65535.0,
+            reader.read_u16()? as f32 / 65535.0,
+        ))
+    }
+
     fn read_rgba_f32<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
         Ok(LinSrgba::new(
             reader.read_f32()?,
@@ -48,11 +81,51 @@ impl LinSrgbaExt for LinSrgba {
         ))
     }
 
+    fn read_packed_rgb565<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+        Ok(LinSrgba::from_rgb565(reader.read_u16()?))
+    }
+    fn read_packed_rgba5551<R: Read + Seek>(reader: &mut BinaryReader<R>) -> io::Result<Self> {
+        Ok(LinSrgba::from_rgba5551(reader.read_u16()?))
+    }
+
+    fn from_rgb565(packed: u16) -> Self {
+        LinSrgba::new(
+            ((packed >> 11) & 0x1F) as f32 / 31.0,
+            ((packed >> 5) & 0x3F) as f32 / 63.0,
+            (packed & 0x1F) as f32 / 31.0,
+            1.0,
+        )
+    }
+    fn from_rgba5551(packed: u16) -> Self {
+        LinSrgba::new(
+            ((packed >> 10) & 0x1F) as f32 / 31.0,
+            ((packed >> 5) & 0x1F) as f32 / 31.0,
+            (packed & 0x1F) as f32 / 31.0,
+            (packed >> 15) as f32,
+        )
+    }
+
     fn write_rgba_u8<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()> {
-        writer.write_u8((self.color.red * 255.0) as u8)?;
-        writer.write_u8((self.color.green * 255.0) as u8)?;
-        writer.write_u8((self.color.blue * 255.0) as u8)?;
-        writer.write_u8((self.alpha * 255.0) as u8)?;
+        writer.write_u8(to_u8(self.color.red))?;
+        writer.write_u8(to_u8(self.color.green))?;
+        writer.write_u8(to_u8(self.color.blue))?;
+        writer.write_u8(to_u8(self.alpha))?;
+
+        Ok(())
+    }
+    fn write_srgba_u8<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()> {
+        writer.write_u8(to_u8(linear_to_srgb(self.color.red)))?;
+        writer.write_u8(to_u8(linear_to_srgb(self.color.green)))?;
+        writer.write_u8(to_u8(linear_to_srgb(self.color.blue)))?;
+        writer.write_u8(to_u8(self.alpha))?;
+
+        Ok(())
+    }
+    fn write_rgba_u16<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()> {
+        writer.write_u16(to_u16(self.color.red))?;
+        writer.write_u16(to_u16(self.color.green))?;
+        writer.write_u16(to_u16(self.color.blue))?;
+        writer.write_u16(to_u16(self.alpha))?;
 
         Ok(())
     }
@@ -83,17 +156,42 @@ impl LinSrgbExt for LinSrgb {
     }
 
     fn write_rgb_u8<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()> {
-        writer.write_u8((self.red * 255.0) as u8)?;
-        writer.write_u8((self.green * 255.0) as u8)?;
-        writer.write_u8((self.blue * 255.0) as u8)?;
+        writer.write_u8(to_u8(self.red))?;
+        writer.write_u8(to_u8(self.green))?;
+        writer.write_u8(to_u8(self.blue))?;
 
         Ok(())
     }
     fn write_bgr_u8<W: Write + Seek>(&self, writer: &mut BinaryWriter<W>) -> io::Result<()> {
-        writer.write_u8((self.blue * 255.0) as u8)?;
-        writer.write_u8((self.green * 255.0) as u8)?;
-        writer.write_u8((self.red * 255.0) as u8)?;
+        writer.write_u8(to_u8(self.blue))?;
+        writer.write_u8(to_u8(self.green))?;
+        writer.write_u8(to_u8(self.red))?;
 
         Ok(())
     }
 }
+
+// Rounding instead of truncating keeps u8 -> f32 -> u8 lossless
+pub(crate) fn to_u8(channel: f32) -> u8 {
+    (channel * 255.0).round().clamp(0.0, 255.0) as u8
+}
+fn to_u16(channel: f32) -> u16 {
+    (channel * 65535.0).round().clamp(0.0, 65535.0) as u16
+}
+
+/// The sRGB electro-optical transfer function for a single channel in [0, 1]
+pub fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+/// Inverse of `srgb_to_linear`
+pub fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,53 @@
+use crate::io::binary_reader::BinaryReader;
+use crate::io::binary_writer::BinaryWriter;
+use glam::Quat;
+use std::io;
+use std::io::{Read, Seek, Write};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Quaternion { x, y, z, w }
+    }
+    pub fn identity() -> Self {
+        Quaternion::IDENTITY
+    }
+    pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
+        let [x, y, z, w] = reader.read_f32_array::<4>()?;
+
+        Ok(Quaternion { x, y, z, w })
+    }
+
+    pub fn write<T: Write + Seek>(&self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+        writer.write_f32_slice(&[self.x, self.y, self.z, self.w])?;
+
+        Ok(())
+    }
+}
+
+impl From<Quat> for Quaternion {
+    fn from(quaternion: Quat) -> Self {
+        Quaternion::new(quaternion.x(), quaternion.y(), quaternion.z(), quaternion.w())
+    }
+}
+
+impl From<Quaternion> for Quat {
+    fn from(quaternion: Quaternion) -> Self {
+        Quat::from_xyzw(quaternion.x, quaternion.y, quaternion.z, quaternion.w)
+    }
+}
@@ -1,9 +1,11 @@
 use crate::io::binary_reader::BinaryReader;
 use crate::io::binary_writer::BinaryWriter;
+use crate::io::world_geometry::WorldGeometryModel;
 use crate::structures::box3d::Box3D;
 use crate::structures::vector3::Vector3;
+use std::collections::HashMap;
 use std::io;
-use std::io::{Read, Seek, Write};
+use std::io::{ErrorKind, Read, Seek, Write};
 
 #[derive(Clone)]
 pub struct RenderBucketGrid {
@@ -32,6 +34,143 @@ impl RenderBucketGrid {
             buckets: Vec::new(),
         }
     }
+    /// Splits the X/Z bounds of the models into `buckets_per_side` squared buckets and puts every face
+    /// into the bucket containing its centroid. Each bucket gets its own vertices so its indices fit in 16 bits,
+    /// faces which are fully inside the bucket come first, followed by the ones sticking out of it
+    pub fn build(models: &[WorldGeometryModel], buckets_per_side: u16) -> io::Result<Self> {
+        let positions: Vec<Vector3> = models
+            .iter()
+            .flat_map(|model| model.vertices().iter())
+            .map(|vertex| vertex.position)
+            .collect();
+        let mut faces: Vec<[usize; 3]> = Vec::new();
+        let mut base_vertex = 0;
+        for model in models {
+            for face in model.indices().chunks_exact(3) {
+                faces.push([
+                    base_vertex + face[0] as usize,
+                    base_vertex + face[1] as usize,
+                    base_vertex + face[2] as usize,
+                ]);
+            }
+
+            base_vertex += model.vertices().len();
+        }
+
+        let bounds = Box3D::from_points(positions.iter().copied());
+        let side = buckets_per_side as usize;
+        if side == 0 {
+            return Ok(RenderBucketGrid { bounds, ..RenderBucketGrid::empty() });
+        }
+        let bucket_size_x = (bounds.max.x - bounds.min.x) / side as f32;
+        let bucket_size_z = (bounds.max.z - bounds.min.z) / side as f32;
+        let cell = |value: f32, min: f32, size: f32| {
+            if size > 0.0 {
+                usize::min(((value - min) / size).max(0.0) as usize, side - 1)
+            } else {
+                0
+            }
+        };
+
+        // Faces of each bucket, row major with rows going along Z like `bucket_at`
+        let mut bucket_faces: Vec<Vec<usize>> = vec![Vec::new(); side * side];
+        for (face_id, face) in faces.iter().enumerate() {
+            let centroid_x = face.iter().map(|index| positions[*index].x).sum::<f32>() / 3.0;
+            let centroid_z = face.iter().map(|index| positions[*index].z).sum::<f32>() / 3.0;
+            let column = cell(centroid_x, bounds.min.x, bucket_size_x);
+            let row = cell(centroid_z, bounds.min.z, bucket_size_z);
+
+            bucket_faces[row * side + column].push(face_id);
+        }
+
+        let mut vertices: Vec<Vector3> = Vec::new();
+        let mut indices: Vec<u16> = Vec::with_capacity(faces.len() * 3);
+        let mut buckets: Vec<Vec<RenderBucket>> = Vec::with_capacity(side);
+        for row in 0..side {
+            let mut bucket_row: Vec<RenderBucket> = Vec::with_capacity(side);
+            for column in 0..side {
+                let min_x = bounds.min.x + column as f32 * bucket_size_x;
+                let min_z = bounds.min.z + row as f32 * bucket_size_z;
+                let max_x = min_x + bucket_size_x;
+                let max_z = min_z + bucket_size_z;
+
+                let mut inside_faces: Vec<usize> = Vec::new();
+                let mut sticking_out_faces: Vec<usize> = Vec::new();
+                let mut max_stick_out_x: f32 = 0.0;
+                let mut max_stick_out_z: f32 = 0.0;
+                for face_id in &bucket_faces[row * side + column] {
+                    let mut stick_out_x: f32 = 0.0;
+                    let mut stick_out_z: f32 = 0.0;
+                    for index in &faces[*face_id] {
+                        let position = positions[*index];
+                        stick_out_x = stick_out_x.max(min_x - position.x).max(position.x - max_x);
+                        stick_out_z = stick_out_z.max(min_z - position.z).max(position.z - max_z);
+                    }
+
+                    if stick_out_x > 0.0 || stick_out_z > 0.0 {
+                        sticking_out_faces.push(*face_id);
+                        max_stick_out_x = max_stick_out_x.max(stick_out_x);
+                        max_stick_out_z = max_stick_out_z.max(stick_out_z);
+                    } else {
+                        inside_faces.push(*face_id);
+                    }
+                }
+
+                let start_index = indices.len() as u32;
+                let base_vertex = vertices.len() as u32;
+                let mut vertex_lookup: HashMap<usize, u16> = HashMap::new();
+                for face_id in inside_faces.iter().chain(sticking_out_faces.iter()) {
+                    for index in &faces[*face_id] {
+                        let bucket_index = match vertex_lookup.get(index) {
+                            Some(bucket_index) => *bucket_index,
+                            None => {
+                                let bucket_index = vertices.len() - base_vertex as usize;
+                                if bucket_index > std::u16::MAX as usize {
+                                    return Err(io::Error::new(
+                                        ErrorKind::InvalidData,
+                                        "A bucket has too many vertices for 16-bit indices, use more buckets",
+                                    ));
+                                }
+
+                                vertices.push(positions[*index]);
+                                vertex_lookup.insert(*index, bucket_index as u16);
+                                bucket_index as u16
+                            }
+                        };
+
+                        indices.push(bucket_index);
+                    }
+                }
+
+                if inside_faces.len() > std::u16::MAX as usize || sticking_out_faces.len() > std::u16::MAX as usize {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "A bucket has too many faces, use more buckets",
+                    ));
+                }
+
+                bucket_row.push(RenderBucket {
+                    max_stick_out_x,
+                    max_stick_out_z,
+                    start_index,
+                    base_vertex,
+                    inside_face_count: inside_faces.len() as u16,
+                    sticking_out_face_count: sticking_out_faces.len() as u16,
+                });
+            }
+
+            buckets.push(bucket_row);
+        }
+
+        Ok(RenderBucketGrid {
+            bounds,
+            vertices,
+            indices,
+            buckets,
+        })
+    }
+    /// The header holds the X/Z bounds, the largest stick out of any bucket and the bucket size,
+    /// the last two are recomputed from the buckets and bounds when writing
     pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         let min_x = reader.read_f32()?;
         let min_z = reader.read_f32()?;
@@ -44,18 +183,15 @@ impl RenderBucketGrid {
 
         let buckets_per_side = reader.read_u16()?;
         let unknown = reader.read_u16()?;
-        let vertex_count = reader.read_u32()?;
-        let index_count = reader.read_u32()?;
-        let mut vertices: Vec<Vector3> = Vec::with_capacity(vertex_count as usize);
-        let mut indices: Vec<u16> = Vec::with_capacity(index_count as usize);
-        let mut buckets: Vec<Vec<RenderBucket>> = Vec::with_capacity(buckets_per_side as usize);
+        let vertex_count = reader.read_count(12)?;
+        let index_count = reader.read_count(2)?;
 
-        for _ in 0..vertex_count {
-            vertices.push(Vector3::read(reader)?);
-        }
-        for _ in 0..index_count {
-            indices.push(reader.read_u16()?);
-        }
+        let vertices = reader.read_vec_of::<Vector3>(vertex_count)?;
+        let indices = reader.read_vec(index_count, |reader| reader.read_u16())?;
+
+        let bucket_count = buckets_per_side as usize * buckets_per_side as usize;
+        reader.check_count(bucket_count, 20)?;
+        let mut buckets: Vec<Vec<RenderBucket>> = Vec::with_capacity(buckets_per_side as usize);
         for _ in 0..buckets_per_side {
             let mut bucket_row: Vec<RenderBucket> = Vec::with_capacity(buckets_per_side as usize);
 
@@ -87,7 +223,7 @@ impl RenderBucketGrid {
         writer.write(bounds.min.x)?;
         writer.write(bounds.min.z)?;
         writer.write(bounds.max.x)?;
-        writer.write(bounds.max.x)?;
+        writer.write(bounds.max.z)?;
         writer.write(max_stick_out_x)?;
         writer.write(max_stick_out_z)?;
         writer.write(bucket_size_x)?;
@@ -97,13 +233,11 @@ impl RenderBucketGrid {
         writer.write(self.vertices.len() as u32)?;
         writer.write(self.indices.len() as u32)?;
 
-        for vertex in &mut self.vertices {
-            vertex.write(writer)?;
-        }
+        writer.write_slice_of(&self.vertices)?;
         for index in &self.indices {
             writer.write(*index)?;
         }
-        for bucket_row in &mut self.buckets {
+        for bucket_row in &self.buckets {
             for bucket in bucket_row {
                 bucket.write(writer)?;
             }
@@ -114,21 +248,7 @@ impl RenderBucketGrid {
 
     pub fn bounds(&mut self) -> Box3D {
         if self.bounds == Box3D::ZERO {
-            let mut min = Vector3::new(
-                self.vertices[0].x,
-                std::f32::NEG_INFINITY,
-                self.vertices[0].z,
-            );
-            let mut max = Vector3::new(self.vertices[0].x, std::f32::INFINITY, self.vertices[0].z);
-
-            for vertex in &self.vertices {
-                if min.x > vertex.x { min.x = vertex.x };
-                if min.z > vertex.z { min.z = vertex.z };
-                if max.x < vertex.x { max.x = vertex.x };
-                if max.z < vertex.z { max.z = vertex.z };
-            }
-
-            self.bounds = Box3D::new(min, max);
+            self.bounds = Box3D::from_points(self.vertices.iter().copied());
         }
 
         self.bounds
@@ -150,12 +270,53 @@ impl RenderBucketGrid {
     }
     pub fn bucket_size(&mut self) -> (f32, f32) {
         let bounds = self.bounds();
-        let length_x = f32::abs(bounds.min.x) + f32::abs(bounds.max.x);
-        let length_z = f32::abs(bounds.min.z) + f32::abs(bounds.max.z);
+        let length_x = bounds.max.x - bounds.min.x;
+        let length_z = bounds.max.z - bounds.min.z;
         let buckets_per_side = self.buckets_per_side() as f32;
+        if buckets_per_side == 0.0 {
+            return (0.0, 0.0);
+        }
 
         (length_x / buckets_per_side, length_z / buckets_per_side)
     }
+    pub fn bucket_at(&mut self, x: f32, z: f32) -> Option<&RenderBucket> {
+        let bounds = self.bounds();
+        let (bucket_size_x, bucket_size_z) = self.bucket_size();
+        let buckets_per_side = self.buckets.len();
+
+        if buckets_per_side == 0
+            || x < bounds.min.x || x > bounds.max.x
+            || z < bounds.min.z || z > bounds.max.z
+        {
+            return None;
+        }
+
+        // Points on the max edge belong to the last bucket
+        let column = usize::min(((x - bounds.min.x) / bucket_size_x) as usize, buckets_per_side - 1);
+        let row = usize::min(((z - bounds.min.z) / bucket_size_z) as usize, buckets_per_side - 1);
+
+        self.buckets.get(row)?.get(column)
+    }
+    /// Inside faces followed by the faces sticking out of the bucket, indexing into `vertices`.
+    /// Faces whose indices overflow past the bucket's base vertex are left out
+    pub fn faces_in_bucket<'a>(&'a self, bucket: &RenderBucket) -> impl Iterator<Item = [u32; 3]> + 'a {
+        let start = bucket.start_index as usize;
+        let face_count = bucket.inside_face_count as usize + bucket.sticking_out_face_count as usize;
+        let end = usize::min(start + face_count * 3, self.indices.len());
+        let base_vertex = bucket.base_vertex;
+
+        self.indices
+            .get(start..end)
+            .unwrap_or(&[])
+            .chunks_exact(3)
+            .filter_map(move |face| {
+                Some([
+                    base_vertex.checked_add(face[0] as u32)?,
+                    base_vertex.checked_add(face[1] as u32)?,
+                    base_vertex.checked_add(face[2] as u32)?,
+                ])
+            })
+    }
     pub fn buckets_per_side(&self) -> u16 { self.buckets.len() as u16 }
     pub fn vertices(&self) -> &[Vector3] { &self.vertices }
     pub fn indices(&self) -> &[u16] { &self.indices }
@@ -174,7 +335,7 @@ impl RenderBucket {
         })
     }
 
-    pub fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+    pub fn write<T: Write + Seek>(&self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
         writer.write(self.max_stick_out_x)?;
         writer.write(self.max_stick_out_z)?;
         writer.write(self.start_index)?;
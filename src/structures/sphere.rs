@@ -5,6 +5,7 @@ use std::io;
 use std::io::{Read, Seek, Write};
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sphere {
     pub center: Vector3,
     pub radius: f32,
@@ -25,6 +26,57 @@ impl Sphere {
     pub fn new(center: Vector3, radius: f32) -> Self {
         Sphere { center, radius }
     }
+    /// Approximates the smallest enclosing sphere with Ritter's algorithm, usually within a few percent.
+    /// Returns `Sphere::ZERO` if there are no points
+    pub fn from_points<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = Vector3>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let first = match points.clone().next() {
+            Some(first) => first,
+            None => return Sphere::ZERO,
+        };
+
+        let farthest_from = |origin: Vector3| {
+            points.clone().fold(origin, |farthest, point| {
+                if Vector3::distance(origin, point) > Vector3::distance(origin, farthest) {
+                    point
+                } else {
+                    farthest
+                }
+            })
+        };
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+
+        let initial = Sphere::new(Vector3::lerp(a, b, 0.5), Vector3::distance(a, b) / 2.0);
+        let mut sphere = points.clone().fold(initial, |sphere, point| sphere.merge(&Sphere::new(point, 0.0)));
+
+        // Growing the sphere accumulates rounding errors, so settle the radius exactly
+        sphere.radius = points.fold(0.0, |radius, point| f32::max(radius, Vector3::distance(sphere.center, point)));
+        sphere
+    }
+
+    /// Returns the smallest sphere enclosing both spheres
+    pub fn merge(&self, other: &Sphere) -> Sphere {
+        let distance = Vector3::distance(self.center, other.center);
+
+        if distance + other.radius <= self.radius {
+            *self
+        } else if distance + self.radius <= other.radius {
+            *other
+        } else {
+            let radius = (distance + self.radius + other.radius) / 2.0;
+            let center = Vector3::lerp(self.center, other.center, (radius - self.radius) / distance);
+
+            Sphere::new(center, radius)
+        }
+    }
+    pub fn contains(&self, point: Vector3) -> bool {
+        Vector3::distance(self.center, point) <= self.radius
+    }
     pub fn read<T: Read + Seek>(reader: &mut BinaryReader<T>) -> io::Result<Self> {
         Ok(Sphere {
             center: Vector3::read(reader)?,
@@ -32,10 +84,14 @@ impl Sphere {
         })
     }
 
-    pub fn write<T: Write + Seek>(&mut self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
+    pub fn write<T: Write + Seek>(&self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
         self.center.write(writer)?;
         writer.write(self.radius)?;
 
         Ok(())
     }
+
+    pub fn approx_eq(&self, other: &Sphere, epsilon: f32) -> bool {
+        self.center.approx_eq(&other.center, epsilon) && (self.radius - other.radius).abs() <= epsilon
+    }
 }
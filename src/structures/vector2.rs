@@ -1,9 +1,12 @@
 use crate::io::binary_reader::BinaryReader;
 use crate::io::binary_writer::BinaryWriter;
+use glam::Vec2;
 use std::io;
 use std::io::{Read, Seek, Write};
+use std::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
@@ -31,4 +34,83 @@ impl Vector2 {
 
         Ok(())
     }
+
+    /// Compares each component within `epsilon`, the derived `PartialEq` is exact
+    pub fn approx_eq(&self, other: &Vector2, epsilon: f32) -> bool {
+        (self.x - other.x).abs() <= epsilon && (self.y - other.y).abs() <= epsilon
+    }
+
+    pub fn distance(x: Vector2, y: Vector2) -> f32 {
+        f32::sqrt(f32::powi(x.x - y.x, 2) + f32::powi(x.y - y.y, 2))
+    }
+    pub fn lerp(a: Vector2, b: Vector2, t: f32) -> Vector2 {
+        a + (b - a) * t
+    }
+    pub fn min(a: Vector2, b: Vector2) -> Vector2 {
+        Vector2::new(f32::min(a.x, b.x), f32::min(a.y, b.y))
+    }
+    pub fn max(a: Vector2, b: Vector2) -> Vector2 {
+        Vector2::new(f32::max(a.x, b.x), f32::max(a.y, b.y))
+    }
+
+    pub fn length(&self) -> f32 {
+        f32::sqrt(self.x * self.x + self.y * self.y)
+    }
+    /// Zero length vectors are returned unchanged
+    pub fn normalize(&self) -> Vector2 {
+        let length = self.length();
+        if length == 0.0 {
+            *self
+        } else {
+            *self * (1.0 / length)
+        }
+    }
+    /// Component-wise multiplication
+    pub fn scale(&self, factor: Vector2) -> Vector2 {
+        Vector2::new(self.x * factor.x, self.y * factor.y)
+    }
+}
+
+impl From<Vec2> for Vector2 {
+    fn from(vector: Vec2) -> Self {
+        Vector2::new(vector.x(), vector.y())
+    }
+}
+
+impl From<Vector2> for Vec2 {
+    fn from(vector: Vector2) -> Self {
+        Vec2::new(vector.x, vector.y)
+    }
+}
+
+impl Add for Vector2 {
+    type Output = Vector2;
+
+    fn add(self, other: Vector2) -> Vector2 {
+        Vector2 { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Vector2 {
+    type Output = Vector2;
+
+    fn sub(self, other: Vector2) -> Vector2 {
+        Vector2 { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul<f32> for Vector2 {
+    type Output = Vector2;
+
+    fn mul(self, scalar: f32) -> Vector2 {
+        Vector2 { x: self.x * scalar, y: self.y * scalar }
+    }
+}
+
+impl Neg for Vector2 {
+    type Output = Vector2;
+
+    fn neg(self) -> Vector2 {
+        Vector2 { x: -self.x, y: -self.y }
+    }
 }
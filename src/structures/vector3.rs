@@ -1,9 +1,12 @@
 use crate::io::binary_reader::BinaryReader;
 use crate::io::binary_writer::BinaryWriter;
+use glam::Vec3;
 use std::io;
 use std::io::{Read, Seek, Write};
+use std::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -36,16 +39,94 @@ impl Vector3 {
     }
 
     pub fn write<T: Write + Seek>(&self, writer: &mut BinaryWriter<T>) -> io::Result<()> {
-        writer.write(self.x)?;
-        writer.write(self.y)?;
-        writer.write(self.z)?;
+        writer.write_f32_slice(&[self.x, self.y, self.z])?;
 
         Ok(())
     }
 
+    /// Compares each component within `epsilon`, the derived `PartialEq` is exact
+    pub fn approx_eq(&self, other: &Vector3, epsilon: f32) -> bool {
+        (self.x - other.x).abs() <= epsilon
+            && (self.y - other.y).abs() <= epsilon
+            && (self.z - other.z).abs() <= epsilon
+    }
+
     pub fn distance(x: Vector3, y: Vector3) -> f32 {
         f32::sqrt(
-            f32::powi(x.x - y.x, 2) - f32::powi(x.y - y.y, 2) - f32::powi(x.z - y.z, 2),
+            f32::powi(x.x - y.x, 2) + f32::powi(x.y - y.y, 2) + f32::powi(x.z - y.z, 2),
         )
     }
+    pub fn cross(a: Vector3, b: Vector3) -> Vector3 {
+        Vector3::new(a.y * b.z - a.z * b.y, a.z * b.x - a.x * b.z, a.x * b.y - a.y * b.x)
+    }
+    pub fn lerp(a: Vector3, b: Vector3, t: f32) -> Vector3 {
+        a + (b - a) * t
+    }
+    pub fn min(a: Vector3, b: Vector3) -> Vector3 {
+        Vector3::new(f32::min(a.x, b.x), f32::min(a.y, b.y), f32::min(a.z, b.z))
+    }
+    pub fn max(a: Vector3, b: Vector3) -> Vector3 {
+        Vector3::new(f32::max(a.x, b.x), f32::max(a.y, b.y), f32::max(a.z, b.z))
+    }
+
+    pub fn length(&self) -> f32 {
+        f32::sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
+    }
+    /// Zero length vectors are returned unchanged
+    pub fn normalize(&self) -> Vector3 {
+        let length = self.length();
+        if length == 0.0 {
+            *self
+        } else {
+            *self * (1.0 / length)
+        }
+    }
+    /// Component-wise multiplication
+    pub fn scale(&self, factor: Vector3) -> Vector3 {
+        Vector3::new(self.x * factor.x, self.y * factor.y, self.z * factor.z)
+    }
+}
+
+impl From<Vec3> for Vector3 {
+    fn from(vector: Vec3) -> Self {
+        Vector3::new(vector.x(), vector.y(), vector.z())
+    }
+}
+
+impl From<Vector3> for Vec3 {
+    fn from(vector: Vector3) -> Self {
+        Vec3::new(vector.x, vector.y, vector.z)
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, other: Vector3) -> Vector3 {
+        Vector3 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z }
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Vector3) -> Vector3 {
+        Vector3 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z }
+    }
+}
+
+impl Mul<f32> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, scalar: f32) -> Vector3 {
+        Vector3 { x: self.x * scalar, y: self.y * scalar, z: self.z * scalar }
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3 { x: -self.x, y: -self.y, z: -self.z }
+    }
 }
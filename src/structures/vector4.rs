@@ -1,9 +1,12 @@
 use crate::io::binary_reader::BinaryReader;
 use crate::io::binary_writer::BinaryWriter;
+use glam::Vec4;
 use std::io;
 use std::io::{Read, Seek, Write};
+use std::ops::{Add, Mul, Neg, Sub};
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector4 {
     pub x: f32,
     pub y: f32,
@@ -47,4 +50,56 @@ impl Vector4 {
 
         Ok(())
     }
+
+    /// Compares each component within `epsilon`, the derived `PartialEq` is exact
+    pub fn approx_eq(&self, other: &Vector4, epsilon: f32) -> bool {
+        (self.x - other.x).abs() <= epsilon
+            && (self.y - other.y).abs() <= epsilon
+            && (self.z - other.z).abs() <= epsilon
+            && (self.w - other.w).abs() <= epsilon
+    }
+}
+
+impl From<Vec4> for Vector4 {
+    fn from(vector: Vec4) -> Self {
+        Vector4::new(vector.x(), vector.y(), vector.z(), vector.w())
+    }
+}
+
+impl From<Vector4> for Vec4 {
+    fn from(vector: Vector4) -> Self {
+        Vec4::new(vector.x, vector.y, vector.z, vector.w)
+    }
+}
+
+impl Add for Vector4 {
+    type Output = Vector4;
+
+    fn add(self, other: Vector4) -> Vector4 {
+        Vector4 { x: self.x + other.x, y: self.y + other.y, z: self.z + other.z, w: self.w + other.w }
+    }
+}
+
+impl Sub for Vector4 {
+    type Output = Vector4;
+
+    fn sub(self, other: Vector4) -> Vector4 {
+        Vector4 { x: self.x - other.x, y: self.y - other.y, z: self.z - other.z, w: self.w - other.w }
+    }
+}
+
+impl Mul<f32> for Vector4 {
+    type Output = Vector4;
+
+    fn mul(self, scalar: f32) -> Vector4 {
+        Vector4 { x: self.x * scalar, y: self.y * scalar, z: self.z * scalar, w: self.w * scalar }
+    }
+}
+
+impl Neg for Vector4 {
+    type Output = Vector4;
+
+    fn neg(self) -> Vector4 {
+        Vector4 { x: -self.x, y: -self.y, z: -self.z, w: -self.w }
+    }
 }
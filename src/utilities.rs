@@ -1,3 +1,10 @@
 pub mod hashing;
 pub mod version;
-pub mod directx9;
\ No newline at end of file
+pub mod directx9;
+pub mod triangles;
+pub mod checksum;
+pub mod hash_table;
+pub mod uv_convention;
+pub mod convert;
+pub mod vertex_cache;
+pub mod simplify;
@@ -0,0 +1,114 @@
+const CRC32_POLYNOMIAL: u32 = 0xEDB88320;
+
+const XXH64_PRIME_1: u64 = 0x9E3779B185EBCA87;
+const XXH64_PRIME_2: u64 = 0xC2B2AE3D27D4EB4F;
+const XXH64_PRIME_3: u64 = 0x165667B19E3779F9;
+const XXH64_PRIME_4: u64 = 0x85EBCA77C2B2AE63;
+const XXH64_PRIME_5: u64 = 0x27D4EB2F165667C5;
+
+// CRC-32 (IEEE 802.3), same as zlib
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ CRC32_POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+pub fn xxhash64(data: &[u8]) -> u64 {
+    xxhash64_seeded(data, 0)
+}
+pub fn xxhash64_seeded(data: &[u8], seed: u64) -> u64 {
+    let mut chunks = data.chunks_exact(32);
+    let mut hash = if data.len() >= 32 {
+        let mut v1 = seed.wrapping_add(XXH64_PRIME_1).wrapping_add(XXH64_PRIME_2);
+        let mut v2 = seed.wrapping_add(XXH64_PRIME_2);
+        let mut v3 = seed;
+        let mut v4 = seed.wrapping_sub(XXH64_PRIME_1);
+
+        for chunk in &mut chunks {
+            v1 = xxhash64_round(v1, read_u64(&chunk[0..8]));
+            v2 = xxhash64_round(v2, read_u64(&chunk[8..16]));
+            v3 = xxhash64_round(v3, read_u64(&chunk[16..24]));
+            v4 = xxhash64_round(v4, read_u64(&chunk[24..32]));
+        }
+
+        let mut hash = v1
+            .rotate_left(1)
+            .wrapping_add(v2.rotate_left(7))
+            .wrapping_add(v3.rotate_left(12))
+            .wrapping_add(v4.rotate_left(18));
+        hash = xxhash64_merge_round(hash, v1);
+        hash = xxhash64_merge_round(hash, v2);
+        hash = xxhash64_merge_round(hash, v3);
+        xxhash64_merge_round(hash, v4)
+    } else {
+        seed.wrapping_add(XXH64_PRIME_5)
+    };
+
+    hash = hash.wrapping_add(data.len() as u64);
+
+    let mut remainder = chunks.remainder();
+    while remainder.len() >= 8 {
+        hash ^= xxhash64_round(0, read_u64(&remainder[0..8]));
+        hash = hash
+            .rotate_left(27)
+            .wrapping_mul(XXH64_PRIME_1)
+            .wrapping_add(XXH64_PRIME_4);
+        remainder = &remainder[8..];
+    }
+    if remainder.len() >= 4 {
+        hash ^= (read_u32(&remainder[0..4]) as u64).wrapping_mul(XXH64_PRIME_1);
+        hash = hash
+            .rotate_left(23)
+            .wrapping_mul(XXH64_PRIME_2)
+            .wrapping_add(XXH64_PRIME_3);
+        remainder = &remainder[4..];
+    }
+    for byte in remainder {
+        hash ^= (*byte as u64).wrapping_mul(XXH64_PRIME_5);
+        hash = hash.rotate_left(11).wrapping_mul(XXH64_PRIME_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(XXH64_PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(XXH64_PRIME_3);
+    hash ^= hash >> 32;
+
+    hash
+}
+
+fn xxhash64_round(accumulator: u64, input: u64) -> u64 {
+    accumulator
+        .wrapping_add(input.wrapping_mul(XXH64_PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(XXH64_PRIME_1)
+}
+fn xxhash64_merge_round(accumulator: u64, value: u64) -> u64 {
+    (accumulator ^ xxhash64_round(0, value))
+        .wrapping_mul(XXH64_PRIME_1)
+        .wrapping_add(XXH64_PRIME_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buffer = [0; 8];
+    buffer.copy_from_slice(bytes);
+
+    u64::from_le_bytes(buffer)
+}
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buffer = [0; 4];
+    buffer.copy_from_slice(bytes);
+
+    u32::from_le_bytes(buffer)
+}
@@ -0,0 +1,127 @@
+use crate::error::{Error, Result};
+use crate::io::bin::{BinReader, BinWriter};
+use crate::io::detect::FileKind;
+use crate::io::simple_skin::SimpleSkin;
+use crate::io::static_object::StaticObject;
+use crate::io::world_geometry::WorldGeometry;
+use crate::utilities::hash_table::HashTable;
+use std::fs;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum OutputKind {
+    SimpleSkin,
+    WorldGeometry,
+    Bin,
+    /// `BinTree::pretty_print` output, hashes are printed as hex
+    Text,
+}
+
+#[derive(Debug, Default)]
+pub struct ConversionReport {
+    pub converted: Vec<PathBuf>,
+    /// Files which weren't detected as the source kind
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+impl OutputKind {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputKind::SimpleSkin => "skn",
+            OutputKind::WorldGeometry => "wgeo",
+            OutputKind::Bin => "bin",
+            OutputKind::Text => "txt",
+        }
+    }
+    pub fn can_convert_from(self, from: FileKind) -> bool {
+        matches!(
+            (from, self),
+            (FileKind::SimpleSkin, OutputKind::SimpleSkin)
+                | (FileKind::StaticObject, OutputKind::SimpleSkin)
+                | (FileKind::WorldGeometry, OutputKind::WorldGeometry)
+                | (FileKind::Bin, OutputKind::Bin)
+                | (FileKind::Bin, OutputKind::Text)
+        )
+    }
+}
+
+/// Converts every file of kind `from` under `src` into `to`, mirroring the directory structure into `dst`.
+/// A failing file is recorded in the report and doesn't stop the conversion of the others
+pub fn convert_dir(src: &Path, dst: &Path, from: FileKind, to: OutputKind) -> Result<ConversionReport> {
+    if !to.can_convert_from(from) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Converting {:?} to {:?} isn't supported", from, to),
+        )
+        .into());
+    }
+
+    let mut files = Vec::new();
+    collect_files(src, &mut files)?;
+
+    let mut report = ConversionReport::default();
+    for file in files {
+        match FileKind::detect_file(&file) {
+            Ok(Some(kind)) if kind == from => {}
+            Ok(_) => {
+                report.skipped.push(file);
+                continue;
+            }
+            Err(error) => {
+                report.failed.push((file, error.into()));
+                continue;
+            }
+        }
+
+        let relative = file.strip_prefix(src).unwrap_or(&file);
+        let destination = dst.join(relative).with_extension(to.extension());
+        match convert_file(&file, &destination, from, to) {
+            Ok(()) => report.converted.push(file),
+            Err(error) => report.failed.push((file, error)),
+        }
+    }
+
+    Ok(report)
+}
+
+fn convert_file(src: &Path, dst: &Path, from: FileKind, to: OutputKind) -> Result<()> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match (from, to) {
+        (FileKind::SimpleSkin, OutputKind::SimpleSkin) => SimpleSkin::read_from_file(src)?.write_to_file(dst),
+        (FileKind::StaticObject, OutputKind::SimpleSkin) => {
+            StaticObject::read_scb_from_file(src)?.to_simple_skin()?.write_to_file(dst)
+        }
+        (FileKind::WorldGeometry, OutputKind::WorldGeometry) => WorldGeometry::read_from_file(src)?.write_to_file(dst),
+        (FileKind::Bin, OutputKind::Bin) => BinWriter::write_tree_file(&BinReader::read_tree_file(src)?, dst),
+        (FileKind::Bin, OutputKind::Text) => {
+            let tree = BinReader::read_tree_file(src)?;
+            Ok(fs::write(dst, tree.pretty_print(&HashTable::new()))?)
+        }
+        _ => unreachable!("checked by can_convert_from"),
+    }
+}
+
+// Sorted so the report order doesn't depend on the file system
+// Symlinked directories are skipped so a link back up the tree can't recurse forever
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (path, file_type) in entries {
+        if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if !path.is_dir() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::Path;
+
+/// Hash to name lookup built from the `<hex> <name>` dictionaries,
+/// 32-bit BIN hashes and 64-bit path hashes share the same table
+#[derive(Clone, Default, Debug)]
+pub struct HashTable {
+    names: HashMap<u64, String>,
+}
+
+impl HashTable {
+    pub fn new() -> Self {
+        HashTable {
+            names: HashMap::new(),
+        }
+    }
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        HashTable::read(BufReader::new(File::open(path)?))
+    }
+    /// Lines can separate the hash and the name with either a space or a colon
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut hash_table = HashTable::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let separator = line.find([' ', ':']).ok_or_else(|| {
+                io::Error::new(ErrorKind::InvalidData, format!("Missing separator on line {}", i + 1))
+            })?;
+            let (hash, name) = line.split_at(separator);
+            let hash = hash.trim_start_matches("0x");
+            let hash = u64::from_str_radix(hash, 16).map_err(|_| {
+                io::Error::new(ErrorKind::InvalidData, format!("Invalid hash on line {}", i + 1))
+            })?;
+
+            hash_table.insert(hash, name[1..].trim().to_string());
+        }
+
+        Ok(hash_table)
+    }
+
+    pub fn insert(&mut self, hash: u64, name: String) {
+        self.names.insert(hash, name);
+    }
+    /// Names from `other` replace existing ones with the same hash
+    pub fn merge(&mut self, other: HashTable) {
+        self.names.extend(other.names);
+    }
+    pub fn get(&self, hash: u64) -> Option<&str> {
+        self.names.get(&hash).map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize { self.names.len() }
+    pub fn is_empty(&self) -> bool { self.names.is_empty() }
+}
@@ -15,6 +15,17 @@ pub fn hash_string_lc<H: Hasher + Default>(string: &str) -> u64 {
     hasher.finish()
 }
 
+/// 32-bit FNV-1a of the lowercased string, used for BIN names and paths
+pub fn fnv1a_lc(string: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for c in string.bytes() {
+        hash ^= c.to_ascii_lowercase() as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+
+    hash
+}
+
 impl<H: Hasher> StringHasher for H {
     fn hash_string_lc(&mut self, string: &str) -> u64 {
         for c in string.chars() {
@@ -0,0 +1,367 @@
+// Garland and Heckbert's "Surface Simplification Using Quadric Error Metrics"
+// https://www.cs.cmu.edu/~garland/Papers/quadrics.pdf
+// Edges are collapsed either into one of their vertices or into the point along them with the least error
+
+use crate::structures::vector3::Vector3;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::convert::TryFrom;
+use std::fmt::Debug;
+
+/// Sum of squared distances to a set of planes, the upper triangle of a symmetric 4x4 matrix
+#[derive(Copy, Clone, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_triangle(a: Vector3, b: Vector3, c: Vector3) -> Self {
+        let normal = Vector3::cross(b - a, c - a);
+        let length = normal.length() as f64;
+        if length == 0.0 {
+            return Quadric::default();
+        }
+
+        // Weighted by area so small triangles don't pull as hard as big ones
+        let area = length / 2.0;
+        let [x, y, z] = [normal.x as f64 / length, normal.y as f64 / length, normal.z as f64 / length];
+        let d = -(x * a.x as f64 + y * a.y as f64 + z * a.z as f64);
+
+        Quadric([x * x, x * y, x * z, x * d, y * y, y * z, y * d, z * z, z * d, d * d]).scaled(area)
+    }
+
+    fn scaled(mut self, factor: f64) -> Self {
+        for value in &mut self.0 {
+            *value *= factor;
+        }
+
+        self
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += other;
+        }
+    }
+
+    fn error(&self, point: Vector3) -> f64 {
+        let point = [point.x as f64, point.y as f64, point.z as f64, 1.0];
+
+        self.product(point, point)
+    }
+
+    /// `u^T Q v` with homogeneous vectors
+    fn product(&self, u: [f64; 4], v: [f64; 4]) -> f64 {
+        let q = &self.0;
+        let matrix = [
+            [q[0], q[1], q[2], q[3]],
+            [q[1], q[4], q[5], q[6]],
+            [q[2], q[5], q[7], q[8]],
+            [q[3], q[6], q[8], q[9]],
+        ];
+
+        let mut product = 0.0;
+        for row in 0..4 {
+            for column in 0..4 {
+                product += u[row] * matrix[row][column] * v[column];
+            }
+        }
+
+        product
+    }
+
+    /// The position between `from` (0.0) and `to` (1.0) with the least error, the middle if the error is the same everywhere
+    fn minimum_along(&self, from: Vector3, to: Vector3) -> f32 {
+        let direction = to - from;
+        let from = [from.x as f64, from.y as f64, from.z as f64, 1.0];
+        let direction = [direction.x as f64, direction.y as f64, direction.z as f64, 0.0];
+
+        let curvature = self.product(direction, direction);
+        if curvature <= 1e-12 {
+            return 0.5;
+        }
+
+        (-self.product(direction, from) / curvature).clamp(0.0, 1.0) as f32
+    }
+}
+
+/// Removing `from` and keeping `to`, ordered by the lowest cost first
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Mesh {
+    positions: Vec<Vector3>,
+    interpolate: bool,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    alive_count: usize,
+    vertex_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    removed: Vec<bool>,
+    locked: Vec<bool>,
+}
+
+/// Collapses edges in order of the least added error until at most `target_triangle_count` triangles are left
+/// or no edge can be collapsed anymore. Vertices on open or non-manifold edges are kept in place, which includes
+/// vertices split along UV seams, so the outline and the seams don't open up.
+/// Collapses which would flip a triangle are skipped.
+/// Edges collapse into one of their vertices so the attributes stay as they are,
+/// degenerate triangles are dropped and the returned indices still point into `positions`.
+/// Panics if an index is out of the vertex range
+pub fn simplify<I>(positions: &[Vector3], indices: &[I], target_triangle_count: usize) -> Vec<I>
+where
+    I: Copy + Into<u32> + TryFrom<u32>,
+    I::Error: Debug,
+{
+    let mut mesh = Mesh::new(positions.to_vec(), false, indices);
+    mesh.collapse_until(target_triangle_count, |_, _, _| {});
+
+    mesh.indices()
+}
+
+/// Like `simplify`, but the vertex left by a collapse is moved to the point along the edge with the least error.
+/// `lerp` interpolates all of the vertex attributes, the result replaces the kept vertex in `vertices`.
+/// Returns the number of triangles left, which can be more than the target if open edges or seams block the collapses
+pub fn simplify_vertices<V, I>(
+    vertices: &mut [V],
+    indices: &mut Vec<I>,
+    target_triangle_count: usize,
+    position: impl Fn(&V) -> Vector3,
+    lerp: impl Fn(&V, &V, f32) -> V,
+) -> usize
+where
+    I: Copy + Into<u32> + TryFrom<u32>,
+    I::Error: Debug,
+{
+    let mut mesh = Mesh::new(vertices.iter().map(&position).collect(), true, indices);
+    mesh.collapse_until(target_triangle_count, |from, to, t| {
+        vertices[to as usize] = lerp(&vertices[from as usize], &vertices[to as usize], t);
+    });
+
+    *indices = mesh.indices();
+    mesh.alive_count
+}
+
+impl Mesh {
+    fn collapse_until(&mut self, target_triangle_count: usize, mut on_collapse: impl FnMut(u32, u32, f32)) {
+        let mut collapses = BinaryHeap::new();
+        for vertex in 0..self.positions.len() as u32 {
+            self.push_collapses(vertex, &mut collapses);
+        }
+
+        while self.alive_count > target_triangle_count {
+            let collapse = match collapses.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            if self.removed[collapse.from as usize] || self.removed[collapse.to as usize] {
+                continue;
+            }
+            if !self.neighbours(collapse.from).contains(&collapse.to) {
+                continue;
+            }
+
+            // Moved vertices make entries stale, those which got more expensive are pushed back with their current cost
+            let (cost, t) = self.cost(collapse.from, collapse.to);
+            if cost > collapse.cost + collapse.cost.abs() * 1e-9 {
+                collapses.push(Collapse { cost, ..collapse });
+                continue;
+            }
+            if !self.can_collapse(collapse.from, collapse.to) || self.flips(collapse.from, collapse.to, t) {
+                continue;
+            }
+
+            on_collapse(collapse.from, collapse.to, t);
+            self.collapse(collapse.from, collapse.to, t);
+            self.push_collapses(collapse.to, &mut collapses);
+            for neighbour in self.neighbours(collapse.to) {
+                self.push_collapses(neighbour, &mut collapses);
+            }
+        }
+    }
+
+    fn indices<I>(&self) -> Vec<I>
+    where
+        I: TryFrom<u32>,
+        I::Error: Debug,
+    {
+        let mut indices = Vec::with_capacity(self.alive_count * 3);
+        for (triangle, alive) in self.triangles.iter().zip(self.alive.iter()) {
+            if *alive {
+                indices.extend(triangle.iter().map(|index| I::try_from(*index).expect("Indices come from the input")));
+            }
+        }
+
+        indices
+    }
+
+    fn new<I: Copy + Into<u32>>(positions: Vec<Vector3>, interpolate: bool, indices: &[I]) -> Self {
+        let triangles: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0].into(), triangle[1].into(), triangle[2].into()])
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .collect();
+
+        let mut vertex_triangles = vec![Vec::new(); positions.len()];
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut edge_uses: HashMap<(u32, u32), u32> = HashMap::new();
+        for (triangle_index, triangle) in triangles.iter().enumerate() {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let quadric = Quadric::from_triangle(positions[a], positions[b], positions[c]);
+
+            for (i, vertex) in triangle.iter().enumerate() {
+                vertex_triangles[*vertex as usize].push(triangle_index);
+                quadrics[*vertex as usize].add(&quadric);
+
+                let next = triangle[(i + 1) % 3];
+                *edge_uses.entry((u32::min(*vertex, next), u32::max(*vertex, next))).or_insert(0) += 1;
+            }
+        }
+
+        let mut locked = vec![false; positions.len()];
+        for ((a, b), uses) in edge_uses {
+            if uses != 2 {
+                locked[a as usize] = true;
+                locked[b as usize] = true;
+            }
+        }
+
+        Mesh {
+            removed: vec![false; positions.len()],
+            positions,
+            interpolate,
+            alive: vec![true; triangles.len()],
+            alive_count: triangles.len(),
+            triangles,
+            vertex_triangles,
+            quadrics,
+            locked,
+        }
+    }
+
+    fn neighbours(&self, vertex: u32) -> Vec<u32> {
+        let mut neighbours: Vec<u32> = self.vertex_triangles[vertex as usize]
+            .iter()
+            .filter(|triangle| self.alive[**triangle])
+            .flat_map(|triangle| self.triangles[*triangle].iter().copied())
+            .filter(|neighbour| *neighbour != vertex)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+
+        neighbours
+    }
+
+    /// The error of the collapse and where along the edge the kept vertex ends up, locked vertices can't move
+    fn cost(&self, from: u32, to: u32) -> (f64, f32) {
+        let mut quadric = self.quadrics[from as usize];
+        quadric.add(&self.quadrics[to as usize]);
+
+        let (from_position, to_position) = (self.positions[from as usize], self.positions[to as usize]);
+        let t = if self.interpolate && !self.locked[to as usize] {
+            quadric.minimum_along(from_position, to_position)
+        } else {
+            1.0
+        };
+
+        (quadric.error(Vector3::lerp(from_position, to_position, t)), t)
+    }
+
+    fn push_collapses(&self, vertex: u32, collapses: &mut BinaryHeap<Collapse>) {
+        for neighbour in self.neighbours(vertex) {
+            for (from, to) in [(vertex, neighbour), (neighbour, vertex)].iter().copied() {
+                if !self.locked[from as usize] {
+                    let (cost, _) = self.cost(from, to);
+                    collapses.push(Collapse { cost, from, to });
+                }
+            }
+        }
+    }
+
+    /// Whether a triangle kept by the collapse would face the other way or end up with no area
+    fn flips(&self, from: u32, to: u32, t: f32) -> bool {
+        let collapsed = Vector3::lerp(self.positions[from as usize], self.positions[to as usize], t);
+
+        [from, to].iter().any(|vertex| {
+            self.vertex_triangles[*vertex as usize].iter().any(|triangle_index| {
+                let triangle = self.triangles[*triangle_index];
+                if !self.alive[*triangle_index] || (triangle.contains(&from) && triangle.contains(&to)) {
+                    return false;
+                }
+
+                let corners = [0, 1, 2].map(|i| self.positions[triangle[i] as usize]);
+                let moved = [0, 1, 2].map(|i| if triangle[i] == *vertex { collapsed } else { corners[i] });
+                let before = Vector3::cross(corners[1] - corners[0], corners[2] - corners[0]);
+                let after = Vector3::cross(moved[1] - moved[0], moved[2] - moved[0]);
+
+                let dot = before.x * after.x + before.y * after.y + before.z * after.z;
+                dot <= 1e-6 * before.length() * after.length() || after.length() <= 1e-12
+            })
+        })
+    }
+
+    /// The link condition, the edge's own triangles have to be the only ones the two vertices share
+    /// or the collapse pinches the surface into a non-manifold one
+    fn can_collapse(&self, from: u32, to: u32) -> bool {
+        let from_neighbours = self.neighbours(from);
+        let shared_neighbours = self
+            .neighbours(to)
+            .iter()
+            .filter(|neighbour| from_neighbours.binary_search(neighbour).is_ok())
+            .count();
+        let shared_triangles = self.vertex_triangles[from as usize]
+            .iter()
+            .filter(|triangle| self.alive[**triangle] && self.triangles[**triangle].contains(&to))
+            .count();
+
+        shared_neighbours == shared_triangles
+    }
+
+    fn collapse(&mut self, from: u32, to: u32, t: f32) {
+        for triangle in std::mem::take(&mut self.vertex_triangles[from as usize]) {
+            if !self.alive[triangle] {
+                continue;
+            }
+
+            if self.triangles[triangle].contains(&to) {
+                self.alive[triangle] = false;
+                self.alive_count -= 1;
+            } else {
+                for vertex in &mut self.triangles[triangle] {
+                    if *vertex == from {
+                        *vertex = to;
+                    }
+                }
+                self.vertex_triangles[to as usize].push(triangle);
+            }
+        }
+
+        let alive = &self.alive;
+        self.vertex_triangles[to as usize].retain(|triangle| alive[*triangle]);
+        let from_quadric = self.quadrics[from as usize];
+        self.quadrics[to as usize].add(&from_quadric);
+        self.removed[from as usize] = true;
+        self.positions[to as usize] = Vector3::lerp(self.positions[from as usize], self.positions[to as usize], t);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::structures::vector3::Vector3;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::slice::ChunksExact;
+
+// Relative to the edge lengths so the check doesn't depend on the scale of the mesh
+const COLINEAR_EPSILON: f32 = 1e-6;
+
+pub struct Triangles<'a, V, I> {
+    vertices: &'a [V],
+    indices: ChunksExact<'a, I>,
+}
+
+impl<'a, V, I: Copy + Into<u32>> Triangles<'a, V, I> {
+    pub fn new(vertices: &'a [V], indices: &'a [I]) -> io::Result<Self> {
+        if indices.len() % 3 != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Index count is not a multiple of 3",
+            ));
+        }
+        if indices.iter().any(|index| (*index).into() as usize >= vertices.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Index is out of the vertex range",
+            ));
+        }
+
+        Ok(Triangles {
+            vertices,
+            indices: indices.chunks_exact(3),
+        })
+    }
+}
+
+impl<'a, V, I: Copy + Into<u32>> Iterator for Triangles<'a, V, I> {
+    type Item = [&'a V; 3];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let triangle = self.indices.next()?;
+
+        Some([
+            &self.vertices[triangle[0].into() as usize],
+            &self.vertices[triangle[1].into() as usize],
+            &self.vertices[triangle[2].into() as usize],
+        ])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<'a, V, I: Copy + Into<u32>> ExactSizeIterator for Triangles<'a, V, I> {}
+
+/// Removes triangles which repeat an index, point outside of `vertices` or whose positions are colinear within epsilon,
+/// returns the number of triangles removed
+pub fn remove_degenerate_triangles<V, I: Copy + Into<u32>>(
+    vertices: &[V],
+    indices: &mut Vec<I>,
+    position: impl Fn(&V) -> Vector3,
+) -> usize {
+    let triangle_count = indices.len() / 3;
+
+    let mut kept = 0;
+    for triangle in 0..triangle_count {
+        let [a, b, c] = [indices[triangle * 3], indices[triangle * 3 + 1], indices[triangle * 3 + 2]];
+        let [a_index, b_index, c_index] = [a.into(), b.into(), c.into()];
+        if a_index == b_index || b_index == c_index || a_index == c_index {
+            continue;
+        }
+
+        let positions = (
+            vertices.get(a_index as usize),
+            vertices.get(b_index as usize),
+            vertices.get(c_index as usize),
+        );
+        let (a_position, b_position, c_position) = match positions {
+            (Some(a), Some(b), Some(c)) => (position(a), position(b), position(c)),
+            _ => continue,
+        };
+        let ab = b_position - a_position;
+        let ac = c_position - a_position;
+        if Vector3::cross(ab, ac).length() <= COLINEAR_EPSILON * ab.length() * ac.length() {
+            continue;
+        }
+
+        indices[kept * 3] = a;
+        indices[kept * 3 + 1] = b;
+        indices[kept * 3 + 2] = c;
+        kept += 1;
+    }
+
+    indices.truncate(kept * 3);
+    triangle_count - kept
+}
+
+/// Removes vertices which no triangle uses and remaps the indices, returns the number of vertices removed.
+/// Panics if an index is out of the vertex range
+pub fn remove_unused_vertices<V, I>(vertices: &mut Vec<V>, indices: &mut [I]) -> usize
+where
+    I: Copy + Into<u32> + TryFrom<u32>,
+    I::Error: Debug,
+{
+    let mut remap: Vec<Option<u32>> = vec![None; vertices.len()];
+    for index in indices.iter() {
+        remap[(*index).into() as usize] = Some(0);
+    }
+
+    for (next, new_index) in remap.iter_mut().flatten().enumerate() {
+        *new_index = next as u32;
+    }
+
+    let mut vertex_index = 0;
+    vertices.retain(|_| {
+        vertex_index += 1;
+        remap[vertex_index - 1].is_some()
+    });
+    for index in indices.iter_mut() {
+        let new_index = remap[(*index).into() as usize].unwrap();
+        *index = I::try_from(new_index).expect("Remapped indices only get smaller");
+    }
+
+    remap.len() - vertices.len()
+}
+
+/// Reverses the winding of every triangle
+pub fn flip_winding<I>(indices: &mut [I]) {
+    for triangle in indices.chunks_exact_mut(3) {
+        triangle.swap(1, 2);
+    }
+}
+
+/// Flips the triangles whose face normal points away from the direction `reference` gives for them,
+/// returns the number of triangles flipped. Triangles without an area or a reference direction are left as they are
+pub fn fix_winding<V, I: Copy + Into<u32>>(
+    vertices: &[V],
+    indices: &mut [I],
+    position: impl Fn(&V) -> Vector3,
+    reference: impl Fn([&V; 3]) -> Vector3,
+) -> usize {
+    let mut flipped = 0;
+    for triangle in indices.chunks_exact_mut(3) {
+        let corners = (
+            vertices.get(triangle[0].into() as usize),
+            vertices.get(triangle[1].into() as usize),
+            vertices.get(triangle[2].into() as usize),
+        );
+        let corners = match corners {
+            (Some(a), Some(b), Some(c)) => [a, b, c],
+            _ => continue,
+        };
+
+        let a = position(corners[0]);
+        let face_normal = Vector3::cross(position(corners[1]) - a, position(corners[2]) - a);
+        let reference = reference(corners);
+        let agreement = face_normal.x * reference.x + face_normal.y * reference.y + face_normal.z * reference.z;
+        if agreement < 0.0 {
+            triangle.swap(1, 2);
+            flipped += 1;
+        }
+    }
+
+    flipped
+}
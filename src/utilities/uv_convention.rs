@@ -0,0 +1,21 @@
+use crate::structures::vector2::Vector2;
+
+/// Where a UV of (0, 0) lies on the texture. League stores UVs with the origin at the top left,
+/// exporters should take a convention and go through `convert` instead of flipping by hand
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum UvConvention {
+    /// League and DirectX, also used by glTF
+    TopLeft,
+    /// OpenGL and most DCC tools, also used by OBJ
+    BottomLeft,
+}
+
+impl UvConvention {
+    /// Converts a UV as stored by League into this convention
+    pub fn convert(self, uv: Vector2) -> Vector2 {
+        match self {
+            UvConvention::TopLeft => uv,
+            UvConvention::BottomLeft => Vector2::new(uv.x, 1.0 - uv.y),
+        }
+    }
+}
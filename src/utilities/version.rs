@@ -1,4 +1,6 @@
 
+use std::fmt;
+
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub struct Version {
     pub major: u8,
@@ -12,4 +14,10 @@ impl Version {
             minor
         }
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
@@ -0,0 +1,172 @@
+// Tom Forsyth's "Linear-Speed Vertex Cache Optimisation"
+// https://tomforsyth1000.github.io/papers/fast_vert_cache_opt.html
+
+const CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// Average cache miss ratio (transformed vertices per triangle) before and after optimizing,
+/// 0.5 is about the best a regular grid can get and 3.0 means the cache is never hit
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CacheOptimization {
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+}
+
+struct VertexData {
+    score: f32,
+    cache_position: Option<usize>,
+    // Triangles using this vertex which aren't emitted yet
+    triangles: Vec<usize>,
+}
+
+/// Reorders the triangles for a better post-transform cache hit rate.
+/// Triangles are kept as they are, including their winding, only their order changes.
+/// Meshes from the game are usually optimized already, the original order is kept if the new one isn't better
+pub fn optimize_index_order<I: Copy + Into<u32>>(indices: &mut [I]) -> CacheOptimization {
+    let acmr_before = average_cache_miss_ratio(indices);
+
+    let triangles: Vec<[I; 3]> = indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+    let mut optimized: Vec<I> = Vec::with_capacity(indices.len());
+    for triangle in triangle_order(&triangles) {
+        optimized.extend_from_slice(&triangles[triangle]);
+    }
+
+    let acmr_after = average_cache_miss_ratio(&optimized);
+    if acmr_after < acmr_before {
+        indices[..optimized.len()].copy_from_slice(&optimized);
+
+        CacheOptimization { acmr_before, acmr_after }
+    } else {
+        CacheOptimization { acmr_before, acmr_after: acmr_before }
+    }
+}
+
+/// Simulates a FIFO cache of 32 vertices and returns the misses per triangle
+pub fn average_cache_miss_ratio<I: Copy + Into<u32>>(indices: &[I]) -> f32 {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return 0.0;
+    }
+
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE);
+    let mut next = 0;
+    let mut misses = 0;
+    for index in &indices[..triangle_count * 3] {
+        let index = (*index).into();
+        if !cache.contains(&index) {
+            misses += 1;
+            if cache.len() < CACHE_SIZE {
+                cache.push(index);
+            } else {
+                cache[next] = index;
+                next = (next + 1) % CACHE_SIZE;
+            }
+        }
+    }
+
+    misses as f32 / triangle_count as f32
+}
+
+fn triangle_order<I: Copy + Into<u32>>(triangles: &[[I; 3]]) -> Vec<usize> {
+    let vertex_count = triangles
+        .iter()
+        .flat_map(|triangle| triangle.iter())
+        .map(|index| (*index).into() as usize + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut vertices: Vec<VertexData> = (0..vertex_count)
+        .map(|_| VertexData { score: 0.0, cache_position: None, triangles: Vec::new() })
+        .collect();
+    for (triangle_id, triangle) in triangles.iter().enumerate() {
+        for index in triangle {
+            vertices[(*index).into() as usize].triangles.push(triangle_id);
+        }
+    }
+    for vertex in &mut vertices {
+        vertex.score = vertex_score(vertex);
+    }
+
+    let triangle_vertices = |triangle: usize| triangles[triangle].iter().map(|index| (*index).into() as usize);
+    let mut triangle_scores: Vec<f32> = (0..triangles.len())
+        .map(|triangle| triangle_vertices(triangle).map(|vertex| vertices[vertex].score).sum())
+        .collect();
+    let mut emitted = vec![false; triangles.len()];
+
+    let mut order = Vec::with_capacity(triangles.len());
+    let mut cache: Vec<usize> = Vec::with_capacity(CACHE_SIZE + 3);
+    // Once no cached vertex has triangles left, continue from the first triangle which isn't emitted yet
+    let mut scan_start = 0;
+    while order.len() < triangles.len() {
+        let best = cache
+            .iter()
+            .flat_map(|vertex| vertices[*vertex].triangles.iter().copied())
+            .max_by(|a, b| triangle_scores[*a].partial_cmp(&triangle_scores[*b]).unwrap());
+        let best = match best {
+            Some(best) => best,
+            None => {
+                while emitted[scan_start] {
+                    scan_start += 1;
+                }
+                scan_start
+            }
+        };
+
+        order.push(best);
+        emitted[best] = true;
+
+        // Move the triangle's vertices to the front of the cache, what falls off the end gets evicted
+        let mut new_cache: Vec<usize> = Vec::with_capacity(CACHE_SIZE + 3);
+        for vertex in triangle_vertices(best).chain(cache.iter().copied()) {
+            if !new_cache.contains(&vertex) {
+                new_cache.push(vertex);
+            }
+        }
+        for vertex in triangle_vertices(best) {
+            vertices[vertex].triangles.retain(|triangle| *triangle != best);
+        }
+
+        for (position, vertex) in new_cache.iter().enumerate() {
+            let vertex = &mut vertices[*vertex];
+            vertex.cache_position = if position < CACHE_SIZE { Some(position) } else { None };
+        }
+        new_cache.truncate(CACHE_SIZE + 3);
+
+        for vertex in &new_cache {
+            let data = &mut vertices[*vertex];
+            let score = vertex_score(data);
+            let delta = score - data.score;
+            data.score = score;
+
+            for triangle in &data.triangles {
+                triangle_scores[*triangle] += delta;
+            }
+        }
+
+        new_cache.truncate(CACHE_SIZE);
+        cache = new_cache;
+    }
+
+    order
+}
+
+fn vertex_score(vertex: &VertexData) -> f32 {
+    if vertex.triangles.is_empty() {
+        return -1.0;
+    }
+
+    let cache_score = match vertex.cache_position {
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+    let valence_boost = VALENCE_BOOST_SCALE * (vertex.triangles.len() as f32).powf(-VALENCE_BOOST_POWER);
+
+    cache_score + valence_boost
+}